@@ -0,0 +1,46 @@
+-- One conversation per pair of users who have messaged each other.
+CREATE TABLE IF NOT EXISTS conversations (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    -- "<lower user id>:<higher user id>", so each pair has exactly one
+    dm_key TEXT NOT NULL UNIQUE,
+    last_message_id UUID REFERENCES messages(id) ON DELETE SET NULL,
+    last_message_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS conversation_members (
+    conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    unread_count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (conversation_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_conversation_members_user ON conversation_members(user_id);
+CREATE INDEX IF NOT EXISTS idx_conversations_last_message_at ON conversations(last_message_at DESC);
+
+-- Backfill one conversation, with both members, for every existing DM pair.
+INSERT INTO conversations (dm_key, created_at, last_message_at)
+SELECT LEAST(sender_id, recipient_id)::text || ':' || GREATEST(sender_id, recipient_id)::text,
+       MIN(created_at),
+       MAX(created_at)
+FROM messages
+GROUP BY LEAST(sender_id, recipient_id), GREATEST(sender_id, recipient_id)
+ON CONFLICT (dm_key) DO NOTHING;
+
+UPDATE conversations c
+SET last_message_id = (
+    SELECT id FROM messages
+    WHERE LEAST(sender_id, recipient_id)::text || ':' || GREATEST(sender_id, recipient_id)::text = c.dm_key
+    ORDER BY created_at DESC
+    LIMIT 1
+);
+
+INSERT INTO conversation_members (conversation_id, user_id, joined_at)
+SELECT DISTINCT c.id, p.user_id, c.created_at
+FROM messages m
+JOIN conversations c
+  ON c.dm_key = LEAST(m.sender_id, m.recipient_id)::text || ':' || GREATEST(m.sender_id, m.recipient_id)::text
+CROSS JOIN LATERAL (VALUES (m.sender_id), (m.recipient_id)) AS p(user_id)
+ON CONFLICT DO NOTHING;
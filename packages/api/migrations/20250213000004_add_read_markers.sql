@@ -0,0 +1,14 @@
+ALTER TABLE conversation_members
+    ADD COLUMN IF NOT EXISTS last_read_message_id UUID REFERENCES messages(id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS last_read_message_at TIMESTAMPTZ;
+
+-- Members with nothing unread start out read up to the last message.
+UPDATE conversation_members cm
+SET last_read_message_id = c.last_message_id, last_read_message_at = c.last_message_at
+FROM conversations c
+WHERE c.id = cm.conversation_id AND cm.unread_count = 0;
+
+-- Unread counts are now computed from the read marker.
+ALTER TABLE conversation_members DROP COLUMN IF EXISTS unread_count;
+
+CREATE INDEX IF NOT EXISTS idx_messages_pair_created_at ON messages(sender_id, recipient_id, created_at);
@@ -0,0 +1,27 @@
+ALTER TABLE conversations
+    ADD COLUMN IF NOT EXISTS kind VARCHAR(16) NOT NULL DEFAULT 'direct' CHECK (kind IN ('direct', 'group')),
+    ADD COLUMN IF NOT EXISTS name VARCHAR(100),
+    ADD COLUMN IF NOT EXISTS avatar_url TEXT,
+    ADD COLUMN IF NOT EXISTS created_by UUID REFERENCES users(id) ON DELETE SET NULL;
+-- Every existing conversation is direct; new ones must say which they are
+ALTER TABLE conversations ALTER COLUMN kind DROP DEFAULT;
+-- Only direct conversations have a dm_key
+ALTER TABLE conversations ALTER COLUMN dm_key DROP NOT NULL;
+
+ALTER TABLE conversation_members
+    ADD COLUMN IF NOT EXISTS role VARCHAR(16) NOT NULL DEFAULT 'member' CHECK (role IN ('owner', 'admin', 'member'));
+
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS conversation_id UUID REFERENCES conversations(id) ON DELETE CASCADE;
+
+UPDATE messages m
+SET conversation_id = c.id
+FROM conversations c
+WHERE c.dm_key = LEAST(m.sender_id, m.recipient_id)::text || ':' || GREATEST(m.sender_id, m.recipient_id)::text;
+
+ALTER TABLE messages ALTER COLUMN conversation_id SET NOT NULL;
+-- Only direct messages have a single recipient
+ALTER TABLE messages ALTER COLUMN recipient_id DROP NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_messages_conversation_created_at ON messages(conversation_id, created_at);
+
+DROP INDEX IF EXISTS idx_messages_pair_created_at;
@@ -0,0 +1,43 @@
+CREATE TABLE IF NOT EXISTS reigns (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name VARCHAR(100) NOT NULL,
+    icon_url TEXT,
+    owner_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS reign_members (
+    reign_id UUID NOT NULL REFERENCES reigns(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR(16) NOT NULL DEFAULT 'member' CHECK (role IN ('owner', 'admin', 'member')),
+    joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (reign_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_reign_members_user ON reign_members(user_id);
+
+CREATE TABLE IF NOT EXISTS channel_categories (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    reign_id UUID NOT NULL REFERENCES reigns(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_channel_categories_reign ON channel_categories(reign_id);
+
+-- Text channels are conversations owned by a Reign. Every Reign member is also a
+-- member of each of its channels, so messages, read markers and fan-out work unchanged.
+ALTER TABLE conversations DROP CONSTRAINT IF EXISTS conversations_kind_check;
+ALTER TABLE conversations ADD CONSTRAINT conversations_kind_check CHECK (kind IN ('direct', 'group', 'channel'));
+
+ALTER TABLE conversations ADD COLUMN IF NOT EXISTS reign_id UUID REFERENCES reigns(id) ON DELETE CASCADE;
+ALTER TABLE conversations ADD COLUMN IF NOT EXISTS category_id UUID REFERENCES channel_categories(id) ON DELETE SET NULL;
+ALTER TABLE conversations ADD COLUMN IF NOT EXISTS topic VARCHAR(1024);
+ALTER TABLE conversations ADD COLUMN IF NOT EXISTS position INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE conversations ADD CONSTRAINT conversations_channel_reign_check
+    CHECK ((kind = 'channel') = (reign_id IS NOT NULL));
+
+CREATE INDEX IF NOT EXISTS idx_conversations_reign ON conversations(reign_id) WHERE reign_id IS NOT NULL;
@@ -0,0 +1,63 @@
+-- Permission bits match `api::permissions::Permissions`
+CREATE TABLE IF NOT EXISTS reign_roles (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    reign_id UUID NOT NULL REFERENCES reigns(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    permissions BIGINT NOT NULL DEFAULT 0,
+    -- Higher positions outrank lower ones; @everyone is always 0
+    position INTEGER NOT NULL DEFAULT 0,
+    -- The implicit @everyone role every member has
+    is_default BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_reign_roles_default ON reign_roles(reign_id) WHERE is_default;
+
+CREATE TABLE IF NOT EXISTS reign_member_roles (
+    reign_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    role_id UUID NOT NULL REFERENCES reign_roles(id) ON DELETE CASCADE,
+    PRIMARY KEY (user_id, role_id),
+    FOREIGN KEY (reign_id, user_id) REFERENCES reign_members(reign_id, user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_reign_member_roles_reign ON reign_member_roles(reign_id, user_id);
+
+CREATE TABLE IF NOT EXISTS channel_overwrites (
+    channel_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    target_type VARCHAR(8) NOT NULL CHECK (target_type IN ('role', 'member')),
+    -- A reign_roles id or a users id, depending on target_type
+    target_id UUID NOT NULL,
+    allow BIGINT NOT NULL DEFAULT 0,
+    deny BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (channel_id, target_type, target_id)
+);
+
+CREATE TABLE IF NOT EXISTS reign_bans (
+    reign_id UUID NOT NULL REFERENCES reigns(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    banned_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    reason VARCHAR(512),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (reign_id, user_id)
+);
+
+-- @everyone for existing Reigns: view, send and mention everyone (1 | 2 | 64)
+INSERT INTO reign_roles (reign_id, name, permissions, is_default)
+SELECT id, '@everyone', 67, TRUE FROM reigns
+ON CONFLICT DO NOTHING;
+
+-- Former admins get an Administrator role (bit 128)
+INSERT INTO reign_roles (reign_id, name, permissions, position)
+SELECT DISTINCT reign_id, 'Admin', 128, 1 FROM reign_members WHERE role = 'admin';
+
+INSERT INTO reign_member_roles (reign_id, user_id, role_id)
+SELECT rm.reign_id, rm.user_id, rr.id
+FROM reign_members rm
+JOIN reign_roles rr ON rr.reign_id = rm.reign_id AND rr.name = 'Admin' AND NOT rr.is_default
+WHERE rm.role = 'admin';
+
+-- Ownership lives on reigns.owner_id; everything else comes from roles
+UPDATE reign_members SET role = 'member' WHERE role = 'admin';
+ALTER TABLE reign_members DROP CONSTRAINT IF EXISTS reign_members_role_check;
+ALTER TABLE reign_members ADD CONSTRAINT reign_members_role_check CHECK (role IN ('owner', 'member'));
@@ -0,0 +1,17 @@
+-- Shareable invite codes. A group invite names the group; a Reign invite names
+-- the Reign; a channel invite names both the Reign and the channel it lands in.
+CREATE TABLE IF NOT EXISTS invites (
+    code VARCHAR(16) PRIMARY KEY,
+    created_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    conversation_id UUID REFERENCES conversations(id) ON DELETE CASCADE,
+    reign_id UUID REFERENCES reigns(id) ON DELETE CASCADE,
+    max_uses INTEGER CHECK (max_uses > 0),
+    uses INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT invites_target_check CHECK (conversation_id IS NOT NULL OR reign_id IS NOT NULL)
+);
+
+CREATE INDEX IF NOT EXISTS idx_invites_conversation ON invites(conversation_id) WHERE conversation_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_invites_reign ON invites(reign_id) WHERE reign_id IS NOT NULL;
@@ -0,0 +1,25 @@
+-- Filled in by the image processing job; NULL until then and for non-images
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS width INTEGER;
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS height INTEGER;
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS blurhash VARCHAR(64);
+-- Set once processing finished or gave up; images with NULL are still queued
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS processed_at TIMESTAMPTZ;
+-- Claimed by a worker; stale claims are retried
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS processing_started_at TIMESTAMPTZ;
+
+UPDATE attachments SET processed_at = created_at WHERE mime_type NOT LIKE 'image/%';
+
+CREATE INDEX IF NOT EXISTS idx_attachments_unprocessed ON attachments(created_at) WHERE processed_at IS NULL;
+
+CREATE TABLE IF NOT EXISTS attachment_thumbnails (
+    attachment_id UUID NOT NULL REFERENCES attachments(id) ON DELETE CASCADE,
+    -- The longest edge the thumbnail was scaled to fit
+    size INTEGER NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    sha256 CHAR(64) NOT NULL REFERENCES blobs(sha256),
+    mime_type VARCHAR(127) NOT NULL,
+    PRIMARY KEY (attachment_id, size)
+);
+
+CREATE INDEX IF NOT EXISTS idx_attachment_thumbnails_sha256 ON attachment_thumbnails(sha256);
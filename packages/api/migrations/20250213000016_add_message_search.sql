@@ -0,0 +1,5 @@
+-- Kept in sync by Postgres; tombstones have empty content and so match nothing
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS content_tsv TSVECTOR
+    GENERATED ALWAYS AS (to_tsvector('english'::regconfig, content)) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_messages_content_tsv ON messages USING GIN (content_tsv);
@@ -0,0 +1,13 @@
+-- Users mentioned by @username; rebuilt whenever the message is edited
+CREATE TABLE IF NOT EXISTS message_mentions (
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (message_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_mentions_user ON message_mentions(user_id);
+
+-- @everyone, honoured only when the sender may mention everyone
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS mentions_everyone BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX IF NOT EXISTS idx_messages_mentions_everyone ON messages(conversation_id, created_at) WHERE mentions_everyone;
@@ -0,0 +1,10 @@
+-- Markdown-stripped copy of content, written by the server; search indexes it
+-- instead of the raw markup. Rows from before this migration fall back to content.
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS content_plain TEXT;
+
+DROP INDEX IF EXISTS idx_messages_content_tsv;
+ALTER TABLE messages DROP COLUMN IF EXISTS content_tsv;
+ALTER TABLE messages ADD COLUMN content_tsv TSVECTOR
+    GENERATED ALWAYS AS (to_tsvector('english'::regconfig, COALESCE(content_plain, content))) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_messages_content_tsv ON messages USING GIN (content_tsv);
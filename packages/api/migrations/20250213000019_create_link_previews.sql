@@ -0,0 +1,23 @@
+-- Unfurled pages, shared by every message linking the same URL. Failed
+-- fetches are cached too (ok = FALSE) so dead links aren't refetched each time.
+CREATE TABLE IF NOT EXISTS link_previews (
+    url TEXT PRIMARY KEY,
+    ok BOOLEAN NOT NULL,
+    title TEXT,
+    description TEXT,
+    image_url TEXT,
+    site_name TEXT,
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_link_previews_fetched_at ON link_previews(fetched_at);
+
+-- Previews shown under a message, in the order the links appear; rebuilt on edit
+CREATE TABLE IF NOT EXISTS message_link_previews (
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    url TEXT NOT NULL REFERENCES link_previews(url) ON DELETE CASCADE,
+    position SMALLINT NOT NULL,
+    PRIMARY KEY (message_id, url)
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_link_previews_url ON message_link_previews(url);
@@ -0,0 +1,27 @@
+-- Messages queued to be sent later, removed once delivered. The delivery
+-- worker claims due rows with claimed_at; a claim older than a few minutes is
+-- assumed abandoned.
+CREATE TABLE IF NOT EXISTS scheduled_messages (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    sender_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    attachment_ids UUID[] NOT NULL DEFAULT '{}',
+    reply_to_id UUID REFERENCES messages(id) ON DELETE SET NULL,
+    send_at TIMESTAMPTZ NOT NULL,
+    claimed_at TIMESTAMPTZ,
+    attempts INT NOT NULL DEFAULT 0,
+    -- Set once delivery has failed for good; the row stays so the sender sees why
+    failed_at TIMESTAMPTZ,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_scheduled_messages_due ON scheduled_messages(send_at)
+    WHERE failed_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_scheduled_messages_sender ON scheduled_messages(sender_id, send_at);
+
+-- The scheduled message a message was delivered from. Unique, so a delivery
+-- retried after a crash can't send the same message twice.
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS scheduled_message_id UUID UNIQUE;
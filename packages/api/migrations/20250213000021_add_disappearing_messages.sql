@@ -0,0 +1,6 @@
+-- Disappearing messages: new messages in a conversation with a TTL get an
+-- expires_at, are hidden once it passes and are hard-deleted by the reaper.
+ALTER TABLE conversations ADD COLUMN IF NOT EXISTS message_ttl_secs INT;
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS expires_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_messages_expires_at ON messages(expires_at) WHERE expires_at IS NOT NULL;
@@ -0,0 +1,10 @@
+-- Unsent composer text per user and conversation, synced across devices.
+-- Cleared drafts keep their row with empty content so a stale save from
+-- another device can't bring them back
+CREATE TABLE IF NOT EXISTS drafts (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, conversation_id)
+);
@@ -0,0 +1,7 @@
+-- Images that couldn't be decoded, or were too large to, are never served,
+-- since their metadata was never stripped
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS processing_failed BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Processing records dimensions for every image it could decode
+UPDATE attachments SET processing_failed = TRUE
+WHERE mime_type LIKE 'image/%' AND processed_at IS NOT NULL AND width IS NULL;
@@ -0,0 +1,3 @@
+-- Saves are ordered by this counter instead of the device's clock: a save
+-- names the revision it was typed over and only replaces that revision
+ALTER TABLE drafts ADD COLUMN IF NOT EXISTS revision BIGINT NOT NULL DEFAULT 0;
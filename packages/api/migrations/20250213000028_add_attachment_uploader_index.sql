@@ -0,0 +1,2 @@
+-- Upload quotas sum each user's recent uploads
+CREATE INDEX IF NOT EXISTS idx_attachments_uploader_created_at ON attachments(uploader_id, created_at);
@@ -71,3 +71,26 @@ pub fn validate_token(token: &str) -> Result<Claims, jsonwebtoken::errors::Error
     )?;
     Ok(token_data.claims)
 }
+
+/// Hex HMAC-SHA256 of `message` under the JWT secret, for short-lived signed URLs.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn sign(message: &str) -> String {
+    use hmac::{Hmac, Mac};
+
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(jwt_secret().as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Check a signature from [`sign`] in constant time.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn verify_signature(message: &str, signature: &str) -> bool {
+    use hmac::{Hmac, Mac};
+
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(jwt_secret().as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    mac.verify_slice(&signature).is_ok()
+}
@@ -0,0 +1,150 @@
+//! Rules every message body must follow.
+//!
+//! Shared between the server, which enforces them on create, edit and
+//! schedule, and the UI composer, which checks as the user types, so this
+//! module must stay WASM-compatible.
+//!
+//! Content is normalized before it is checked: line endings become `\n`,
+//! blank lines at the start and whitespace at the end are dropped. Leading
+//! spaces on the first line are kept so indented code survives.
+
+use std::fmt;
+
+/// Longest accepted body, in characters.
+pub const MAX_CONTENT_CHARS: usize = 4000;
+/// Longest accepted body, in UTF-8 bytes.
+pub const MAX_CONTENT_BYTES: usize = 12 * 1024;
+/// Most lines one body may span.
+pub const MAX_CONTENT_LINES: usize = 200;
+
+/// Why content was rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContentError {
+    TooManyChars { chars: usize },
+    TooManyBytes { bytes: usize },
+    TooManyLines { lines: usize },
+    /// A control character, bidirectional override or other codepoint that
+    /// could hide or disguise text.
+    DisallowedChar(char),
+}
+
+impl fmt::Display for ContentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyChars { chars } => {
+                write!(f, "Messages can be at most {MAX_CONTENT_CHARS} characters ({chars} given)")
+            }
+            Self::TooManyBytes { bytes } => {
+                write!(f, "Messages can be at most {MAX_CONTENT_BYTES} bytes ({bytes} given)")
+            }
+            Self::TooManyLines { lines } => {
+                write!(f, "Messages can span at most {MAX_CONTENT_LINES} lines ({lines} given)")
+            }
+            Self::DisallowedChar(c) => write!(f, "Messages can't contain the character U+{:04X}", *c as u32),
+        }
+    }
+}
+
+impl std::error::Error for ContentError {}
+
+/// Private-use characters search puts around matches in snippets. Messages
+/// can't contain them, so every highlight in a snippet is a real match.
+pub const HIGHLIGHT_START: char = '\u{E000}';
+pub const HIGHLIGHT_STOP: char = '\u{E001}';
+
+/// Whether `c` may appear in a message. Newlines and tabs are the only
+/// control characters allowed; bidirectional embeddings, overrides and
+/// isolates are refused since they can reorder how surrounding text reads.
+/// Left-to-right and right-to-left marks stay allowed.
+pub fn is_allowed_char(c: char) -> bool {
+    match c {
+        HIGHLIGHT_START | HIGHLIGHT_STOP => false,
+        '\n' | '\t' => true,
+        c if c.is_control() => false,
+        '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}' => false,
+        // Line and paragraph separators break lines without a newline
+        '\u{2028}' | '\u{2029}' => false,
+        // Byte order mark and interlinear annotation controls
+        '\u{FEFF}' | '\u{FFF9}'..='\u{FFFB}' => false,
+        _ => true,
+    }
+}
+
+/// Normalize `content` and check it against the limits above. Returns the
+/// content to store, which is empty for whitespace-only input; callers decide
+/// whether an empty body is acceptable.
+pub fn normalize_content(content: &str) -> Result<String, ContentError> {
+    let unified = content.replace("\r\n", "\n").replace('\r', "\n");
+    let start = unified
+        .find(|c: char| !c.is_whitespace())
+        .map_or(unified.len(), |i| unified[..i].rfind('\n').map_or(0, |nl| nl + 1));
+    let normalized = unified[start..].trim_end().to_string();
+
+    if let Some(c) = normalized.chars().find(|c| !is_allowed_char(*c)) {
+        return Err(ContentError::DisallowedChar(c));
+    }
+    if normalized.len() > MAX_CONTENT_BYTES {
+        return Err(ContentError::TooManyBytes { bytes: normalized.len() });
+    }
+    let chars = normalized.chars().count();
+    if chars > MAX_CONTENT_CHARS {
+        return Err(ContentError::TooManyChars { chars });
+    }
+    let lines = normalized.lines().count();
+    if lines > MAX_CONTENT_LINES {
+        return Err(ContentError::TooManyLines { lines });
+    }
+
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whitespace_only_normalizes_to_empty() {
+        assert_eq!(normalize_content(" \n\t \r\n ").unwrap(), "");
+    }
+
+    #[test]
+    fn leading_blank_lines_and_trailing_whitespace_are_dropped() {
+        assert_eq!(normalize_content("\n\n    code\r\nmore  \n\n").unwrap(), "    code\nmore");
+    }
+
+    #[test]
+    fn bidi_overrides_are_refused() {
+        assert_eq!(normalize_content("abc\u{202E}fed"), Err(ContentError::DisallowedChar('\u{202E}')));
+        assert_eq!(normalize_content("a\u{2067}b"), Err(ContentError::DisallowedChar('\u{2067}')));
+    }
+
+    #[test]
+    fn control_characters_are_refused_but_tabs_and_marks_are_not() {
+        assert_eq!(normalize_content("a\u{0007}b"), Err(ContentError::DisallowedChar('\u{0007}')));
+        assert_eq!(normalize_content("a\tb\u{200F}").unwrap(), "a\tb\u{200F}");
+    }
+
+    #[test]
+    fn search_highlight_marks_are_refused() {
+        assert_eq!(normalize_content("a\u{E000}b"), Err(ContentError::DisallowedChar(HIGHLIGHT_START)));
+        assert_eq!(normalize_content("a\u{E001}b"), Err(ContentError::DisallowedChar(HIGHLIGHT_STOP)));
+        assert!(normalize_content("a\u{E002}b").is_ok());
+    }
+
+    #[test]
+    fn limits_are_enforced() {
+        assert!(normalize_content(&"a".repeat(MAX_CONTENT_CHARS)).is_ok());
+        assert_eq!(
+            normalize_content(&"a".repeat(MAX_CONTENT_CHARS + 1)),
+            Err(ContentError::TooManyChars { chars: MAX_CONTENT_CHARS + 1 })
+        );
+        // Four bytes per character reaches the byte limit first
+        let wide = "😀".repeat(MAX_CONTENT_BYTES / 4 + 1);
+        assert!(matches!(normalize_content(&wide), Err(ContentError::TooManyBytes { .. })));
+        let tall = "a\n".repeat(MAX_CONTENT_LINES + 1);
+        assert_eq!(
+            normalize_content(&tall),
+            Err(ContentError::TooManyLines { lines: MAX_CONTENT_LINES + 1 })
+        );
+    }
+}
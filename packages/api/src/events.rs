@@ -0,0 +1,156 @@
+//! Events pushed to clients over the `/ws` socket.
+//!
+//! Shared between the server (which serializes them) and the UI (which
+//! deserializes them), so this module must stay WASM-compatible.
+
+use serde::{Deserialize, Serialize};
+
+use crate::features::attachments::Attachment;
+use crate::features::conversations::list::ConversationSummary;
+use crate::features::conversations::mark_read::ReadReceipt;
+use crate::features::drafts::Draft;
+use crate::features::messages::create::MessageResponse;
+
+/// Envelope for every event pushed over the socket.
+///
+/// `seq` increases monotonically per user. Clients acknowledge it with
+/// [`ClientCommand::Ack`]; anything unacknowledged is redelivered on reconnect,
+/// so clients should ignore frames with a `seq` they have already processed.
+/// Ephemeral events such as typing indicators carry no `seq` and need no ack.
+/// When frames a client missed can no longer be redelivered, it is sent
+/// [`WsEvent::Resync`] on reconnecting instead.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ServerFrame {
+    pub seq: Option<u64>,
+    pub event: WsEvent,
+}
+
+/// Sent to a message's sender once a recipient device acknowledges it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DeliveryReceipt {
+    pub message_id: String,
+    pub recipient_id: String,
+    pub delivered_at: String,
+}
+
+/// Tells clients to replace a message with a tombstone (`for_everyone`) or,
+/// on the deleting user's other devices, to drop it from view.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MessageDeletion {
+    pub message_id: String,
+    pub for_everyone: bool,
+    pub deleted_at: String,
+    /// The message disappeared on its timer and is gone for good; drop it
+    /// from view rather than leaving a tombstone.
+    #[serde(default)]
+    pub expired: bool,
+}
+
+/// A reaction was added or removed. `count` and `reacted` describe the new
+/// aggregate for `emoji` from the receiving user's point of view.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReactionUpdate {
+    pub message_id: String,
+    /// Who added or removed the reaction.
+    pub user_id: String,
+    pub emoji: String,
+    pub added: bool,
+    pub count: i64,
+    pub reacted: bool,
+}
+
+/// Relayed to the other members while a user is typing in a conversation.
+/// Clients should hide the indicator after `expires_in_ms` unless refreshed.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TypingIndicator {
+    pub conversation_id: String,
+    pub user_id: String,
+    pub typing: bool,
+    pub expires_in_ms: u64,
+}
+
+/// Someone joined or left a group. `actor_id` is who added or removed them
+/// (the user themselves when leaving). Sent to the remaining members and to
+/// the departed user, whose clients should drop the conversation.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MembershipChange {
+    pub conversation_id: String,
+    pub user_id: String,
+    pub actor_id: String,
+    pub joined: bool,
+}
+
+/// A Reign's channels or categories changed, or the Reign was deleted.
+/// Clients showing it should refetch it with `get_reign`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReignUpdate {
+    pub reign_id: String,
+}
+
+/// Sent only to a user mentioned in a message, by name or through
+/// `@everyone`, when the message is sent or an edit adds the mention.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MentionNotice {
+    pub message_id: String,
+    pub conversation_id: String,
+    pub sender_id: String,
+    /// The start of the message, for the notification.
+    pub content: String,
+    /// Whether the user was only reached through `@everyone`.
+    pub everyone: bool,
+}
+
+/// An image attachment finished processing; `attachment` now has its
+/// dimensions, blurhash and thumbnails. `message_id` is `None` while the
+/// upload is unsent, in which case only the uploader is told.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AttachmentUpdate {
+    pub conversation_id: String,
+    pub message_id: Option<String>,
+    pub attachment: Attachment,
+}
+
+/// A message was pinned or unpinned. `actor_id` is who did it. Clients
+/// showing the conversation should refetch its pins with `list_pins`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PinUpdate {
+    pub conversation_id: String,
+    pub message_id: String,
+    pub actor_id: String,
+    pub pinned: bool,
+}
+
+/// A server → client WebSocket event, serialized as `{"type": ..., "data": ...}`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum WsEvent {
+    MessageCreated(MessageResponse),
+    MessageUpdated(MessageResponse),
+    MessageDeleted(MessageDeletion),
+    ReactionUpdated(ReactionUpdate),
+    ConversationUpdated(ConversationSummary),
+    MessagesRead(ReadReceipt),
+    MessageDelivered(DeliveryReceipt),
+    Typing(TypingIndicator),
+    MembershipChanged(MembershipChange),
+    ReignUpdated(ReignUpdate),
+    AttachmentProcessed(AttachmentUpdate),
+    Mentioned(MentionNotice),
+    PinsUpdated(PinUpdate),
+    DraftUpdated(Draft),
+    /// Some frames sent while this client was away were dropped before it
+    /// acknowledged them. Refetch history and anything else kept from events.
+    Resync,
+}
+
+/// A client → server WebSocket command, in the same `{"type": ..., "data": ...}` shape.
+/// The socket is already authenticated, so commands carry no token.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum ClientCommand {
+    MarkRead { conversation_id: String, message_id: String },
+    /// Acknowledge every frame up to and including `seq`.
+    Ack { seq: u64 },
+    /// Started (`typing: true`) or stopped typing in a conversation.
+    Typing { conversation_id: String, typing: bool },
+}
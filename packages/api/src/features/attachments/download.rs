@@ -0,0 +1,149 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AttachmentUrlRequest {
+    pub token: String,
+    pub attachment_id: String,
+}
+
+/// A fresh signed download URL for an attachment the caller can see: one sent
+/// in a conversation they can view, or their own unsent upload.
+#[post("/api/attachments/url")]
+pub async fn attachment_url(req: AttachmentUrlRequest) -> Result<String, ServerFnError> {
+    use super::{can_view, download_url};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let attachment_id: uuid::Uuid = req
+        .attachment_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid attachment_id: {e}")))?;
+
+    let pool = db::pool().await;
+    if !can_view(pool, attachment_id, user_id).await.map_err(|e| ServerFnError::new(e.to_string()))? {
+        return Err(ServerFnError::new("Attachment not found"));
+    }
+
+    Ok(download_url(attachment_id, user_id))
+}
+
+#[cfg(feature = "server")]
+#[derive(Deserialize)]
+pub struct DownloadParams {
+    /// Who the link was signed for.
+    pub user: uuid::Uuid,
+    pub expires: i64,
+    pub sig: String,
+    /// Serve this thumbnail instead of the original.
+    #[serde(default)]
+    pub size: Option<i32>,
+}
+
+/// `GET /api/attachments/{id}?user=..&expires=..&sig=..[&size=..]`
+///
+/// Streams the attachment, or one of its thumbnails, from the blob store.
+/// Images are withheld until processing has stripped their metadata, and for
+/// good if it couldn't. The signature stands in for a token so links work in
+/// `<img>` tags. It names the user it was signed for, who must still be able
+/// to see the attachment, and lapses after [`super::DOWNLOAD_URL_TTL_SECS`].
+#[cfg(feature = "server")]
+pub async fn download_handler(
+    axum::extract::Path(id): axum::extract::Path<uuid::Uuid>,
+    axum::extract::Query(params): axum::extract::Query<DownloadParams>,
+) -> axum::response::Response {
+    use axum::http::{header, StatusCode};
+    use axum::response::IntoResponse;
+
+    if params.expires < chrono::Utc::now().timestamp()
+        || !crate::auth::verify_signature(&format!("{id}:{}:{}", params.user, params.expires), &params.sig)
+    {
+        return (StatusCode::FORBIDDEN, "Link expired or invalid").into_response();
+    }
+
+    let pool = crate::db::pool().await;
+    match super::can_view(pool, id, params.user).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::NOT_FOUND, "Attachment not found").into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+    let row = match params.size {
+        Some(size) => {
+            sqlx::query_as::<_, (String, String, String, i64, bool, bool)>(
+                "SELECT t.sha256, a.filename, t.mime_type, b.size_bytes, TRUE, FALSE
+                 FROM attachment_thumbnails t
+                 JOIN attachments a ON a.id = t.attachment_id
+                 JOIN blobs b ON b.sha256 = t.sha256
+                 WHERE t.attachment_id = $1 AND t.size = $2",
+            )
+            .bind(id)
+            .bind(size)
+            .fetch_optional(pool)
+            .await
+        }
+        None => {
+            sqlx::query_as::<_, (String, String, String, i64, bool, bool)>(
+                "SELECT sha256, filename, mime_type, size_bytes, processed_at IS NOT NULL, processing_failed
+                 FROM attachments WHERE id = $1",
+            )
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+        }
+    };
+    let (sha256, filename, mime_type, size_bytes, processed, failed) = match row {
+        Ok(Some(row)) => row,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Attachment not found").into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    if failed {
+        return (StatusCode::UNPROCESSABLE_ENTITY, "This image couldn't be processed").into_response();
+    }
+    if !processed {
+        return (StatusCode::CONFLICT, [(header::RETRY_AFTER, "2")], "Attachment is still processing").into_response();
+    }
+
+    let stream = match crate::storage::blob_store().get(&sha256).await {
+        Ok(stream) => stream,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return (StatusCode::NOT_FOUND, "Attachment not found").into_response()
+        }
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    // Only media is shown inline; anything else downloads rather than renders
+    let inline = ["image/", "audio/", "video/"].iter().any(|p| mime_type.starts_with(p));
+    let disposition = format!(
+        "{}; filename*=UTF-8''{}",
+        if inline { "inline" } else { "attachment" },
+        percent_encode(&filename)
+    );
+
+    (
+        [
+            (header::CONTENT_TYPE, mime_type),
+            (header::CONTENT_LENGTH, size_bytes.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            (header::CACHE_CONTROL, "private, max-age=3600".to_string()),
+        ],
+        axum::body::Body::from_stream(stream),
+    )
+        .into_response()
+}
+
+/// RFC 5987 encoding for the `filename*` parameter.
+#[cfg(feature = "server")]
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{b:02X}"));
+        }
+    }
+    encoded
+}
@@ -0,0 +1,450 @@
+pub mod download;
+#[cfg(feature = "server")]
+pub mod previews;
+#[cfg(feature = "server")]
+pub mod upload;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use dioxus::prelude::ServerFnError;
+
+/// Largest accepted upload: 25 MiB.
+pub const MAX_ATTACHMENT_BYTES: u64 = 25 * 1024 * 1024;
+/// Most files one message can carry.
+pub const MAX_ATTACHMENTS_PER_MESSAGE: usize = 10;
+/// How long a download URL stays valid.
+pub const DOWNLOAD_URL_TTL_SECS: i64 = 60 * 60;
+/// Most bytes one user may upload per [`UPLOAD_QUOTA_WINDOW_SECS`].
+pub const UPLOAD_QUOTA_BYTES: u64 = 500 * 1024 * 1024;
+/// The rolling window [`UPLOAD_QUOTA_BYTES`] applies to.
+pub const UPLOAD_QUOTA_WINDOW_SECS: i64 = 24 * 60 * 60;
+
+/// Types accepted for upload. Binary types are identified from the file's
+/// contents; the `text/*` types are taken from the upload's `Content-Type`
+/// when the contents are valid UTF-8.
+pub const ALLOWED_MIME_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "application/pdf",
+    "application/zip",
+    "audio/mpeg",
+    "audio/ogg",
+    "video/mp4",
+    "video/webm",
+    "text/plain",
+    "text/markdown",
+    "text/csv",
+];
+
+/// A scaled-down copy of an image attachment.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Thumbnail {
+    /// The longest edge the image was scaled to fit; one of [`THUMBNAIL_SIZES`].
+    pub size: i32,
+    pub width: i32,
+    pub height: i32,
+    pub url: String,
+}
+
+/// Thumbnails generated for each image, by longest edge. Sizes at or above
+/// the original's are skipped; use the original instead.
+pub const THUMBNAIL_SIZES: &[u32] = &[160, 480, 1080];
+
+/// A file sent with a message.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Attachment {
+    pub id: String,
+    pub filename: String,
+    pub mime_type: String,
+    pub size_bytes: i64,
+    /// A download link signed for the user it was sent to, valid for
+    /// [`DOWNLOAD_URL_TTL_SECS`] while they can still see the attachment;
+    /// ask `attachment_url` for a fresh one once it lapses.
+    pub url: String,
+    /// Set for images while they are being processed. Until then the image
+    /// can't be downloaded and has no thumbnails; an `attachment_processed`
+    /// event follows once it is done.
+    pub processing: bool,
+    /// Set for images that couldn't be processed. They are never served,
+    /// since their metadata could not be stripped.
+    pub failed: bool,
+    /// Pixel dimensions of images, after applying their EXIF orientation.
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    /// A blurhash placeholder to show while the image loads.
+    pub blurhash: Option<String>,
+    /// Smallest first.
+    pub thumbnails: Vec<Thumbnail>,
+}
+
+/// Signed, expiring path for `viewer` to download an attachment without a
+/// token, so it can be used directly as an `<img src>` or link. The download
+/// checks again that `viewer` can see the attachment.
+#[cfg(feature = "server")]
+pub(crate) fn download_url(attachment_id: uuid::Uuid, viewer: uuid::Uuid) -> String {
+    let expires = chrono::Utc::now().timestamp() + DOWNLOAD_URL_TTL_SECS;
+    let signature = crate::auth::sign(&format!("{attachment_id}:{viewer}:{expires}"));
+    format!("/api/attachments/{attachment_id}?user={viewer}&expires={expires}&sig={signature}")
+}
+
+/// Point an attachment's links, thumbnails included, at `viewer`.
+#[cfg(feature = "server")]
+fn sign_for(attachment: &mut Attachment, viewer: uuid::Uuid) {
+    let Ok(id) = attachment.id.parse() else {
+        return;
+    };
+    attachment.url = download_url(id, viewer);
+    for thumbnail in &mut attachment.thumbnails {
+        thumbnail.url = format!("{}&size={}", attachment.url, thumbnail.size);
+    }
+}
+
+/// `event` with any attachment links in it signed for `viewer`, so one
+/// broadcast hands every member links of their own.
+#[cfg(feature = "server")]
+pub(crate) fn event_for(event: &crate::events::WsEvent, viewer: uuid::Uuid) -> std::borrow::Cow<'_, crate::events::WsEvent> {
+    use crate::events::WsEvent;
+    use std::borrow::Cow;
+
+    match event {
+        WsEvent::MessageCreated(m) | WsEvent::MessageUpdated(m) if !m.attachments.is_empty() => {
+            let mut event = event.clone();
+            if let WsEvent::MessageCreated(m) | WsEvent::MessageUpdated(m) = &mut event {
+                m.attachments.iter_mut().for_each(|a| sign_for(a, viewer));
+            }
+            Cow::Owned(event)
+        }
+        WsEvent::AttachmentProcessed(update) => {
+            let mut update = update.clone();
+            sign_for(&mut update.attachment, viewer);
+            Cow::Owned(WsEvent::AttachmentProcessed(update))
+        }
+        _ => Cow::Borrowed(event),
+    }
+}
+
+/// Whether `user_id` may download an attachment: one sent in a conversation
+/// they can view, or their own unsent upload.
+#[cfg(feature = "server")]
+pub(crate) async fn can_view(
+    pool: &sqlx::PgPool,
+    attachment_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+) -> Result<bool, sqlx::Error> {
+    use crate::permissions::Permissions;
+
+    let Some((conversation_id, uploader_id, sent)) = sqlx::query_as::<_, (uuid::Uuid, uuid::Uuid, bool)>(
+        "SELECT conversation_id, uploader_id, message_id IS NOT NULL FROM attachments WHERE id = $1",
+    )
+    .bind(attachment_id)
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    if !sent && uploader_id != user_id {
+        return Ok(false);
+    }
+    Ok(crate::features::roles::user_permissions(pool, conversation_id, user_id)
+        .await?
+        .is_some_and(|p| p.contains(Permissions::VIEW_CHANNEL)))
+}
+
+/// Selects everything needed to build an [`Attachment`] from `attachments a`.
+/// Callers append their own `WHERE`/`ORDER BY`.
+#[cfg(feature = "server")]
+const ATTACHMENT_SELECT: &str =
+    "SELECT a.id, a.message_id, a.filename, a.mime_type, a.size_bytes, a.width, a.height, a.blurhash,
+            a.processed_at IS NULL AS processing, a.processing_failed AS failed
+     FROM attachments a";
+
+#[cfg(feature = "server")]
+#[derive(sqlx::FromRow)]
+struct AttachmentRow {
+    id: uuid::Uuid,
+    message_id: Option<uuid::Uuid>,
+    filename: String,
+    mime_type: String,
+    size_bytes: i64,
+    width: Option<i32>,
+    height: Option<i32>,
+    blurhash: Option<String>,
+    processing: bool,
+    failed: bool,
+}
+
+/// Build attachments from rows for `viewer`, loading their thumbnails in one query.
+#[cfg(feature = "server")]
+async fn attachments_from_rows(
+    pool: &sqlx::PgPool,
+    viewer: uuid::Uuid,
+    rows: Vec<AttachmentRow>,
+) -> Result<Vec<(Option<uuid::Uuid>, Attachment)>, sqlx::Error> {
+    let ids: Vec<uuid::Uuid> = rows.iter().map(|r| r.id).collect();
+    let thumbnail_rows = sqlx::query_as::<_, (uuid::Uuid, i32, i32, i32)>(
+        "SELECT attachment_id, size, width, height FROM attachment_thumbnails
+         WHERE attachment_id = ANY($1)
+         ORDER BY size ASC",
+    )
+    .bind(&ids)
+    .fetch_all(pool)
+    .await?;
+
+    let mut thumbnails: std::collections::HashMap<uuid::Uuid, Vec<Thumbnail>> = std::collections::HashMap::new();
+    for (attachment_id, size, width, height) in thumbnail_rows {
+        thumbnails.entry(attachment_id).or_default().push(Thumbnail {
+            size,
+            width,
+            height,
+            url: format!("{}&size={size}", download_url(attachment_id, viewer)),
+        });
+    }
+
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            let attachment = Attachment {
+                id: r.id.to_string(),
+                filename: r.filename,
+                mime_type: r.mime_type,
+                size_bytes: r.size_bytes,
+                url: download_url(r.id, viewer),
+                processing: r.processing,
+                failed: r.failed,
+                width: r.width,
+                height: r.height,
+                blurhash: r.blurhash,
+                thumbnails: thumbnails.remove(&r.id).unwrap_or_default(),
+            };
+            (r.message_id, attachment)
+        })
+        .collect())
+}
+
+/// Load one attachment with its thumbnails, and the message it was sent with,
+/// with links for `viewer`.
+#[cfg(feature = "server")]
+pub(crate) async fn fetch_attachment(
+    pool: &sqlx::PgPool,
+    attachment_id: uuid::Uuid,
+    viewer: uuid::Uuid,
+) -> Result<Option<(Option<uuid::Uuid>, Attachment)>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, AttachmentRow>(&format!("{ATTACHMENT_SELECT} WHERE a.id = $1"))
+        .bind(attachment_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(attachments_from_rows(pool, viewer, rows).await?.pop())
+}
+
+/// Load the attachments of a batch of messages and attach them to the
+/// matching responses, in upload order, with links for `viewer`.
+#[cfg(feature = "server")]
+pub(crate) async fn attach_attachments(
+    pool: &sqlx::PgPool,
+    viewer: uuid::Uuid,
+    messages: &mut [crate::features::messages::create::MessageResponse],
+) -> Result<(), sqlx::Error> {
+    let ids: Vec<uuid::Uuid> = messages.iter().filter_map(|m| m.id.parse().ok()).collect();
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    let rows = sqlx::query_as::<_, AttachmentRow>(&format!(
+        "{ATTACHMENT_SELECT} WHERE a.message_id = ANY($1) ORDER BY a.created_at ASC"
+    ))
+    .bind(&ids)
+    .fetch_all(pool)
+    .await?;
+
+    let mut by_message: std::collections::HashMap<String, Vec<Attachment>> = std::collections::HashMap::new();
+    for (message_id, attachment) in attachments_from_rows(pool, viewer, rows).await? {
+        if let Some(message_id) = message_id {
+            by_message.entry(message_id.to_string()).or_default().push(attachment);
+        }
+    }
+
+    for m in messages.iter_mut() {
+        m.attachments = by_message.remove(&m.id).unwrap_or_default();
+    }
+
+    Ok(())
+}
+
+/// Record a blob and write it to the store, unless identical contents are
+/// already stored. Refreshes the blob's `created_at` either way so the
+/// orphan purge leaves it alone while the caller references it.
+#[cfg(feature = "server")]
+pub(crate) async fn put_blob(
+    pool: &sqlx::PgPool,
+    sha256: &str,
+    path: &std::path::Path,
+    size: u64,
+    content_type: &str,
+) -> std::io::Result<()> {
+    let existed = sqlx::query_scalar::<_, bool>(
+        "INSERT INTO blobs (sha256, size_bytes) VALUES ($1, $2)
+         ON CONFLICT (sha256) DO UPDATE SET created_at = NOW()
+         RETURNING xmax <> 0",
+    )
+    .bind(sha256)
+    .bind(size as i64)
+    .fetch_one(pool)
+    .await
+    .map_err(std::io::Error::other)?;
+
+    if !existed {
+        if let Err(e) = crate::storage::blob_store().put_file(sha256, path, size, content_type).await {
+            let _ = sqlx::query("DELETE FROM blobs WHERE sha256 = $1").bind(sha256).execute(pool).await;
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Link the sender's pending uploads to a new message. Every id must be an
+/// unsent upload by `sender_id` to `conversation_id`.
+#[cfg(feature = "server")]
+pub(crate) async fn link_attachments(
+    conn: &mut sqlx::PgConnection,
+    conversation_id: uuid::Uuid,
+    sender_id: uuid::Uuid,
+    message_id: uuid::Uuid,
+    attachment_ids: &[uuid::Uuid],
+) -> Result<(), ServerFnError> {
+    if attachment_ids.is_empty() {
+        return Ok(());
+    }
+
+    let linked = sqlx::query(
+        "UPDATE attachments SET message_id = $1
+         WHERE id = ANY($2) AND conversation_id = $3 AND uploader_id = $4 AND message_id IS NULL",
+    )
+    .bind(message_id)
+    .bind(attachment_ids)
+    .bind(conversation_id)
+    .bind(sender_id)
+    .execute(conn)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?
+    .rows_affected();
+
+    if linked != attachment_ids.len() as u64 {
+        return Err(ServerFnError::new("Attachment not found or already sent"));
+    }
+    Ok(())
+}
+
+/// Copy a message's attachments onto a forwarded copy of it in
+/// `conversation_id`. The copies share the originals' blobs and thumbnails,
+/// so nothing is uploaded again.
+#[cfg(feature = "server")]
+pub(crate) async fn copy_attachments(
+    conn: &mut sqlx::PgConnection,
+    source_id: uuid::Uuid,
+    conversation_id: uuid::Uuid,
+    sender_id: uuid::Uuid,
+    message_id: uuid::Uuid,
+) -> Result<(), ServerFnError> {
+    let source_ids = sqlx::query_scalar::<_, uuid::Uuid>(
+        "SELECT id FROM attachments WHERE message_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(source_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    for source in source_ids {
+        // Keeping created_at keeps the copies in upload order
+        let copy_id = sqlx::query_scalar::<_, uuid::Uuid>(
+            "INSERT INTO attachments
+                 (conversation_id, uploader_id, message_id, sha256, filename, mime_type, size_bytes,
+                  width, height, blurhash, processed_at, processing_failed, created_at)
+             SELECT $2, $3, $4, sha256, filename, mime_type, size_bytes,
+                    width, height, blurhash, processed_at, processing_failed, created_at
+             FROM attachments WHERE id = $1
+             RETURNING id",
+        )
+        .bind(source)
+        .bind(conversation_id)
+        .bind(sender_id)
+        .bind(message_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+        sqlx::query(
+            "INSERT INTO attachment_thumbnails (attachment_id, size, width, height, sha256, mime_type)
+             SELECT $2, size, width, height, sha256, mime_type FROM attachment_thumbnails WHERE attachment_id = $1",
+        )
+        .bind(source)
+        .bind(copy_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Parse and deduplicate attachment ids from a request, enforcing
+/// [`MAX_ATTACHMENTS_PER_MESSAGE`].
+#[cfg(feature = "server")]
+pub(crate) fn parse_attachment_ids(ids: &[String]) -> Result<Vec<uuid::Uuid>, ServerFnError> {
+    let mut parsed = Vec::with_capacity(ids.len());
+    for id in ids {
+        let id: uuid::Uuid = id
+            .parse()
+            .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid attachment id: {e}")))?;
+        if !parsed.contains(&id) {
+            parsed.push(id);
+        }
+    }
+    if parsed.len() > MAX_ATTACHMENTS_PER_MESSAGE {
+        return Err(ServerFnError::new(format!(
+            "A message can carry at most {MAX_ATTACHMENTS_PER_MESSAGE} attachments"
+        )));
+    }
+    Ok(parsed)
+}
+
+/// How long an upload may stay unsent before it is discarded.
+#[cfg(feature = "server")]
+const UNSENT_RETENTION: &str = "24 hours";
+
+/// Drop uploads never sent with a message, then delete blobs nothing refers
+/// to any more from both the database and the blob store. Returns how many
+/// blobs were deleted.
+#[cfg(feature = "server")]
+pub(crate) async fn purge_orphans(pool: &sqlx::PgPool) -> Result<u64, sqlx::Error> {
+    // Uploads held by a scheduled message wait for it
+    sqlx::query(&format!(
+        "DELETE FROM attachments a
+         WHERE a.message_id IS NULL AND a.created_at < NOW() - INTERVAL '{UNSENT_RETENTION}'
+           AND NOT EXISTS (SELECT 1 FROM scheduled_messages s WHERE a.id = ANY(s.attachment_ids))"
+    ))
+    .execute(pool)
+    .await?;
+
+    // Blobs get an hour's grace so a concurrent upload can still claim them
+    let orphans = sqlx::query_scalar::<_, String>(
+        "DELETE FROM blobs b
+         WHERE b.created_at < NOW() - INTERVAL '1 hour'
+           AND NOT EXISTS (SELECT 1 FROM attachments a WHERE a.sha256 = b.sha256)
+           AND NOT EXISTS (SELECT 1 FROM attachment_thumbnails t WHERE t.sha256 = b.sha256)
+         RETURNING b.sha256",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let store = crate::storage::blob_store();
+    for sha256 in &orphans {
+        if let Err(e) = store.delete(sha256).await {
+            println!("Deleting blob {sha256} failed: {e}");
+        }
+    }
+
+    Ok(orphans.len() as u64)
+}
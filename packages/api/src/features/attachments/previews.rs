@@ -0,0 +1,397 @@
+//! Background processing of image attachments: decoding, EXIF stripping,
+//! thumbnails and blurhash placeholders.
+//!
+//! Uploads queue themselves by leaving `processed_at` unset. The upload
+//! handler starts processing straight away, and a periodic job picks up
+//! anything a restart or crash left behind. Workers claim rows with
+//! `processing_started_at`, so several server instances never process the
+//! same image at once.
+
+use std::io::Cursor;
+
+use futures_util::StreamExt;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use super::THUMBNAIL_SIZES;
+
+/// Images wider or taller than this are not decoded.
+const MAX_DIMENSION: u32 = 12_000;
+/// Decoder memory cap, which also rejects decompression bombs.
+const MAX_DECODE_BYTES: u64 = 512 * 1024 * 1024;
+const JPEG_QUALITY: u8 = 85;
+/// Images claimed per run of the periodic job.
+const BATCH: i64 = 20;
+
+/// An encoded image ready to be stored as a blob.
+struct Rendition {
+    bytes: Vec<u8>,
+    width: u32,
+    height: u32,
+    mime_type: &'static str,
+}
+
+struct Processed {
+    width: u32,
+    height: u32,
+    blurhash: Option<String>,
+    /// Keyed by the longest edge from [`THUMBNAIL_SIZES`].
+    thumbnails: Vec<(u32, Rendition)>,
+    /// The original re-encoded without its metadata, if it carried any.
+    stripped: Option<Rendition>,
+}
+
+struct Claimed {
+    id: Uuid,
+    conversation_id: Uuid,
+    uploader_id: Uuid,
+    sha256: String,
+    mime_type: String,
+}
+
+/// Process a freshly uploaded image in the background.
+pub(crate) fn spawn_processing(attachment_id: Uuid) {
+    tokio::spawn(async move {
+        let pool = crate::db::pool().await;
+        match claim(pool, Some(attachment_id), 1).await {
+            Ok(claimed) => {
+                for image in claimed {
+                    process(pool, image).await;
+                }
+            }
+            Err(e) => println!("Claiming attachment {attachment_id} for processing failed: {e}"),
+        }
+    });
+}
+
+/// Process images still waiting, including ones whose worker went away.
+/// Returns how many were picked up.
+pub(crate) async fn process_pending(pool: &sqlx::PgPool) -> Result<u64, sqlx::Error> {
+    let claimed = claim(pool, None, BATCH).await?;
+    let count = claimed.len() as u64;
+    for image in claimed {
+        process(pool, image).await;
+    }
+    Ok(count)
+}
+
+/// Claim up to `limit` unprocessed images, or just `only`. Claims older than
+/// ten minutes are assumed abandoned and can be taken over.
+async fn claim(pool: &sqlx::PgPool, only: Option<Uuid>, limit: i64) -> Result<Vec<Claimed>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (Uuid, Uuid, Uuid, String, String)>(
+        "UPDATE attachments SET processing_started_at = NOW()
+         WHERE id IN (
+             SELECT id FROM attachments
+             WHERE processed_at IS NULL
+               AND (processing_started_at IS NULL OR processing_started_at < NOW() - INTERVAL '10 minutes')
+               AND ($1::uuid IS NULL OR id = $1)
+             ORDER BY created_at ASC
+             LIMIT $2
+             FOR UPDATE SKIP LOCKED
+         )
+         RETURNING id, conversation_id, uploader_id, sha256, mime_type",
+    )
+    .bind(only)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, conversation_id, uploader_id, sha256, mime_type)| Claimed {
+            id,
+            conversation_id,
+            uploader_id,
+            sha256,
+            mime_type,
+        })
+        .collect())
+}
+
+/// Process one claimed image and tell whoever can see it. Images that can't
+/// be decoded within the limits are marked failed and never served; storage
+/// failures leave the claim to lapse so the image is retried.
+async fn process(pool: &sqlx::PgPool, image: Claimed) {
+    let id = image.id;
+    let result = match read_blob(&image.sha256).await {
+        Ok(bytes) => {
+            let mime_type = image.mime_type.clone();
+            tokio::task::spawn_blocking(move || render(&bytes, &mime_type))
+                .await
+                .map_err(std::io::Error::other)
+        }
+        Err(e) => Err(e),
+    };
+
+    let stored = match result {
+        Ok(processed) => save(pool, id, processed).await,
+        Err(e) => Err(e),
+    };
+    match stored {
+        Ok(true) => notify(pool, &image).await,
+        Ok(false) => {}
+        Err(e) => println!("Processing attachment {id} failed: {e}"),
+    }
+}
+
+async fn read_blob(sha256: &str) -> std::io::Result<Vec<u8>> {
+    let mut stream = crate::storage::blob_store().get(sha256).await?;
+    let mut bytes = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        bytes.extend_from_slice(&chunk?);
+    }
+    Ok(bytes)
+}
+
+/// Decode the image, apply its EXIF orientation and build every preview.
+/// `None` when it can't be decoded within the limits.
+fn render(bytes: &[u8], mime_type: &str) -> Option<Processed> {
+    let format = ImageFormat::from_mime_type(mime_type)?;
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_BYTES);
+    reader.limits(limits);
+
+    let mut decoder = reader.into_decoder().ok()?;
+    let exif = decoder.exif_metadata().ok().flatten();
+    let has_metadata = exif.is_some() || decoder.xmp_metadata().ok().flatten().is_some();
+    let orientation = exif
+        .as_deref()
+        .and_then(Orientation::from_exif_chunk)
+        .unwrap_or(Orientation::NoTransforms);
+
+    let mut image = DynamicImage::from_decoder(decoder).ok()?;
+    image.apply_orientation(orientation);
+    let (width, height) = (image.width(), image.height());
+
+    let thumbnails = THUMBNAIL_SIZES
+        .iter()
+        .filter(|&&size| size < width.max(height))
+        .filter_map(|&size| Some((size, encode(&image.thumbnail(size, size), None)?)))
+        .collect();
+
+    // Re-encoding drops EXIF and XMP, including any location the camera recorded
+    let stripped = if has_metadata { encode(&image, Some(format)) } else { None };
+
+    Some(Processed {
+        width,
+        height,
+        blurhash: blurhash(&image),
+        thumbnails,
+        stripped,
+    })
+}
+
+/// Encode as `format`, or for thumbnails as JPEG, falling back to PNG when
+/// the image has transparency.
+fn encode(image: &DynamicImage, format: Option<ImageFormat>) -> Option<Rendition> {
+    let format = format.unwrap_or(if image.color().has_alpha() { ImageFormat::Png } else { ImageFormat::Jpeg });
+    let mut bytes = Vec::new();
+    if format == ImageFormat::Jpeg {
+        let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY);
+        image.to_rgb8().write_with_encoder(encoder).ok()?;
+    } else {
+        image.write_to(&mut Cursor::new(&mut bytes), format).ok()?;
+    }
+
+    Some(Rendition {
+        bytes,
+        width: image.width(),
+        height: image.height(),
+        mime_type: format.to_mime_type(),
+    })
+}
+
+/// A blurhash of the image, computed on a tiny copy since the hash only
+/// keeps a few colour components anyway.
+fn blurhash(image: &DynamicImage) -> Option<String> {
+    let small = image.thumbnail(32, 32).to_rgba8();
+    let (x, y) = if small.width() >= small.height() { (4, 3) } else { (3, 4) };
+    blurhash::encode(x, y, small.width(), small.height(), small.as_raw()).ok()
+}
+
+/// Store a rendition in the blob store under its digest.
+async fn store(pool: &sqlx::PgPool, rendition: &Rendition) -> std::io::Result<String> {
+    let sha256 = hex::encode(Sha256::digest(&rendition.bytes));
+    let temp_path = std::env::temp_dir().join(format!("reigncloud-preview-{}", Uuid::new_v4()));
+    tokio::fs::write(&temp_path, &rendition.bytes).await?;
+    let result = super::put_blob(pool, &sha256, &temp_path, rendition.bytes.len() as u64, rendition.mime_type).await;
+    let _ = tokio::fs::remove_file(&temp_path).await;
+    result.map(|_| sha256)
+}
+
+/// Record the results. Returns `false` if the attachment was deleted or
+/// processed elsewhere in the meantime.
+async fn save(pool: &sqlx::PgPool, id: Uuid, processed: Option<Processed>) -> std::io::Result<bool> {
+    let Some(processed) = processed else {
+        let result = sqlx::query(
+            "UPDATE attachments SET processed_at = NOW(), processing_failed = TRUE
+             WHERE id = $1 AND processed_at IS NULL",
+        )
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(std::io::Error::other)?;
+        return Ok(result.rows_affected() > 0);
+    };
+
+    let mut thumbnails = Vec::with_capacity(processed.thumbnails.len());
+    for (size, rendition) in &processed.thumbnails {
+        thumbnails.push((*size, store(pool, rendition).await?, rendition));
+    }
+    let stripped = match &processed.stripped {
+        Some(rendition) => Some((store(pool, rendition).await?, rendition.bytes.len() as i64)),
+        None => None,
+    };
+
+    let mut tx = pool.begin().await.map_err(std::io::Error::other)?;
+
+    // The original blob is purged once nothing else refers to it
+    let updated = sqlx::query(
+        "UPDATE attachments
+         SET width = $2, height = $3, blurhash = $4, processed_at = NOW(),
+             sha256 = COALESCE($5, sha256), size_bytes = COALESCE($6, size_bytes)
+         WHERE id = $1 AND processed_at IS NULL",
+    )
+    .bind(id)
+    .bind(processed.width as i32)
+    .bind(processed.height as i32)
+    .bind(&processed.blurhash)
+    .bind(stripped.as_ref().map(|(sha256, _)| sha256))
+    .bind(stripped.as_ref().map(|(_, size)| *size))
+    .execute(&mut *tx)
+    .await
+    .map_err(std::io::Error::other)?
+    .rows_affected();
+    if updated == 0 {
+        return Ok(false);
+    }
+
+    for (size, sha256, rendition) in thumbnails {
+        sqlx::query(
+            "INSERT INTO attachment_thumbnails (attachment_id, size, width, height, sha256, mime_type)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT (attachment_id, size) DO NOTHING",
+        )
+        .bind(id)
+        .bind(size as i32)
+        .bind(rendition.width as i32)
+        .bind(rendition.height as i32)
+        .bind(&sha256)
+        .bind(rendition.mime_type)
+        .execute(&mut *tx)
+        .await
+        .map_err(std::io::Error::other)?;
+    }
+
+    tx.commit().await.map_err(std::io::Error::other)?;
+    Ok(true)
+}
+
+/// Push the finished attachment to the conversation once it was sent, or
+/// otherwise only to the uploader, whose composer is showing it.
+async fn notify(pool: &sqlx::PgPool, image: &Claimed) {
+    use crate::events::{AttachmentUpdate, WsEvent};
+
+    let (message_id, attachment) = match super::fetch_attachment(pool, image.id, image.uploader_id).await {
+        Ok(Some(found)) => found,
+        Ok(None) => return,
+        Err(e) => {
+            println!("Loading processed attachment {} failed: {e}", image.id);
+            return;
+        }
+    };
+
+    let event = WsEvent::AttachmentProcessed(AttachmentUpdate {
+        conversation_id: image.conversation_id.to_string(),
+        message_id: message_id.map(|id| id.to_string()),
+        attachment,
+    });
+    match message_id {
+        Some(_) => crate::features::conversations::broadcast(pool, image.conversation_id, &event).await,
+        None => crate::ws::send_event(image.uploader_id, &event),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, ImageEncoder, RgbImage};
+
+    use super::*;
+
+    /// A little-endian TIFF header with one IFD entry: orientation 6, which
+    /// means the image has to be turned 90° clockwise to display upright.
+    const EXIF_ROTATE_90: &[u8] = &[
+        0x49, 0x49, 0x2A, 0x00, 0x08, 0x00, 0x00, 0x00, 0x01, 0x00, 0x12, 0x01, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00,
+        0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    fn jpeg_with_exif(width: u32, height: u32, exif: &[u8]) -> Vec<u8> {
+        let image = RgbImage::from_pixel(width, height, image::Rgb([200, 40, 40]));
+        let mut bytes = Vec::new();
+        let mut encoder = image::codecs::jpeg::JpegEncoder::new(&mut bytes);
+        encoder.set_exif_metadata(exif.to_vec()).unwrap();
+        encoder.write_image(image.as_raw(), width, height, image::ExtendedColorType::Rgb8).unwrap();
+        bytes
+    }
+
+    fn png(image: DynamicImage) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn metadata_is_stripped_after_applying_orientation() {
+        let processed = render(&jpeg_with_exif(40, 20, EXIF_ROTATE_90), "image/jpeg").unwrap();
+        assert_eq!((processed.width, processed.height), (20, 40));
+
+        let stripped = processed.stripped.expect("an image with EXIF is re-encoded");
+        assert_eq!(stripped.mime_type, "image/jpeg");
+        assert_eq!((stripped.width, stripped.height), (20, 40));
+        let mut decoder = ImageReader::with_format(Cursor::new(&stripped.bytes), ImageFormat::Jpeg)
+            .into_decoder()
+            .unwrap();
+        assert_eq!(decoder.exif_metadata().unwrap(), None);
+    }
+
+    #[test]
+    fn images_without_metadata_keep_their_original() {
+        let processed = render(&png(DynamicImage::ImageRgb8(RgbImage::new(30, 30))), "image/png").unwrap();
+        assert!(processed.stripped.is_none());
+    }
+
+    #[test]
+    fn thumbnails_are_only_made_below_the_original_size() {
+        let processed = render(&png(DynamicImage::ImageRgb8(RgbImage::new(600, 300))), "image/png").unwrap();
+        let sizes: Vec<u32> = processed.thumbnails.iter().map(|(size, _)| *size).collect();
+        assert_eq!(sizes, [160, 480]);
+        for (size, thumbnail) in &processed.thumbnails {
+            assert_eq!((thumbnail.width, thumbnail.height), (*size, *size / 2));
+            assert_eq!(thumbnail.mime_type, "image/jpeg");
+        }
+    }
+
+    #[test]
+    fn blurhash_matches_the_aspect_ratio() {
+        let wide = render(&png(DynamicImage::ImageRgb8(RgbImage::new(64, 32))), "image/png").unwrap();
+        let tall = render(&png(DynamicImage::ImageRgb8(RgbImage::new(32, 64))), "image/png").unwrap();
+        // The first character encodes the component counts: 4x3 and 3x4
+        let (wide, tall) = (wide.blurhash.unwrap(), tall.blurhash.unwrap());
+        assert_eq!(wide.len(), 4 + 2 * 4 * 3);
+        assert_ne!(wide[..1], tall[..1]);
+        assert_eq!(blurhash::decode(&wide, 4, 2, 1.0).unwrap().len(), 4 * 2 * 4);
+    }
+
+    #[test]
+    fn undecodable_and_oversized_images_fail() {
+        assert!(render(b"not an image", "image/png").is_none());
+        assert!(render(&jpeg_with_exif(4, 4, EXIF_ROTATE_90), "image/png").is_none());
+        let huge = png(DynamicImage::ImageLuma8(GrayImage::new(MAX_DIMENSION + 1, 1)));
+        assert!(render(&huge, "image/png").is_none());
+    }
+}
@@ -0,0 +1,233 @@
+use axum::{
+    body::Body,
+    extract::Query,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::StreamExt;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+use super::{Attachment, ALLOWED_MIME_TYPES, MAX_ATTACHMENT_BYTES, UPLOAD_QUOTA_BYTES, UPLOAD_QUOTA_WINDOW_SECS};
+use crate::permissions::Permissions;
+
+/// Longest stored filename, matching the column width.
+const MAX_FILENAME_CHARS: usize = 255;
+/// How much of the upload is inspected to identify its type.
+const SNIFF_BYTES: usize = 8 * 1024;
+
+#[derive(Deserialize)]
+pub struct UploadParams {
+    pub conversation_id: String,
+    pub filename: String,
+}
+
+fn reject(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, message.into()).into_response()
+}
+
+/// `POST /api/attachments/upload?conversation_id=..&filename=..`
+///
+/// Takes the raw file as the request body with an `Authorization: Bearer`
+/// token, streaming it to a temp file while hashing. The result is an
+/// unsent [`Attachment`] whose id can be passed to `create_message`. Each
+/// user may upload [`UPLOAD_QUOTA_BYTES`] per [`UPLOAD_QUOTA_WINDOW_SECS`].
+pub async fn upload_handler(headers: HeaderMap, Query(params): Query<UploadParams>, body: Body) -> Response {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    let claims = match crate::auth::validate_token(token) {
+        Ok(c) => c,
+        Err(_) => return reject(StatusCode::UNAUTHORIZED, "Invalid token"),
+    };
+    let Ok(uploader_id) = claims.sub.parse::<Uuid>() else {
+        return reject(StatusCode::BAD_REQUEST, "Invalid user ID in token");
+    };
+    let Ok(conversation_id) = params.conversation_id.parse::<Uuid>() else {
+        return reject(StatusCode::BAD_REQUEST, "Invalid conversation_id");
+    };
+
+    // A Content-Length over the limit can be turned away before reading anything
+    let declared_size = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if declared_size.is_some_and(|size| size > MAX_ATTACHMENT_BYTES) {
+        return too_large();
+    }
+
+    let pool = crate::db::pool().await;
+    if let Err(e) =
+        crate::features::roles::require_permission(pool, conversation_id, uploader_id, Permissions::SEND_MESSAGES).await
+    {
+        return reject(StatusCode::FORBIDDEN, e.to_string());
+    }
+
+    let uploaded = sqlx::query_scalar::<_, i64>(
+        "SELECT COALESCE(SUM(size_bytes), 0)::BIGINT FROM attachments
+         WHERE uploader_id = $1 AND created_at > NOW() - make_interval(secs => $2)",
+    )
+    .bind(uploader_id)
+    .bind(UPLOAD_QUOTA_WINDOW_SECS as f64)
+    .fetch_one(pool)
+    .await;
+    let remaining = match uploaded {
+        Ok(uploaded) => UPLOAD_QUOTA_BYTES.saturating_sub(uploaded.max(0) as u64),
+        Err(e) => return reject(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    if remaining == 0 || declared_size.is_some_and(|size| size > remaining) {
+        return over_quota();
+    }
+
+    let declared_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.split(';').next().unwrap_or_default().trim().to_ascii_lowercase());
+
+    let temp_path = std::env::temp_dir().join(format!("reigncloud-upload-{}", Uuid::new_v4()));
+    let upload = Upload { conversation_id, uploader_id, filename: &params.filename, declared_type, remaining };
+    let result = store_upload(pool, body, &temp_path, upload).await;
+    let _ = tokio::fs::remove_file(&temp_path).await;
+
+    match result {
+        Ok(attachment) => Json(attachment).into_response(),
+        Err(response) => response,
+    }
+}
+
+fn too_large() -> Response {
+    reject(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        format!("Attachments can be at most {} MiB", MAX_ATTACHMENT_BYTES / (1024 * 1024)),
+    )
+}
+
+fn over_quota() -> Response {
+    reject(
+        StatusCode::TOO_MANY_REQUESTS,
+        format!("You can upload at most {} MiB a day", UPLOAD_QUOTA_BYTES / (1024 * 1024)),
+    )
+}
+
+/// Who is uploading what, and how much of their quota is left.
+struct Upload<'a> {
+    conversation_id: Uuid,
+    uploader_id: Uuid,
+    filename: &'a str,
+    declared_type: Option<String>,
+    remaining: u64,
+}
+
+async fn store_upload(
+    pool: &sqlx::PgPool,
+    body: Body,
+    temp_path: &std::path::Path,
+    upload: Upload<'_>,
+) -> Result<Attachment, Response> {
+    let Upload { conversation_id, uploader_id, filename, declared_type, remaining } = upload;
+    let internal = |e: &dyn std::fmt::Display| reject(StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+
+    let mut file = tokio::fs::File::create(temp_path).await.map_err(|e| internal(&e))?;
+    let mut hasher = Sha256::new();
+    let mut head = Vec::with_capacity(SNIFF_BYTES);
+    let mut size: u64 = 0;
+
+    let mut stream = body.into_data_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| reject(StatusCode::BAD_REQUEST, e.to_string()))?;
+        size += chunk.len() as u64;
+        if size > MAX_ATTACHMENT_BYTES {
+            return Err(too_large());
+        }
+        if size > remaining {
+            return Err(over_quota());
+        }
+        if head.len() < SNIFF_BYTES {
+            let take = (SNIFF_BYTES - head.len()).min(chunk.len());
+            head.extend_from_slice(&chunk[..take]);
+        }
+        hasher.update(&chunk);
+        file.write_all(&chunk).await.map_err(|e| internal(&e))?;
+    }
+    file.flush().await.map_err(|e| internal(&e))?;
+    drop(file);
+
+    if size == 0 {
+        return Err(reject(StatusCode::BAD_REQUEST, "Attachment is empty"));
+    }
+
+    let mime_type = detect_mime_type(&head, declared_type.as_deref(), temp_path)
+        .await
+        .ok_or_else(|| reject(StatusCode::UNSUPPORTED_MEDIA_TYPE, "This file type is not allowed"))?;
+    let filename = sanitize_filename(filename);
+    let sha256 = hex::encode(hasher.finalize());
+
+    let is_image = mime_type.starts_with("image/");
+
+    // Identical contents are stored once, however many times they are uploaded
+    super::put_blob(pool, &sha256, temp_path, size, &mime_type).await.map_err(|e| internal(&e))?;
+
+    let id = sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO attachments (conversation_id, uploader_id, sha256, filename, mime_type, size_bytes, processed_at)
+         VALUES ($1, $2, $3, $4, $5, $6, CASE WHEN $7 THEN NULL ELSE NOW() END)
+         RETURNING id",
+    )
+    .bind(conversation_id)
+    .bind(uploader_id)
+    .bind(&sha256)
+    .bind(&filename)
+    .bind(&mime_type)
+    .bind(size as i64)
+    .bind(is_image)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| internal(&e))?;
+
+    // Images are decoded off the request path; the client hears back over the socket
+    if is_image {
+        super::previews::spawn_processing(id);
+    }
+
+    super::fetch_attachment(pool, id, uploader_id)
+        .await
+        .map_err(|e| internal(&e))?
+        .map(|(_, attachment)| attachment)
+        .ok_or_else(|| reject(StatusCode::NOT_FOUND, "Attachment not found"))
+}
+
+/// Identify the upload from its contents rather than trusting the client.
+/// Text has no magic number, so a declared `text/*` type is accepted only
+/// when the whole file is valid UTF-8.
+async fn detect_mime_type(head: &[u8], declared: Option<&str>, path: &std::path::Path) -> Option<String> {
+    if let Some(kind) = infer::get(head) {
+        let mime = kind.mime_type();
+        return ALLOWED_MIME_TYPES.contains(&mime).then(|| mime.to_string());
+    }
+
+    let declared = declared.filter(|t| t.starts_with("text/") && ALLOWED_MIME_TYPES.contains(t))?;
+    let contents = tokio::fs::read(path).await.ok()?;
+    std::str::from_utf8(&contents).ok()?;
+    Some(declared.to_string())
+}
+
+/// Keep only the final path component, drop control characters and cap the
+/// length, so the name is safe to echo back in `Content-Disposition`.
+fn sanitize_filename(filename: &str) -> String {
+    let name = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .take(MAX_FILENAME_CHARS)
+        .collect();
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." {
+        "file".to_string()
+    } else {
+        name.to_string()
+    }
+}
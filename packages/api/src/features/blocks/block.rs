@@ -0,0 +1,74 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BlockRequest {
+    pub token: String,
+    pub user_id: String,
+}
+
+/// Block a user. Blocking someone already blocked is a no-op.
+#[post("/api/blocks/block")]
+pub async fn block_user(req: BlockRequest) -> Result<(), ServerFnError> {
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let target_id: uuid::Uuid = req
+        .user_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid user_id: {e}")))?;
+    if target_id == user_id {
+        return Err(ServerFnError::new("You can't block yourself"));
+    }
+
+    let pool = db::pool().await;
+    let inserted = sqlx::query(
+        "INSERT INTO user_blocks (blocker_id, blocked_id)
+         SELECT $1, id FROM users WHERE id = $2
+         ON CONFLICT (blocker_id, blocked_id) DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(target_id)
+    .execute(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if inserted.rows_affected() == 0 {
+        let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM users WHERE id = $1)")
+            .bind(target_id)
+            .fetch_one(pool)
+            .await
+            .map_err(|e| ServerFnError::new(e.to_string()))?;
+        if !exists {
+            return Err(ServerFnError::new("User not found"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Unblock a user. Unblocking someone who isn't blocked is a no-op.
+#[post("/api/blocks/unblock")]
+pub async fn unblock_user(req: BlockRequest) -> Result<(), ServerFnError> {
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let target_id: uuid::Uuid = req
+        .user_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid user_id: {e}")))?;
+
+    let pool = db::pool().await;
+    sqlx::query("DELETE FROM user_blocks WHERE blocker_id = $1 AND blocked_id = $2")
+        .bind(user_id)
+        .bind(target_id)
+        .execute(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::BlockedUser;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ListBlocksRequest {
+    pub token: String,
+}
+
+/// Users the caller blocked, most recently blocked first.
+#[post("/api/blocks/list")]
+pub async fn list_blocks(req: ListBlocksRequest) -> Result<Vec<BlockedUser>, ServerFnError> {
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    let pool = db::pool().await;
+    let rows = sqlx::query_as::<_, (uuid::Uuid, String, chrono::DateTime<chrono::Utc>)>(
+        "SELECT u.id, u.username, b.created_at FROM user_blocks b
+         JOIN users u ON u.id = b.blocked_id
+         WHERE b.blocker_id = $1
+         ORDER BY b.created_at DESC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, username, blocked_at)| BlockedUser {
+            user_id: id.to_string(),
+            username,
+            blocked_at: blocked_at.to_rfc3339(),
+        })
+        .collect())
+}
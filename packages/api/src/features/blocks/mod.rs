@@ -0,0 +1,85 @@
+//! Users blocking other users.
+//!
+//! A blocked user can't send the blocker direct messages or forward the
+//! blocker's messages, and nobody can forward the blocker's messages into a
+//! conversation the blocked user can read. Blocks are private: the blocked
+//! user is only told a send or forward isn't allowed.
+
+pub mod block;
+pub mod list;
+
+use serde::{Deserialize, Serialize};
+
+/// Someone the caller blocked.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BlockedUser {
+    pub user_id: String,
+    pub username: String,
+    pub blocked_at: String,
+}
+
+/// Refuse `sender_id` messaging a direct conversation whose other member
+/// blocked them. Group and channel messages aren't affected.
+#[cfg(feature = "server")]
+pub(crate) async fn require_not_blocked(
+    pool: &sqlx::PgPool,
+    conversation_id: uuid::Uuid,
+    sender_id: uuid::Uuid,
+) -> Result<(), dioxus::prelude::ServerFnError> {
+    use dioxus::prelude::ServerFnError;
+
+    let blocked = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (
+             SELECT 1 FROM conversations c
+             JOIN conversation_members cm ON cm.conversation_id = c.id AND cm.user_id <> $2
+             JOIN user_blocks b ON b.blocker_id = cm.user_id AND b.blocked_id = $2
+             WHERE c.id = $1 AND c.kind = 'direct'
+         )",
+    )
+    .bind(conversation_id)
+    .bind(sender_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if blocked {
+        return Err(ServerFnError::new("You can't message this user"));
+    }
+    Ok(())
+}
+
+/// Refuse forwarding `message_ids` into `conversation_id` when the author of
+/// any of them, or of the message it was itself forwarded from, blocked the
+/// forwarder or anyone who can read the target.
+#[cfg(feature = "server")]
+pub(crate) async fn require_forwardable(
+    pool: &sqlx::PgPool,
+    message_ids: &[uuid::Uuid],
+    forwarder_id: uuid::Uuid,
+    conversation_id: uuid::Uuid,
+) -> Result<(), dioxus::prelude::ServerFnError> {
+    use dioxus::prelude::ServerFnError;
+
+    let members = crate::features::conversations::member_ids(pool, conversation_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let blocked = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (
+             SELECT 1 FROM messages m
+             JOIN user_blocks b ON b.blocker_id IN (m.sender_id, m.forwarded_from_sender_id)
+             WHERE m.id = ANY($1) AND (b.blocked_id = $2 OR b.blocked_id = ANY($3))
+         )",
+    )
+    .bind(message_ids)
+    .bind(forwarder_id)
+    .bind(members.as_slice())
+    .fetch_one(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if blocked {
+        return Err(ServerFnError::new("Some of these messages can't be forwarded here"));
+    }
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::Channel;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CreateChannelRequest {
+    pub token: String,
+    pub reign_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub category_id: Option<String>,
+    #[serde(default)]
+    pub topic: Option<String>,
+}
+
+/// Create a text channel at the end of its category. Every Reign member joins it.
+#[post("/api/channels/create")]
+pub async fn create_channel(req: CreateChannelRequest) -> Result<Channel, ServerFnError> {
+    use super::{fetch_channel, parse_category, validate_topic, MAX_CHANNELS_PER_REIGN};
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::reigns::{notify_reign, parse_reign_id, validate_name};
+    use crate::features::roles::require_reign_permission;
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let reign_id = parse_reign_id(&req.reign_id)?;
+    let name = validate_name(&req.name, "Channel")?;
+    let topic = req.topic.as_deref().map(validate_topic).transpose()?.flatten();
+
+    let pool = db::pool().await;
+    require_reign_permission(pool, reign_id, user_id, Permissions::MANAGE_CHANNELS).await?;
+    let category_id = match &req.category_id {
+        Some(id) => Some(parse_category(pool, reign_id, id).await?),
+        None => None,
+    };
+
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    // Lock the Reign so concurrent creates agree on positions and the channel cap
+    sqlx::query("SELECT id FROM reigns WHERE id = $1 FOR UPDATE")
+        .bind(reign_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM conversations WHERE reign_id = $1")
+        .bind(reign_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    if count >= MAX_CHANNELS_PER_REIGN {
+        return Err(ServerFnError::new(format!("A Reign can have at most {MAX_CHANNELS_PER_REIGN} channels")));
+    }
+
+    let channel_id = sqlx::query_scalar::<_, uuid::Uuid>(
+        "INSERT INTO conversations (kind, name, topic, created_by, reign_id, category_id, position)
+         SELECT 'channel', $1, $2, $3, $4, $5, COALESCE(MAX(position) + 1, 0)
+         FROM conversations WHERE reign_id = $4 AND category_id IS NOT DISTINCT FROM $5
+         RETURNING id",
+    )
+    .bind(&name)
+    .bind(&topic)
+    .bind(user_id)
+    .bind(reign_id)
+    .bind(category_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    sqlx::query(
+        "INSERT INTO conversation_members (conversation_id, user_id)
+         SELECT $1, user_id FROM reign_members WHERE reign_id = $2",
+    )
+    .bind(channel_id)
+    .bind(reign_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    notify_reign(pool, reign_id).await;
+
+    fetch_channel(pool, user_id, channel_id).await
+}
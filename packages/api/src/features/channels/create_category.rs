@@ -0,0 +1,48 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::ChannelCategory;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CreateCategoryRequest {
+    pub token: String,
+    pub reign_id: String,
+    pub name: String,
+}
+
+/// Add a category below the Reign's existing ones.
+#[post("/api/channels/create_category")]
+pub async fn create_category(req: CreateCategoryRequest) -> Result<ChannelCategory, ServerFnError> {
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::reigns::{notify_reign, parse_reign_id, validate_name};
+    use crate::features::roles::require_reign_permission;
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let reign_id = parse_reign_id(&req.reign_id)?;
+    let name = validate_name(&req.name, "Category")?;
+
+    let pool = db::pool().await;
+    require_reign_permission(pool, reign_id, user_id, Permissions::MANAGE_CHANNELS).await?;
+
+    let (id, position) = sqlx::query_as::<_, (uuid::Uuid, i32)>(
+        "INSERT INTO channel_categories (reign_id, name, position)
+         SELECT $1, $2, COALESCE(MAX(position) + 1, 0) FROM channel_categories WHERE reign_id = $1
+         RETURNING id, position",
+    )
+    .bind(reign_id)
+    .bind(&name)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    notify_reign(pool, reign_id).await;
+
+    Ok(ChannelCategory {
+        id: id.to_string(),
+        name,
+        position,
+    })
+}
@@ -0,0 +1,38 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DeleteChannelRequest {
+    pub token: String,
+    pub channel_id: String,
+}
+
+/// Delete a channel together with its messages.
+#[post("/api/channels/delete")]
+pub async fn delete_channel(req: DeleteChannelRequest) -> Result<(), ServerFnError> {
+    use super::channel_reign;
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::conversations::invalidate_members;
+    use crate::features::reigns::notify_reign;
+    use crate::features::roles::require_permission;
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    let pool = db::pool().await;
+    let (channel_id, reign_id) = channel_reign(pool, &req.channel_id).await?;
+    require_permission(pool, channel_id, user_id, Permissions::MANAGE_CHANNELS).await?;
+
+    sqlx::query("DELETE FROM conversations WHERE id = $1")
+        .bind(channel_id)
+        .execute(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    invalidate_members(channel_id);
+    notify_reign(pool, reign_id).await;
+
+    Ok(())
+}
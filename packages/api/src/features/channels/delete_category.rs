@@ -0,0 +1,38 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DeleteCategoryRequest {
+    pub token: String,
+    pub reign_id: String,
+    pub category_id: String,
+}
+
+/// Delete a category. Its channels are kept and become uncategorized.
+#[post("/api/channels/delete_category")]
+pub async fn delete_category(req: DeleteCategoryRequest) -> Result<(), ServerFnError> {
+    use super::parse_category;
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::reigns::{notify_reign, parse_reign_id};
+    use crate::features::roles::require_reign_permission;
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let reign_id = parse_reign_id(&req.reign_id)?;
+
+    let pool = db::pool().await;
+    require_reign_permission(pool, reign_id, user_id, Permissions::MANAGE_CHANNELS).await?;
+    let category_id = parse_category(pool, reign_id, &req.category_id).await?;
+
+    sqlx::query("DELETE FROM channel_categories WHERE id = $1")
+        .bind(category_id)
+        .execute(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    notify_reign(pool, reign_id).await;
+
+    Ok(())
+}
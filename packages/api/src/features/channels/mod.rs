@@ -0,0 +1,150 @@
+pub mod create;
+pub mod create_category;
+pub mod delete;
+pub mod delete_category;
+pub mod update;
+
+use serde::{Deserialize, Serialize};
+
+use crate::permissions::Permissions;
+
+#[cfg(feature = "server")]
+use dioxus::prelude::ServerFnError;
+
+/// Longest accepted channel topic, in characters.
+pub const MAX_TOPIC_CHARS: usize = 1024;
+/// How many channels one Reign may have.
+pub const MAX_CHANNELS_PER_REIGN: i64 = 500;
+
+/// A group of channels in a Reign's sidebar.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChannelCategory {
+    pub id: String,
+    pub name: String,
+    pub position: i32,
+}
+
+/// A text channel. Its id is a conversation id, so history, posting, edits,
+/// reactions and read markers all go through the regular message endpoints.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Channel {
+    pub id: String,
+    pub reign_id: String,
+    pub category_id: Option<String>,
+    pub name: String,
+    pub topic: Option<String>,
+    pub position: i32,
+    pub last_message_at: String,
+    /// Messages from others since the caller's read marker.
+    pub unread_count: i64,
+    /// What the caller may do here, after role overwrites.
+    pub permissions: Permissions,
+}
+
+/// Selects a [`Channel`] as seen by the user bound to `$1`. Callers append their own `WHERE`.
+#[cfg(feature = "server")]
+pub(crate) const CHANNEL_SELECT: &str =
+    "SELECT c.id, c.reign_id, c.category_id, c.name, c.topic, c.position, c.last_message_at,
+            (SELECT COUNT(*) FROM messages um
+             WHERE um.conversation_id = c.id AND um.sender_id <> $1 AND um.deleted_at IS NULL
+               AND (cm.last_read_message_at IS NULL OR um.created_at > cm.last_read_message_at)) AS unread_count
+     FROM conversations c
+     LEFT JOIN conversation_members cm ON cm.conversation_id = c.id AND cm.user_id = $1";
+
+#[cfg(feature = "server")]
+#[derive(sqlx::FromRow)]
+pub(crate) struct ChannelRow {
+    pub(crate) id: uuid::Uuid,
+    reign_id: uuid::Uuid,
+    category_id: Option<uuid::Uuid>,
+    name: String,
+    topic: Option<String>,
+    position: i32,
+    last_message_at: chrono::DateTime<chrono::Utc>,
+    unread_count: i64,
+}
+
+#[cfg(feature = "server")]
+pub(crate) fn channel_from_row(r: ChannelRow, permissions: Permissions) -> Channel {
+    Channel {
+        id: r.id.to_string(),
+        reign_id: r.reign_id.to_string(),
+        category_id: r.category_id.map(|id| id.to_string()),
+        name: r.name,
+        topic: r.topic,
+        position: r.position,
+        last_message_at: r.last_message_at.to_rfc3339(),
+        unread_count: r.unread_count,
+        permissions,
+    }
+}
+
+#[cfg(feature = "server")]
+pub(crate) async fn fetch_channel(
+    pool: &sqlx::PgPool,
+    viewer_id: uuid::Uuid,
+    channel_id: uuid::Uuid,
+) -> Result<Channel, ServerFnError> {
+    let permissions = crate::features::roles::require_permission(pool, channel_id, viewer_id, Permissions::NONE).await?;
+
+    sqlx::query_as::<_, ChannelRow>(&format!("{CHANNEL_SELECT} WHERE c.id = $2"))
+        .bind(viewer_id)
+        .bind(channel_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .map(|row| channel_from_row(row, permissions))
+        .ok_or_else(|| ServerFnError::new("Channel not found"))
+}
+
+/// The Reign a channel belongs to.
+#[cfg(feature = "server")]
+pub(crate) async fn channel_reign(pool: &sqlx::PgPool, channel_id: &str) -> Result<(uuid::Uuid, uuid::Uuid), ServerFnError> {
+    let channel_id: uuid::Uuid = channel_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid channel_id: {e}")))?;
+
+    let reign_id = sqlx::query_scalar::<_, uuid::Uuid>("SELECT reign_id FROM conversations WHERE id = $1 AND kind = 'channel'")
+        .bind(channel_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .ok_or_else(|| ServerFnError::new("Channel not found"))?;
+
+    Ok((channel_id, reign_id))
+}
+
+/// Check that `category_id` belongs to `reign_id`.
+#[cfg(feature = "server")]
+pub(crate) async fn parse_category(
+    pool: &sqlx::PgPool,
+    reign_id: uuid::Uuid,
+    category_id: &str,
+) -> Result<uuid::Uuid, ServerFnError> {
+    let category_id: uuid::Uuid = category_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid category_id: {e}")))?;
+
+    let exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM channel_categories WHERE id = $1 AND reign_id = $2)",
+    )
+    .bind(category_id)
+    .bind(reign_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if !exists {
+        return Err(ServerFnError::new("Category not found"));
+    }
+    Ok(category_id)
+}
+
+#[cfg(feature = "server")]
+pub(crate) fn validate_topic(topic: &str) -> Result<Option<String>, ServerFnError> {
+    let topic = topic.trim();
+    if topic.chars().count() > MAX_TOPIC_CHARS {
+        return Err(ServerFnError::new(format!("Topic is limited to {MAX_TOPIC_CHARS} characters")));
+    }
+    Ok((!topic.is_empty()).then(|| topic.to_string()))
+}
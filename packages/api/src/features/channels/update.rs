@@ -0,0 +1,74 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::Channel;
+
+/// Fields left as `None` are unchanged.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct UpdateChannelRequest {
+    pub token: String,
+    pub channel_id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// An empty topic clears it.
+    #[serde(default)]
+    pub topic: Option<String>,
+    /// Move to this category; an empty string moves the channel out of any category.
+    #[serde(default)]
+    pub category_id: Option<String>,
+    #[serde(default)]
+    pub position: Option<i32>,
+}
+
+#[post("/api/channels/update")]
+pub async fn update_channel(req: UpdateChannelRequest) -> Result<Channel, ServerFnError> {
+    use super::{channel_reign, fetch_channel, parse_category, validate_topic};
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::conversations::notify_all_summaries;
+    use crate::features::reigns::{notify_reign, validate_name};
+    use crate::features::roles::require_permission;
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    let pool = db::pool().await;
+    let (channel_id, reign_id) = channel_reign(pool, &req.channel_id).await?;
+    require_permission(pool, channel_id, user_id, Permissions::MANAGE_CHANNELS).await?;
+
+    let name = req.name.as_deref().map(|n| validate_name(n, "Channel")).transpose()?;
+    let topic = req.topic.as_deref().map(validate_topic).transpose()?;
+    let category_id = match req.category_id.as_deref() {
+        Some("") => Some(None),
+        Some(id) => Some(Some(parse_category(pool, reign_id, id).await?)),
+        None => None,
+    };
+
+    sqlx::query(
+        "UPDATE conversations SET
+             name = COALESCE($2, name),
+             topic = CASE WHEN $3 THEN $4 ELSE topic END,
+             category_id = CASE WHEN $5 THEN $6 ELSE category_id END,
+             position = COALESCE($7, position),
+             updated_at = NOW()
+         WHERE id = $1",
+    )
+    .bind(channel_id)
+    .bind(&name)
+    .bind(topic.is_some())
+    .bind(topic.flatten())
+    .bind(category_id.is_some())
+    .bind(category_id.flatten())
+    .bind(req.position)
+    .execute(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    notify_reign(pool, reign_id).await;
+    if name.is_some() {
+        notify_all_summaries(pool, channel_id);
+    }
+
+    fetch_channel(pool, user_id, channel_id).await
+}
@@ -0,0 +1,34 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Names a member of a group conversation; shared by add and remove.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MemberRequest {
+    pub token: String,
+    pub conversation_id: String,
+    pub user_id: String,
+}
+
+/// Any member may add people to a group. New members start with everything
+/// sent before they joined marked as read.
+#[post("/api/conversations/add_member")]
+pub async fn add_member(req: MemberRequest) -> Result<(), ServerFnError> {
+    use super::{add_group_member, require_group_member};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let conversation_id: uuid::Uuid = req
+        .conversation_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid conversation_id: {e}")))?;
+    let member_id: uuid::Uuid = req
+        .user_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid user_id: {e}")))?;
+
+    let pool = db::pool().await;
+    require_group_member(pool, conversation_id, user_id).await?;
+    add_group_member(pool, conversation_id, member_id, user_id).await
+}
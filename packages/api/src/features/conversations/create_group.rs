@@ -0,0 +1,88 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::list::ConversationSummary;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CreateGroupRequest {
+    pub token: String,
+    pub name: String,
+    /// Users to add alongside the caller, who becomes the group's owner.
+    pub member_ids: Vec<String>,
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+}
+
+#[post("/api/conversations/create_group")]
+pub async fn create_group(req: CreateGroupRequest) -> Result<ConversationSummary, ServerFnError> {
+    use super::{invalidate_members, notify_all_summaries, summary_for, validate_avatar_url, validate_group_name, MAX_GROUP_MEMBERS};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    let name = validate_group_name(&req.name)?;
+    if let Some(url) = &req.avatar_url {
+        validate_avatar_url(url)?;
+    }
+
+    let mut member_ids = req
+        .member_ids
+        .iter()
+        .map(|id| id.parse())
+        .collect::<Result<Vec<uuid::Uuid>, _>>()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid member id: {e}")))?;
+    member_ids.retain(|id| *id != user_id);
+    member_ids.sort();
+    member_ids.dedup();
+
+    if member_ids.len() + 1 > MAX_GROUP_MEMBERS {
+        return Err(ServerFnError::new(format!("Groups are limited to {MAX_GROUP_MEMBERS} members")));
+    }
+
+    let pool = db::pool().await;
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let conversation_id = sqlx::query_scalar::<_, uuid::Uuid>(
+        "INSERT INTO conversations (kind, name, avatar_url, created_by) VALUES ('group', $1, $2, $3) RETURNING id",
+    )
+    .bind(&name)
+    .bind(&req.avatar_url)
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    sqlx::query("INSERT INTO conversation_members (conversation_id, user_id, role) VALUES ($1, $2, 'owner')")
+        .bind(conversation_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let added = sqlx::query(
+        "INSERT INTO conversation_members (conversation_id, user_id)
+         SELECT $1, id FROM users WHERE id = ANY($2)",
+    )
+    .bind(conversation_id)
+    .bind(&member_ids)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if added.rows_affected() != member_ids.len() as u64 {
+        return Err(ServerFnError::new("One or more members not found"));
+    }
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    // Every member, the caller's other devices included, learns about the group from its summary
+    invalidate_members(conversation_id);
+    notify_all_summaries(pool, conversation_id);
+
+    summary_for(pool, user_id, conversation_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .ok_or_else(|| ServerFnError::new("Conversation not found"))
+}
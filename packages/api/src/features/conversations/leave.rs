@@ -0,0 +1,78 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LeaveConversationRequest {
+    pub token: String,
+    pub conversation_id: String,
+}
+
+/// Leave a group. If the owner leaves, the longest-standing admin (or, failing
+/// that, member) takes over; the last member leaving deletes the group and its messages.
+#[post("/api/conversations/leave")]
+pub async fn leave_conversation(req: LeaveConversationRequest) -> Result<(), ServerFnError> {
+    use super::{announce_membership, require_group_member};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let conversation_id: uuid::Uuid = req
+        .conversation_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid conversation_id: {e}")))?;
+
+    let pool = db::pool().await;
+    require_group_member(pool, conversation_id, user_id).await?;
+
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    // Lock the group so two departures can't both skip the ownership hand-over
+    sqlx::query("SELECT id FROM conversations WHERE id = $1 FOR UPDATE")
+        .bind(conversation_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let role = sqlx::query_scalar::<_, String>(
+        "DELETE FROM conversation_members WHERE conversation_id = $1 AND user_id = $2 RETURNING role",
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?
+    .ok_or_else(|| ServerFnError::new("Conversation not found"))?;
+
+    let remaining = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM conversation_members WHERE conversation_id = $1")
+        .bind(conversation_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if remaining == 0 {
+        sqlx::query("DELETE FROM conversations WHERE id = $1")
+            .bind(conversation_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ServerFnError::new(e.to_string()))?;
+    } else if role == "owner" {
+        sqlx::query(
+            "UPDATE conversation_members SET role = 'owner'
+             WHERE conversation_id = $1 AND user_id = (
+                 SELECT user_id FROM conversation_members WHERE conversation_id = $1
+                 ORDER BY (role = 'admin') DESC, joined_at ASC LIMIT 1
+             )",
+        )
+        .bind(conversation_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    }
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    announce_membership(pool, conversation_id, user_id, user_id, false).await;
+
+    Ok(())
+}
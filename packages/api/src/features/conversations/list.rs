@@ -0,0 +1,74 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ListConversationsRequest {
+    pub token: String,
+    /// Page size, defaults to 20 and is capped at 100.
+    pub limit: Option<i64>,
+    /// Only return conversations whose last activity is older than this
+    /// RFC 3339 timestamp (the `last_message_at` of the previous page's last entry).
+    pub before: Option<String>,
+}
+
+/// One entry in a user's inbox.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ConversationSummary {
+    pub conversation_id: String,
+    pub is_group: bool,
+    /// Set for Reign channels, which are left out of the inbox but still
+    /// receive `conversation_updated` events for unread badges.
+    pub reign_id: Option<String>,
+    /// Channel or group name, or the other user's username for direct conversations.
+    pub title: String,
+    pub avatar_url: Option<String>,
+    /// The counterpart in a direct conversation; `None` for groups.
+    pub other_user_id: Option<String>,
+    pub member_count: i64,
+    pub last_message_id: Option<String>,
+    pub last_message_sender_id: Option<String>,
+    pub last_message_preview: Option<String>,
+    pub last_message_at: String,
+    /// The caller's read marker in this conversation.
+    pub last_read_message_id: Option<String>,
+    /// Seconds new messages live before disappearing; `None` keeps them.
+    pub message_ttl_secs: Option<i32>,
+    /// The counterpart's read marker in a direct conversation, used to show
+    /// "Seen" on the caller's messages.
+    pub other_last_read_message_id: Option<String>,
+    pub unread_count: i64,
+}
+
+#[post("/api/conversations/list")]
+pub async fn list_conversations(req: ListConversationsRequest) -> Result<Vec<ConversationSummary>, ServerFnError> {
+    use super::{summary_from_row, SummaryRow, SUMMARY_SELECT};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    let limit = req.limit.unwrap_or(20).clamp(1, 100);
+    let before = req
+        .before
+        .as_deref()
+        .map(chrono::DateTime::parse_from_rfc3339)
+        .transpose()
+        .map_err(|e| ServerFnError::new(format!("Invalid before: {e}")))?
+        .map(|t| t.with_timezone(&chrono::Utc));
+
+    let rows = sqlx::query_as::<_, SummaryRow>(&format!(
+        "{SUMMARY_SELECT}
+         WHERE cm.user_id = $1 AND c.kind <> 'channel' AND ($2::timestamptz IS NULL OR c.last_message_at < $2)
+         ORDER BY c.last_message_at DESC
+         LIMIT $3"
+    ))
+    .bind(user_id)
+    .bind(before)
+    .bind(limit)
+    .fetch_all(db::pool().await)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(rows.into_iter().map(summary_from_row).collect())
+}
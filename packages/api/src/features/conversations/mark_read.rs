@@ -0,0 +1,43 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MarkReadRequest {
+    pub token: String,
+    pub conversation_id: String,
+    /// The newest message the caller has seen in the conversation.
+    pub message_id: String,
+}
+
+/// Sent to the other members when someone reads up to `last_read_message_id`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReadReceipt {
+    pub reader_id: String,
+    pub conversation_id: String,
+    pub last_read_message_id: String,
+    pub read_at: String,
+}
+
+/// Returns the new receipt, or `None` if the marker was already at or past `message_id`.
+#[post("/api/conversations/mark_read")]
+pub async fn mark_conversation_read(req: MarkReadRequest) -> Result<Option<ReadReceipt>, ServerFnError> {
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::roles::require_permission;
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let conversation_id: uuid::Uuid = req
+        .conversation_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid conversation_id: {e}")))?;
+    let message_id: uuid::Uuid = req
+        .message_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid message_id: {e}")))?;
+
+    let pool = db::pool().await;
+    require_permission(pool, conversation_id, user_id, Permissions::VIEW_CHANNEL).await?;
+    super::mark_read(pool, user_id, conversation_id, message_id).await
+}
@@ -0,0 +1,55 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ListMembersRequest {
+    pub token: String,
+    pub conversation_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ConversationMember {
+    pub user_id: String,
+    pub username: String,
+    /// `owner`, `admin` or `member`.
+    pub role: String,
+    pub joined_at: String,
+}
+
+#[post("/api/conversations/members")]
+pub async fn list_members(req: ListMembersRequest) -> Result<Vec<ConversationMember>, ServerFnError> {
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let conversation_id: uuid::Uuid = req
+        .conversation_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid conversation_id: {e}")))?;
+
+    let pool = db::pool().await;
+    super::require_member(pool, conversation_id, user_id).await?;
+
+    let rows = sqlx::query_as::<_, (uuid::Uuid, String, String, chrono::DateTime<chrono::Utc>)>(
+        "SELECT u.id, u.username, cm.role, cm.joined_at
+         FROM conversation_members cm
+         JOIN users u ON u.id = cm.user_id
+         WHERE cm.conversation_id = $1
+         ORDER BY cm.joined_at ASC",
+    )
+    .bind(conversation_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, username, role, joined_at)| ConversationMember {
+            user_id: id.to_string(),
+            username,
+            role,
+            joined_at: joined_at.to_rfc3339(),
+        })
+        .collect())
+}
@@ -0,0 +1,540 @@
+pub mod add_member;
+pub mod create_group;
+pub mod leave;
+pub mod list;
+pub mod mark_read;
+pub mod members;
+pub mod remove_member;
+pub mod rename;
+pub mod set_avatar;
+pub mod set_message_ttl;
+
+#[cfg(feature = "server")]
+use std::sync::{Arc, OnceLock};
+
+#[cfg(feature = "server")]
+use dioxus::prelude::ServerFnError;
+#[cfg(feature = "server")]
+use list::ConversationSummary;
+#[cfg(feature = "server")]
+use mark_read::ReadReceipt;
+
+/// Largest number of members a group conversation may have.
+pub const MAX_GROUP_MEMBERS: usize = 100;
+/// Longest accepted group name, in characters.
+pub const MAX_GROUP_NAME_CHARS: usize = 100;
+
+/// Maximum number of characters of the last message shown in the inbox.
+#[cfg(feature = "server")]
+const PREVIEW_CHARS: usize = 100;
+
+/// Selects one inbox entry per `conversation_members cm` row, from that
+/// member's point of view.
+#[cfg(feature = "server")]
+pub(crate) const SUMMARY_SELECT: &str =
+    "SELECT cm.user_id AS member_id, c.id AS conversation_id, c.kind = 'group' AS is_group, c.reign_id,
+            COALESCE(c.name, other.username, 'Conversation') AS title, c.avatar_url,
+            other.id AS other_user_id,
+            (SELECT COUNT(*) FROM conversation_members mc WHERE mc.conversation_id = c.id) AS member_count,
+            m.id AS last_message_id, m.sender_id AS last_message_sender_id,
+            CASE WHEN m.deleted_at IS NOT NULL OR h.message_id IS NOT NULL OR m.expires_at <= NOW() THEN NULL
+                 WHEN m.kind = 'pin_notice' THEN 'Pinned a message'
+                 ELSE COALESCE(m.content_plain, m.content) END AS last_message_preview,
+            c.last_message_at,
+            cm.last_read_message_id,
+            c.message_ttl_secs,
+            other.last_read_message_id AS other_last_read_message_id,
+            (SELECT COUNT(*) FROM messages um
+             WHERE um.conversation_id = c.id AND um.sender_id <> cm.user_id AND um.deleted_at IS NULL
+               AND (um.expires_at IS NULL OR um.expires_at > NOW())
+               AND (cm.last_read_message_at IS NULL OR um.created_at > cm.last_read_message_at)) AS unread_count
+     FROM conversation_members cm
+     JOIN conversations c ON c.id = cm.conversation_id
+     LEFT JOIN LATERAL (
+         SELECT u.id, u.username, om.last_read_message_id
+         FROM conversation_members om
+         JOIN users u ON u.id = om.user_id
+         WHERE c.kind = 'direct' AND om.conversation_id = c.id AND om.user_id <> cm.user_id
+         LIMIT 1
+     ) AS other ON TRUE
+     LEFT JOIN messages m ON m.id = c.last_message_id
+     LEFT JOIN message_hidden h ON h.message_id = m.id AND h.user_id = cm.user_id";
+
+#[cfg(feature = "server")]
+#[derive(sqlx::FromRow)]
+pub(crate) struct SummaryRow {
+    member_id: uuid::Uuid,
+    conversation_id: uuid::Uuid,
+    is_group: bool,
+    reign_id: Option<uuid::Uuid>,
+    title: String,
+    avatar_url: Option<String>,
+    other_user_id: Option<uuid::Uuid>,
+    member_count: i64,
+    last_message_id: Option<uuid::Uuid>,
+    last_message_sender_id: Option<uuid::Uuid>,
+    last_message_preview: Option<String>,
+    last_message_at: chrono::DateTime<chrono::Utc>,
+    last_read_message_id: Option<uuid::Uuid>,
+    message_ttl_secs: Option<i32>,
+    other_last_read_message_id: Option<uuid::Uuid>,
+    unread_count: i64,
+}
+
+#[cfg(feature = "server")]
+pub(crate) fn summary_from_row(r: SummaryRow) -> ConversationSummary {
+    ConversationSummary {
+        conversation_id: r.conversation_id.to_string(),
+        is_group: r.is_group,
+        reign_id: r.reign_id.map(|id| id.to_string()),
+        title: r.title,
+        avatar_url: r.avatar_url,
+        other_user_id: r.other_user_id.map(|id| id.to_string()),
+        member_count: r.member_count,
+        last_message_id: r.last_message_id.map(|id| id.to_string()),
+        last_message_sender_id: r.last_message_sender_id.map(|id| id.to_string()),
+        last_message_preview: r.last_message_preview.map(|c| c.chars().take(PREVIEW_CHARS).collect()),
+        last_message_at: r.last_message_at.to_rfc3339(),
+        last_read_message_id: r.last_read_message_id.map(|id| id.to_string()),
+        message_ttl_secs: r.message_ttl_secs,
+        other_last_read_message_id: r.other_last_read_message_id.map(|id| id.to_string()),
+        unread_count: r.unread_count,
+    }
+}
+
+/// `dm_key` of the direct conversation between two users, independent of order.
+#[cfg(feature = "server")]
+fn dm_key(a: uuid::Uuid, b: uuid::Uuid) -> String {
+    format!("{}:{}", a.min(b), a.max(b))
+}
+
+/// Get the direct conversation between two users, creating it (and both
+/// memberships) on first contact.
+#[cfg(feature = "server")]
+pub(crate) async fn direct_conversation(
+    pool: &sqlx::PgPool,
+    a: uuid::Uuid,
+    b: uuid::Uuid,
+) -> Result<uuid::Uuid, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let id = sqlx::query_scalar::<_, uuid::Uuid>(
+        "INSERT INTO conversations (kind, dm_key, created_by) VALUES ('direct', $1, $2)
+         ON CONFLICT (dm_key) DO UPDATE SET dm_key = EXCLUDED.dm_key
+         RETURNING id",
+    )
+    .bind(dm_key(a, b))
+    .bind(a)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO conversation_members (conversation_id, user_id)
+         SELECT $1, user_id FROM UNNEST($2::uuid[]) AS user_id
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(id)
+    .bind(vec![a, b])
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(id)
+}
+
+/// Resolve a request addressed either by `conversation_id` or, for direct
+/// messages, by the other user's id, checking that the caller holds `needed` there.
+///
+/// Returns `None` only when no direct conversation exists yet and
+/// `create_direct` is false.
+#[cfg(feature = "server")]
+pub(crate) async fn resolve_conversation(
+    pool: &sqlx::PgPool,
+    user_id: uuid::Uuid,
+    conversation_id: Option<&str>,
+    other_user_id: Option<&str>,
+    create_direct: bool,
+    needed: crate::permissions::Permissions,
+) -> Result<Option<uuid::Uuid>, ServerFnError> {
+    if let Some(conversation_id) = conversation_id {
+        let conversation_id: uuid::Uuid = conversation_id
+            .parse()
+            .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid conversation_id: {e}")))?;
+        crate::features::roles::require_permission(pool, conversation_id, user_id, needed).await?;
+        return Ok(Some(conversation_id));
+    }
+
+    let other_id: uuid::Uuid = other_user_id
+        .ok_or_else(|| ServerFnError::new("Either conversation_id or a user id is required"))?
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid user id: {e}")))?;
+
+    let id = if create_direct {
+        Some(direct_conversation(pool, user_id, other_id).await)
+    } else {
+        sqlx::query_scalar::<_, uuid::Uuid>("SELECT id FROM conversations WHERE dm_key = $1")
+            .bind(dm_key(user_id, other_id))
+            .fetch_optional(pool)
+            .await
+            .transpose()
+    };
+
+    id.transpose().map_err(|e| ServerFnError::new(e.to_string()))
+}
+
+/// The caller's role in a conversation, or an error if they aren't a member.
+#[cfg(feature = "server")]
+pub(crate) async fn require_member(
+    pool: &sqlx::PgPool,
+    conversation_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+) -> Result<String, ServerFnError> {
+    sqlx::query_scalar::<_, String>(
+        "SELECT role FROM conversation_members WHERE conversation_id = $1 AND user_id = $2",
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?
+    .ok_or_else(|| ServerFnError::new("Conversation not found"))
+}
+
+/// Like [`require_member`], but also rejects direct conversations, whose
+/// membership and metadata are fixed, and Reign channels, which are managed
+/// through the Reign.
+#[cfg(feature = "server")]
+pub(crate) async fn require_group_member(
+    pool: &sqlx::PgPool,
+    conversation_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+) -> Result<String, ServerFnError> {
+    let role = require_member(pool, conversation_id, user_id).await?;
+
+    let is_group = sqlx::query_scalar::<_, bool>("SELECT kind = 'group' FROM conversations WHERE id = $1")
+        .bind(conversation_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if !is_group {
+        return Err(ServerFnError::new("Only group conversations can be changed"));
+    }
+
+    Ok(role)
+}
+
+#[cfg(feature = "server")]
+type MemberCache = dashmap::DashMap<uuid::Uuid, Arc<Vec<uuid::Uuid>>>;
+
+#[cfg(feature = "server")]
+static MEMBER_CACHE: OnceLock<MemberCache> = OnceLock::new();
+
+#[cfg(feature = "server")]
+fn member_cache() -> &'static MemberCache {
+    MEMBER_CACHE.get_or_init(dashmap::DashMap::new)
+}
+
+/// Ids of everyone who can view a conversation, cached for WebSocket fan-out.
+/// Anything that changes membership, roles, or who can view a channel, must
+/// call [`invalidate_members`].
+#[cfg(feature = "server")]
+pub(crate) async fn member_ids(
+    pool: &sqlx::PgPool,
+    conversation_id: uuid::Uuid,
+) -> Result<Arc<Vec<uuid::Uuid>>, sqlx::Error> {
+    if let Some(members) = member_cache().get(&conversation_id) {
+        return Ok(members.clone());
+    }
+
+    let members = Arc::new(
+        crate::features::roles::member_permissions(pool, conversation_id, None)
+            .await?
+            .into_iter()
+            .filter(|(_, permissions)| permissions.contains(crate::permissions::Permissions::VIEW_CHANNEL))
+            .map(|(member, _)| member)
+            .collect::<Vec<_>>(),
+    );
+    member_cache().insert(conversation_id, members.clone());
+
+    Ok(members)
+}
+
+/// The cached audience of a conversation, without going to the database.
+#[cfg(feature = "server")]
+pub(crate) fn cached_member_ids(conversation_id: uuid::Uuid) -> Option<Arc<Vec<uuid::Uuid>>> {
+    member_cache().get(&conversation_id).map(|members| members.clone())
+}
+
+#[cfg(feature = "server")]
+pub(crate) fn invalidate_members(conversation_id: uuid::Uuid) {
+    member_cache().remove(&conversation_id);
+    crate::features::roles::invalidate_permissions(conversation_id);
+}
+
+/// Send an event to every member of a conversation, with any attachment
+/// links in it signed for each member.
+#[cfg(feature = "server")]
+pub(crate) async fn broadcast(pool: &sqlx::PgPool, conversation_id: uuid::Uuid, event: &crate::events::WsEvent) {
+    match member_ids(pool, conversation_id).await {
+        Ok(members) => {
+            for member in members.iter() {
+                crate::ws::send_event(*member, &crate::features::attachments::event_for(event, *member));
+            }
+        }
+        Err(e) => println!("Broadcast to conversation {conversation_id} failed: {e}"),
+    }
+}
+
+/// Record a new message as the conversation's last activity.
+#[cfg(feature = "server")]
+pub(crate) async fn record_message(
+    conn: &mut sqlx::PgConnection,
+    conversation_id: uuid::Uuid,
+    message_id: uuid::Uuid,
+    created_at: chrono::DateTime<chrono::Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE conversations SET last_message_id = $2, last_message_at = $3, updated_at = NOW() WHERE id = $1",
+    )
+    .bind(conversation_id)
+    .bind(message_id)
+    .bind(created_at)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// `user_id`'s current summary of a conversation, if they are a member.
+#[cfg(feature = "server")]
+pub(crate) async fn summary_for(
+    pool: &sqlx::PgPool,
+    user_id: uuid::Uuid,
+    conversation_id: uuid::Uuid,
+) -> Result<Option<ConversationSummary>, sqlx::Error> {
+    let row = sqlx::query_as::<_, SummaryRow>(&format!(
+        "{SUMMARY_SELECT} WHERE cm.user_id = $1 AND cm.conversation_id = $2"
+    ))
+    .bind(user_id)
+    .bind(conversation_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(summary_from_row))
+}
+
+/// Push `user_id`'s current summary of a conversation over WebSocket.
+#[cfg(feature = "server")]
+pub(crate) async fn notify_summary(pool: &sqlx::PgPool, user_id: uuid::Uuid, conversation_id: uuid::Uuid) {
+    if let Ok(Some(summary)) = summary_for(pool, user_id, conversation_id).await {
+        crate::ws::send_event(user_id, &crate::events::WsEvent::ConversationUpdated(summary));
+    }
+}
+
+/// Push every member their own refreshed summary of a conversation. The
+/// summaries are fetched in one query from a background task, so a large
+/// Reign doesn't hold up the request that changed the conversation.
+#[cfg(feature = "server")]
+pub(crate) fn notify_all_summaries(pool: &sqlx::PgPool, conversation_id: uuid::Uuid) {
+    let pool = pool.clone();
+    tokio::spawn(async move {
+        let summaries = match member_ids(&pool, conversation_id).await {
+            Ok(members) => {
+                sqlx::query_as::<_, SummaryRow>(&format!(
+                    "{SUMMARY_SELECT} WHERE cm.conversation_id = $1 AND cm.user_id = ANY($2)"
+                ))
+                .bind(conversation_id)
+                .bind(members.as_slice())
+                .fetch_all(&pool)
+                .await
+            }
+            Err(e) => Err(e),
+        };
+
+        match summaries {
+            Ok(rows) => {
+                for row in rows {
+                    let member = row.member_id;
+                    let event = crate::events::WsEvent::ConversationUpdated(summary_from_row(row));
+                    crate::ws::send_event(member, &event);
+                }
+            }
+            Err(e) => println!("Refreshing summaries of conversation {conversation_id} failed: {e}"),
+        }
+    });
+}
+
+/// Tell members about someone joining or leaving, including the departed
+/// user's own devices, and refresh everyone's summary.
+#[cfg(feature = "server")]
+pub(crate) async fn announce_membership(
+    pool: &sqlx::PgPool,
+    conversation_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+    actor_id: uuid::Uuid,
+    joined: bool,
+) {
+    use crate::events::{MembershipChange, WsEvent};
+
+    invalidate_members(conversation_id);
+
+    let event = WsEvent::MembershipChanged(MembershipChange {
+        conversation_id: conversation_id.to_string(),
+        user_id: user_id.to_string(),
+        actor_id: actor_id.to_string(),
+        joined,
+    });
+    broadcast(pool, conversation_id, &event).await;
+    if !joined {
+        crate::ws::send_event(user_id, &event);
+    }
+    notify_all_summaries(pool, conversation_id);
+}
+
+/// Add `member_id` to a group on behalf of `actor_id` and announce it. The new
+/// member starts with everything sent before they joined marked as read.
+/// Adding an existing member is a no-op.
+#[cfg(feature = "server")]
+pub(crate) async fn add_group_member(
+    pool: &sqlx::PgPool,
+    conversation_id: uuid::Uuid,
+    member_id: uuid::Uuid,
+    actor_id: uuid::Uuid,
+) -> Result<(), ServerFnError> {
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    // Lock the group so concurrent adds can't overshoot the size cap
+    sqlx::query("SELECT id FROM conversations WHERE id = $1 FOR UPDATE")
+        .bind(conversation_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let result = sqlx::query(
+        "INSERT INTO conversation_members (conversation_id, user_id, last_read_message_id, last_read_message_at)
+         SELECT c.id, u.id, c.last_message_id, c.last_message_at
+         FROM conversations c, users u
+         WHERE c.id = $1 AND u.id = $2
+           AND (SELECT COUNT(*) FROM conversation_members WHERE conversation_id = c.id) < $3
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(conversation_id)
+    .bind(member_id)
+    .bind(MAX_GROUP_MEMBERS as i64)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        let (exists, is_member) = sqlx::query_as::<_, (bool, bool)>(
+            "SELECT EXISTS(SELECT 1 FROM users WHERE id = $2),
+                    EXISTS(SELECT 1 FROM conversation_members WHERE conversation_id = $1 AND user_id = $2)",
+        )
+        .bind(conversation_id)
+        .bind(member_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+        return match (exists, is_member) {
+            (false, _) => Err(ServerFnError::new("User not found")),
+            (true, true) => Ok(()),
+            (true, false) => Err(ServerFnError::new(format!("Groups are limited to {MAX_GROUP_MEMBERS} members"))),
+        };
+    }
+
+    announce_membership(pool, conversation_id, member_id, actor_id, true).await;
+
+    Ok(())
+}
+
+/// Advance `user_id`'s read marker in a conversation to `message_id`.
+///
+/// Markers only move forward; returns `None` when the message is not newer than
+/// the current marker. On success a read receipt is pushed to the other members
+/// and the reader's own sockets receive a refreshed summary.
+#[cfg(feature = "server")]
+pub(crate) async fn mark_read(
+    pool: &sqlx::PgPool,
+    user_id: uuid::Uuid,
+    conversation_id: uuid::Uuid,
+    message_id: uuid::Uuid,
+) -> Result<Option<ReadReceipt>, ServerFnError> {
+    use crate::events::WsEvent;
+
+    let created_at = sqlx::query_scalar::<_, chrono::DateTime<chrono::Utc>>(
+        "SELECT created_at FROM messages WHERE id = $1 AND conversation_id = $2",
+    )
+    .bind(message_id)
+    .bind(conversation_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?
+    .ok_or_else(|| ServerFnError::new("Message not found in this conversation"))?;
+
+    let result = sqlx::query(
+        "UPDATE conversation_members SET last_read_message_id = $3, last_read_message_at = $4
+         WHERE conversation_id = $1 AND user_id = $2
+           AND (last_read_message_at IS NULL OR last_read_message_at < $4)",
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .bind(message_id)
+    .bind(created_at)
+    .execute(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Ok(None);
+    }
+
+    let receipt = ReadReceipt {
+        reader_id: user_id.to_string(),
+        conversation_id: conversation_id.to_string(),
+        last_read_message_id: message_id.to_string(),
+        read_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let members = member_ids(pool, conversation_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    for member in members.iter().filter(|m| **m != user_id) {
+        crate::ws::send_event(*member, &WsEvent::MessagesRead(receipt.clone()));
+    }
+    notify_summary(pool, user_id, conversation_id).await;
+
+    Ok(Some(receipt))
+}
+
+/// Trim a group name and check it is non-empty and within [`MAX_GROUP_NAME_CHARS`].
+#[cfg(feature = "server")]
+pub(crate) fn validate_group_name(name: &str) -> Result<String, ServerFnError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ServerFnError::new("Group name cannot be empty"));
+    }
+    if name.chars().count() > MAX_GROUP_NAME_CHARS {
+        return Err(ServerFnError::new(format!("Group name is limited to {MAX_GROUP_NAME_CHARS} characters")));
+    }
+    Ok(name.to_string())
+}
+
+/// Avatars are links to images hosted elsewhere; only plain http(s) URLs are accepted.
+#[cfg(feature = "server")]
+pub(crate) fn validate_avatar_url(url: &str) -> Result<(), ServerFnError> {
+    const MAX_AVATAR_URL_BYTES: usize = 2048;
+
+    let valid = url.len() <= MAX_AVATAR_URL_BYTES
+        && (url.starts_with("https://") || url.starts_with("http://"))
+        && !url.chars().any(|c| c.is_whitespace() || c.is_control());
+    if valid {
+        Ok(())
+    } else {
+        Err(ServerFnError::new("Avatar must be an http(s) URL"))
+    }
+}
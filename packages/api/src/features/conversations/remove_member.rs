@@ -0,0 +1,53 @@
+use dioxus::prelude::*;
+
+use super::add_member::MemberRequest;
+
+/// Owners and admins may remove other members, but nobody can remove the owner.
+/// Members leave on their own through `leave_conversation`.
+#[post("/api/conversations/remove_member")]
+pub async fn remove_member(req: MemberRequest) -> Result<(), ServerFnError> {
+    use super::{announce_membership, require_group_member};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let conversation_id: uuid::Uuid = req
+        .conversation_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid conversation_id: {e}")))?;
+    let member_id: uuid::Uuid = req
+        .user_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid user_id: {e}")))?;
+
+    if member_id == user_id {
+        return Err(ServerFnError::new("Use leave to remove yourself"));
+    }
+
+    let pool = db::pool().await;
+    let role = require_group_member(pool, conversation_id, user_id).await?;
+    if role == "member" {
+        return Err(ServerFnError::new("Only owners and admins can remove members"));
+    }
+
+    // Admins can't remove each other; only the owner can
+    let removable = if role == "owner" { vec!["admin", "member"] } else { vec!["member"] };
+    let result = sqlx::query(
+        "DELETE FROM conversation_members WHERE conversation_id = $1 AND user_id = $2 AND role = ANY($3)",
+    )
+    .bind(conversation_id)
+    .bind(member_id)
+    .bind(&removable)
+    .execute(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if result.rows_affected() == 0 {
+        return Err(ServerFnError::new("Member not found or cannot be removed"));
+    }
+
+    announce_membership(pool, conversation_id, member_id, user_id, false).await;
+
+    Ok(())
+}
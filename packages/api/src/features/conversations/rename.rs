@@ -0,0 +1,39 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RenameConversationRequest {
+    pub token: String,
+    pub conversation_id: String,
+    pub name: String,
+}
+
+/// Any member may rename a group. Members receive a `conversation_updated` event.
+#[post("/api/conversations/rename")]
+pub async fn rename_conversation(req: RenameConversationRequest) -> Result<(), ServerFnError> {
+    use super::{notify_all_summaries, require_group_member, validate_group_name};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let conversation_id: uuid::Uuid = req
+        .conversation_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid conversation_id: {e}")))?;
+    let name = validate_group_name(&req.name)?;
+
+    let pool = db::pool().await;
+    require_group_member(pool, conversation_id, user_id).await?;
+
+    sqlx::query("UPDATE conversations SET name = $2, updated_at = NOW() WHERE id = $1")
+        .bind(conversation_id)
+        .bind(&name)
+        .execute(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    notify_all_summaries(pool, conversation_id);
+
+    Ok(())
+}
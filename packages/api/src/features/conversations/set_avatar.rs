@@ -0,0 +1,42 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SetAvatarRequest {
+    pub token: String,
+    pub conversation_id: String,
+    /// `None` clears the avatar.
+    pub avatar_url: Option<String>,
+}
+
+/// Any member may change a group's avatar. Members receive a `conversation_updated` event.
+#[post("/api/conversations/set_avatar")]
+pub async fn set_conversation_avatar(req: SetAvatarRequest) -> Result<(), ServerFnError> {
+    use super::{notify_all_summaries, require_group_member, validate_avatar_url};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let conversation_id: uuid::Uuid = req
+        .conversation_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid conversation_id: {e}")))?;
+    if let Some(url) = &req.avatar_url {
+        validate_avatar_url(url)?;
+    }
+
+    let pool = db::pool().await;
+    require_group_member(pool, conversation_id, user_id).await?;
+
+    sqlx::query("UPDATE conversations SET avatar_url = $2, updated_at = NOW() WHERE id = $1")
+        .bind(conversation_id)
+        .bind(&req.avatar_url)
+        .execute(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    notify_all_summaries(pool, conversation_id);
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Shortest disappearing-message timer: 30 seconds.
+pub const MIN_MESSAGE_TTL_SECS: i32 = 30;
+/// Longest disappearing-message timer: 90 days.
+pub const MAX_MESSAGE_TTL_SECS: i32 = 90 * 24 * 60 * 60;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SetMessageTtlRequest {
+    pub token: String,
+    pub conversation_id: String,
+    /// Seconds new messages live, or `None` to stop messages disappearing.
+    #[serde(default)]
+    pub ttl_secs: Option<i32>,
+}
+
+/// Set how long new messages in a conversation live. Messages already sent
+/// keep their own timer. Any member of a direct conversation may change it;
+/// groups need an owner or admin and Reign channels need Manage Channels.
+/// Members receive a `conversation_updated` event.
+#[post("/api/conversations/set_message_ttl")]
+pub async fn set_message_ttl(req: SetMessageTtlRequest) -> Result<(), ServerFnError> {
+    use super::notify_all_summaries;
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::roles;
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let conversation_id: uuid::Uuid = req
+        .conversation_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid conversation_id: {e}")))?;
+    if let Some(ttl) = req.ttl_secs {
+        if !(MIN_MESSAGE_TTL_SECS..=MAX_MESSAGE_TTL_SECS).contains(&ttl) {
+            return Err(ServerFnError::new(format!(
+                "The timer must be between {MIN_MESSAGE_TTL_SECS} seconds and {} days",
+                MAX_MESSAGE_TTL_SECS / (24 * 60 * 60)
+            )));
+        }
+    }
+
+    let pool = db::pool().await;
+    let (kind, reign_id) = sqlx::query_as::<_, (String, Option<uuid::Uuid>)>(
+        "SELECT kind, reign_id FROM conversations WHERE id = $1",
+    )
+    .bind(conversation_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?
+    .ok_or_else(|| ServerFnError::new("Conversation not found"))?;
+
+    let needed = if reign_id.is_some() {
+        Permissions::MANAGE_CHANNELS
+    } else if kind == "direct" {
+        Permissions::VIEW_CHANNEL
+    } else {
+        Permissions::MANAGE_MESSAGES
+    };
+    roles::require_permission(pool, conversation_id, user_id, needed).await?;
+
+    sqlx::query("UPDATE conversations SET message_ttl_secs = $2, updated_at = NOW() WHERE id = $1")
+        .bind(conversation_id)
+        .bind(req.ttl_secs)
+        .execute(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    notify_all_summaries(pool, conversation_id);
+
+    Ok(())
+}
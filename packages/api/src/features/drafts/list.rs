@@ -0,0 +1,47 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::Draft;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ListDraftsRequest {
+    pub token: String,
+}
+
+/// The user's unsent drafts in conversations they still belong to, most
+/// recently edited first.
+#[post("/api/drafts/list")]
+pub async fn list_drafts(req: ListDraftsRequest) -> Result<Vec<Draft>, ServerFnError> {
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::roles;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    let pool = db::pool().await;
+    let viewable = roles::viewable_conversations(pool, user_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let rows = sqlx::query_as::<_, (uuid::Uuid, String, chrono::DateTime<chrono::Utc>, i64)>(
+        "SELECT conversation_id, content, updated_at, revision FROM drafts
+         WHERE user_id = $1 AND conversation_id = ANY($2) AND content <> ''
+         ORDER BY updated_at DESC",
+    )
+    .bind(user_id)
+    .bind(&viewable)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(conversation_id, content, updated_at, revision)| Draft {
+            conversation_id: conversation_id.to_string(),
+            content,
+            updated_at: updated_at.to_rfc3339(),
+            revision,
+        })
+        .collect())
+}
@@ -0,0 +1,57 @@
+//! Unsent composer text, kept per user and conversation so it follows the
+//! user between devices.
+//!
+//! Every stored change bumps the draft's revision, and a save names the
+//! revision its text was typed over. A save over an older revision, such as
+//! one still in flight when the message was sent, is ignored and the stored
+//! draft returned instead. Device clocks play no part. Every change is pushed
+//! to the user's devices as `draft_updated`.
+
+pub mod list;
+pub mod save;
+
+use serde::{Deserialize, Serialize};
+
+/// Longest draft stored, in bytes.
+pub const MAX_DRAFT_BYTES: usize = 16 * 1024;
+
+/// A conversation's unsent composer text. Empty `content` means the draft
+/// was cleared, for example by sending it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Draft {
+    pub conversation_id: String,
+    pub content: String,
+    pub updated_at: String,
+    /// Bumped by every stored change, including clearing.
+    pub revision: i64,
+}
+
+/// Clear a user's draft once its message is sent, and tell their devices.
+#[cfg(feature = "server")]
+pub(crate) async fn clear_draft(pool: &sqlx::PgPool, user_id: uuid::Uuid, conversation_id: uuid::Uuid) {
+    use crate::events::WsEvent;
+
+    let cleared = sqlx::query_as::<_, (i64, chrono::DateTime<chrono::Utc>)>(
+        "UPDATE drafts SET content = '', revision = revision + 1, updated_at = NOW()
+         WHERE user_id = $1 AND conversation_id = $2 AND content <> ''
+         RETURNING revision, updated_at",
+    )
+    .bind(user_id)
+    .bind(conversation_id)
+    .fetch_optional(pool)
+    .await;
+
+    match cleared {
+        Ok(Some((revision, updated_at))) => {
+            let draft = Draft {
+                conversation_id: conversation_id.to_string(),
+                content: String::new(),
+                updated_at: updated_at.to_rfc3339(),
+                revision,
+            };
+            crate::ws::send_event(user_id, &WsEvent::DraftUpdated(draft));
+        }
+        Ok(None) => {}
+        Err(e) => println!("Clearing draft failed: {e}"),
+    }
+}
@@ -0,0 +1,137 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::Draft;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SaveDraftRequest {
+    pub token: String,
+    pub conversation_id: String,
+    /// The composer text; empty to clear the draft.
+    pub content: String,
+    /// The revision of the stored draft this text was typed over, 0 when the
+    /// device hasn't seen one.
+    pub base_revision: i64,
+}
+
+/// Store a draft unless the stored one has changed since `base_revision`,
+/// and return whichever draft is stored. The user's devices receive
+/// `draft_updated` when it changed.
+#[post("/api/drafts/save")]
+pub async fn save_draft(req: SaveDraftRequest) -> Result<Draft, ServerFnError> {
+    use super::MAX_DRAFT_BYTES;
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::events::WsEvent;
+    use crate::features::roles;
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let conversation_id: uuid::Uuid = req
+        .conversation_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid conversation_id: {e}")))?;
+    if req.content.len() > MAX_DRAFT_BYTES {
+        return Err(ServerFnError::new(format!("Drafts can be at most {MAX_DRAFT_BYTES} bytes")));
+    }
+
+    let pool = db::pool().await;
+    roles::require_permission(pool, conversation_id, user_id, Permissions::VIEW_CHANNEL).await?;
+
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    // An empty revision 0 stands in for a draft never saved, so there is
+    // always a row to lock
+    sqlx::query(
+        "INSERT INTO drafts (user_id, conversation_id, content) VALUES ($1, $2, '')
+         ON CONFLICT (user_id, conversation_id) DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(conversation_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let (stored_content, stored_revision, stored_at) =
+        sqlx::query_as::<_, (String, i64, chrono::DateTime<chrono::Utc>)>(
+            "SELECT content, revision, updated_at FROM drafts
+             WHERE user_id = $1 AND conversation_id = $2 FOR UPDATE",
+        )
+        .bind(user_id)
+        .bind(conversation_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let Some(revision) = next_revision(stored_revision, &stored_content, req.base_revision, &req.content) else {
+        tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+        return Ok(Draft {
+            conversation_id: conversation_id.to_string(),
+            content: stored_content,
+            updated_at: stored_at.to_rfc3339(),
+            revision: stored_revision,
+        });
+    };
+
+    let updated_at = sqlx::query_scalar::<_, chrono::DateTime<chrono::Utc>>(
+        "UPDATE drafts SET content = $3, revision = $4, updated_at = NOW()
+         WHERE user_id = $1 AND conversation_id = $2
+         RETURNING updated_at",
+    )
+    .bind(user_id)
+    .bind(conversation_id)
+    .bind(&req.content)
+    .bind(revision)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let draft = Draft {
+        conversation_id: conversation_id.to_string(),
+        content: req.content,
+        updated_at: updated_at.to_rfc3339(),
+        revision,
+    };
+    crate::ws::send_event(user_id, &WsEvent::DraftUpdated(draft.clone()));
+    Ok(draft)
+}
+
+/// The revision to store `content` under when it was typed over
+/// `base_revision`, or `None` to keep the stored draft: it already holds this
+/// text, or another save or a send has changed it since.
+#[cfg(feature = "server")]
+fn next_revision(stored_revision: i64, stored_content: &str, base_revision: i64, content: &str) -> Option<i64> {
+    (base_revision == stored_revision && content != stored_content).then_some(stored_revision + 1)
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_save_replaces_the_revision_it_was_typed_over() {
+        assert_eq!(next_revision(0, "", 0, "hello"), Some(1));
+        assert_eq!(next_revision(1, "hello", 1, "hello there"), Some(2));
+        assert_eq!(next_revision(2, "hello there", 2, "hello there"), None);
+    }
+
+    #[test]
+    fn a_device_clock_running_behind_cannot_lose_saves_after_a_clear() {
+        // Sending cleared revision 3 to revision 4 on the server's clock. The
+        // device picked up revision 4 and keeps typing; however far behind
+        // its clock runs, each save replaces the one before it
+        assert_eq!(next_revision(4, "", 4, "n"), Some(5));
+        assert_eq!(next_revision(5, "n", 5, "next"), Some(6));
+    }
+
+    #[test]
+    fn a_save_over_a_replaced_revision_is_ignored() {
+        // Still in flight when the message was sent
+        assert_eq!(next_revision(4, "", 3, "the sent message"), None);
+        // Another device saved first
+        assert_eq!(next_revision(6, "from the laptop", 5, "from the phone"), None);
+    }
+}
@@ -0,0 +1,65 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::Invite;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CreateInviteRequest {
+    pub token: String,
+    /// `group`, `reign` or `channel`.
+    pub target_type: String,
+    /// A group, Reign or channel id, matching `target_type`.
+    pub target_id: String,
+    /// Unlimited when `None`.
+    #[serde(default)]
+    pub max_uses: Option<i32>,
+    /// Never expires when `None`.
+    #[serde(default)]
+    pub expires_in_secs: Option<i64>,
+}
+
+#[post("/api/invites/create")]
+pub async fn create_invite(req: CreateInviteRequest) -> Result<Invite, ServerFnError> {
+    use super::{authorize_target, generate_code, invite_from_row, InviteRow, MAX_INVITE_AGE_SECS, MAX_INVITE_USES};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    if req.max_uses.is_some_and(|n| !(1..=MAX_INVITE_USES).contains(&n)) {
+        return Err(ServerFnError::new(format!("max_uses must be between 1 and {MAX_INVITE_USES}")));
+    }
+    if req.expires_in_secs.is_some_and(|s| !(1..=MAX_INVITE_AGE_SECS).contains(&s)) {
+        return Err(ServerFnError::new(format!("expires_in_secs must be between 1 and {MAX_INVITE_AGE_SECS}")));
+    }
+    let expires_at = req.expires_in_secs.map(|s| chrono::Utc::now() + chrono::Duration::seconds(s));
+
+    let pool = db::pool().await;
+    let (conversation_id, reign_id) = authorize_target(pool, user_id, &req.target_type, &req.target_id, false).await?;
+
+    // Retry the rare code collision rather than failing the request
+    for _ in 0..3 {
+        let row = sqlx::query_as::<_, InviteRow>(
+            "INSERT INTO invites (code, created_by, conversation_id, reign_id, max_uses, expires_at)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT (code) DO NOTHING
+             RETURNING code, conversation_id, reign_id, created_by, uses, max_uses, expires_at, created_at",
+        )
+        .bind(generate_code())
+        .bind(user_id)
+        .bind(conversation_id)
+        .bind(reign_id)
+        .bind(req.max_uses)
+        .bind(expires_at)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+        if let Some(row) = row {
+            return Ok(invite_from_row(row));
+        }
+    }
+
+    Err(ServerFnError::new("Couldn't generate a unique invite code; try again"))
+}
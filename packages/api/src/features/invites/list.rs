@@ -0,0 +1,43 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::Invite;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ListInvitesRequest {
+    pub token: String,
+    /// `group`, `reign` or `channel`.
+    pub target_type: String,
+    pub target_id: String,
+}
+
+/// Usable invites to a group, a Reign (including its channel invites) or a
+/// single channel, newest first.
+#[post("/api/invites/list")]
+pub async fn list_invites(req: ListInvitesRequest) -> Result<Vec<Invite>, ServerFnError> {
+    use super::{authorize_target, invite_from_row, InviteRow, INVITE_SELECT, INVITE_VALID};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    let pool = db::pool().await;
+    let (conversation_id, reign_id) = authorize_target(pool, user_id, &req.target_type, &req.target_id, true).await?;
+
+    // A Reign's listing covers its channel invites too
+    let rows = sqlx::query_as::<_, InviteRow>(&format!(
+        "{INVITE_SELECT}
+         WHERE ($1::uuid IS NULL OR i.conversation_id = $1)
+           AND ($2::uuid IS NULL OR i.reign_id = $2)
+           AND {INVITE_VALID}
+         ORDER BY i.created_at DESC"
+    ))
+    .bind(conversation_id)
+    .bind(reign_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(rows.into_iter().map(invite_from_row).collect())
+}
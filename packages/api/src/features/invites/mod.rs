@@ -0,0 +1,147 @@
+pub mod create;
+pub mod list;
+pub mod preview;
+pub mod redeem;
+pub mod revoke;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use dioxus::prelude::ServerFnError;
+
+/// Most uses an invite can be limited to.
+pub const MAX_INVITE_USES: i32 = 10_000;
+/// Longest an invite can stay valid: 30 days.
+pub const MAX_INVITE_AGE_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// An invite as seen by someone who can manage it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Invite {
+    pub code: String,
+    /// `group`, `reign` or `channel`.
+    pub target_type: String,
+    /// The group, or the channel a channel invite lands in.
+    pub conversation_id: Option<String>,
+    pub reign_id: Option<String>,
+    pub created_by: String,
+    pub uses: i32,
+    pub max_uses: Option<i32>,
+    pub expires_at: Option<String>,
+    pub created_at: String,
+}
+
+/// What the invite joins, once redeemed.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct InviteTarget {
+    /// `group`, `reign` or `channel`.
+    pub target_type: String,
+    pub conversation_id: Option<String>,
+    pub reign_id: Option<String>,
+}
+
+/// An invite is usable while it is neither revoked, expired nor used up.
+/// Expects the table aliased as `i`.
+#[cfg(feature = "server")]
+pub(crate) const INVITE_VALID: &str = "i.revoked_at IS NULL
+       AND (i.expires_at IS NULL OR i.expires_at > NOW())
+       AND (i.max_uses IS NULL OR i.uses < i.max_uses)";
+
+#[cfg(feature = "server")]
+pub(crate) const INVITE_SELECT: &str =
+    "SELECT i.code, i.conversation_id, i.reign_id, i.created_by, i.uses, i.max_uses, i.expires_at, i.created_at
+     FROM invites i";
+
+#[cfg(feature = "server")]
+#[derive(sqlx::FromRow)]
+pub(crate) struct InviteRow {
+    code: String,
+    conversation_id: Option<uuid::Uuid>,
+    reign_id: Option<uuid::Uuid>,
+    created_by: uuid::Uuid,
+    uses: i32,
+    max_uses: Option<i32>,
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[cfg(feature = "server")]
+pub(crate) fn invite_from_row(r: InviteRow) -> Invite {
+    Invite {
+        code: r.code,
+        target_type: target_type(r.conversation_id, r.reign_id).to_string(),
+        conversation_id: r.conversation_id.map(|id| id.to_string()),
+        reign_id: r.reign_id.map(|id| id.to_string()),
+        created_by: r.created_by.to_string(),
+        uses: r.uses,
+        max_uses: r.max_uses,
+        expires_at: r.expires_at.map(|t| t.to_rfc3339()),
+        created_at: r.created_at.to_rfc3339(),
+    }
+}
+
+#[cfg(feature = "server")]
+pub(crate) fn target_type(conversation_id: Option<uuid::Uuid>, reign_id: Option<uuid::Uuid>) -> &'static str {
+    match (conversation_id, reign_id) {
+        (Some(_), Some(_)) => "channel",
+        (None, Some(_)) => "reign",
+        _ => "group",
+    }
+}
+
+/// A random 8-character code from `[0-9A-Za-z]`.
+#[cfg(feature = "server")]
+pub(crate) fn generate_code() -> String {
+    const ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+    uuid::Uuid::new_v4()
+        .as_bytes()
+        .iter()
+        .take(8)
+        .map(|b| ALPHABET[*b as usize % ALPHABET.len()] as char)
+        .collect()
+}
+
+/// Resolve an invite target named by `target_type` and `target_id` to
+/// `(conversation_id, reign_id)`, checking the caller may invite people to it,
+/// or with `manage` set, manage its invites.
+///
+/// Any group member may invite, as they may add members directly; group
+/// owners and admins manage invites. Any Reign member may invite to the Reign
+/// or to a channel they can view; Manage Channels is needed to manage invites.
+#[cfg(feature = "server")]
+pub(crate) async fn authorize_target(
+    pool: &sqlx::PgPool,
+    user_id: uuid::Uuid,
+    target_type: &str,
+    target_id: &str,
+    manage: bool,
+) -> Result<(Option<uuid::Uuid>, Option<uuid::Uuid>), ServerFnError> {
+    use crate::features::{channels, conversations, reigns, roles};
+    use crate::permissions::Permissions;
+
+    let needed = if manage { Permissions::MANAGE_CHANNELS } else { Permissions::NONE };
+
+    match target_type {
+        "group" => {
+            let conversation_id: uuid::Uuid = target_id
+                .parse()
+                .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid target_id: {e}")))?;
+            let role = conversations::require_group_member(pool, conversation_id, user_id).await?;
+            if manage && !matches!(role.as_str(), "owner" | "admin") {
+                return Err(ServerFnError::new("Only owners and admins can manage a group's invites"));
+            }
+            Ok((Some(conversation_id), None))
+        }
+        "reign" => {
+            let reign_id = reigns::parse_reign_id(target_id)?;
+            roles::require_reign_permission(pool, reign_id, user_id, needed).await?;
+            Ok((None, Some(reign_id)))
+        }
+        "channel" => {
+            let (channel_id, reign_id) = channels::channel_reign(pool, target_id).await?;
+            roles::require_permission(pool, channel_id, user_id, needed).await?;
+            Ok((Some(channel_id), Some(reign_id)))
+        }
+        _ => Err(ServerFnError::new("target_type must be group, reign or channel")),
+    }
+}
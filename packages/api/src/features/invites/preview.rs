@@ -0,0 +1,70 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Needs no token, so invite pages can be shown before signing in.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct InvitePreviewRequest {
+    pub code: String,
+}
+
+/// What an invite leads to, shown to anyone holding the code.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct InvitePreview {
+    pub code: String,
+    /// `group`, `reign` or `channel`.
+    pub target_type: String,
+    /// The group's or the Reign's name.
+    pub name: String,
+    pub icon_url: Option<String>,
+    pub member_count: i64,
+    /// Set for channel invites.
+    pub channel_name: Option<String>,
+    pub expires_at: Option<String>,
+}
+
+#[post("/api/invites/preview")]
+pub async fn preview_invite(req: InvitePreviewRequest) -> Result<InvitePreview, ServerFnError> {
+    use super::{target_type, INVITE_VALID};
+    use crate::db;
+
+    #[derive(sqlx::FromRow)]
+    struct PreviewRow {
+        conversation_id: Option<uuid::Uuid>,
+        reign_id: Option<uuid::Uuid>,
+        name: String,
+        icon_url: Option<String>,
+        member_count: i64,
+        channel_name: Option<String>,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    }
+
+    let row = sqlx::query_as::<_, PreviewRow>(&format!(
+        "SELECT i.conversation_id, i.reign_id, i.expires_at,
+                COALESCE(r.name, c.name, 'Conversation') AS name,
+                COALESCE(r.icon_url, c.avatar_url) AS icon_url,
+                CASE WHEN r.id IS NOT NULL
+                     THEN (SELECT COUNT(*) FROM reign_members WHERE reign_id = r.id)
+                     ELSE (SELECT COUNT(*) FROM conversation_members WHERE conversation_id = c.id)
+                END AS member_count,
+                CASE WHEN r.id IS NOT NULL THEN c.name END AS channel_name
+         FROM invites i
+         LEFT JOIN reigns r ON r.id = i.reign_id
+         LEFT JOIN conversations c ON c.id = i.conversation_id
+         WHERE i.code = $1 AND {INVITE_VALID}"
+    ))
+    .bind(&req.code)
+    .fetch_optional(db::pool().await)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?
+    .ok_or_else(|| ServerFnError::new("This invite is invalid or has expired"))?;
+
+    Ok(InvitePreview {
+        code: req.code,
+        target_type: target_type(row.conversation_id, row.reign_id).to_string(),
+        name: row.name,
+        icon_url: row.icon_url,
+        member_count: row.member_count,
+        channel_name: row.channel_name,
+        expires_at: row.expires_at.map(|t| t.to_rfc3339()),
+    })
+}
@@ -0,0 +1,78 @@
+use dioxus::prelude::*;
+
+use super::revoke::InviteCodeRequest;
+use super::InviteTarget;
+
+/// Join whatever the invite leads to. Redeeming an invite to something the
+/// caller already belongs to succeeds without using up the invite.
+#[post("/api/invites/redeem")]
+pub async fn redeem_invite(req: InviteCodeRequest) -> Result<InviteTarget, ServerFnError> {
+    use super::{target_type, INVITE_VALID};
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::{conversations, reigns};
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    let pool = db::pool().await;
+    let invalid = || ServerFnError::new("This invite is invalid or has expired");
+
+    let (conversation_id, reign_id, is_member) = sqlx::query_as::<_, (Option<uuid::Uuid>, Option<uuid::Uuid>, bool)>(&format!(
+        "SELECT i.conversation_id, i.reign_id,
+                CASE WHEN i.reign_id IS NOT NULL
+                     THEN EXISTS (SELECT 1 FROM reign_members WHERE reign_id = i.reign_id AND user_id = $2)
+                     ELSE EXISTS (SELECT 1 FROM conversation_members WHERE conversation_id = i.conversation_id AND user_id = $2)
+                END
+         FROM invites i
+         WHERE i.code = $1 AND {INVITE_VALID}"
+    ))
+    .bind(&req.code)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?
+    .ok_or_else(invalid)?;
+
+    let target = InviteTarget {
+        target_type: target_type(conversation_id, reign_id).to_string(),
+        conversation_id: conversation_id.map(|id| id.to_string()),
+        reign_id: reign_id.map(|id| id.to_string()),
+    };
+    if is_member {
+        return Ok(target);
+    }
+
+    // Claim a use up front so concurrent redemptions can't exceed max_uses
+    let claimed = sqlx::query(&format!("UPDATE invites i SET uses = uses + 1 WHERE i.code = $1 AND {INVITE_VALID}"))
+        .bind(&req.code)
+        .execute(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .rows_affected()
+        > 0;
+    if !claimed {
+        return Err(invalid());
+    }
+
+    let joined = match (conversation_id, reign_id) {
+        (_, Some(reign_id)) => reigns::add_to_reign(pool, reign_id, user_id).await,
+        (Some(conversation_id), None) => conversations::add_group_member(pool, conversation_id, user_id, user_id)
+            .await
+            .map(|()| true),
+        (None, None) => Err(invalid()),
+    };
+
+    if !matches!(joined, Ok(true)) {
+        sqlx::query("UPDATE invites SET uses = uses - 1 WHERE code = $1 AND uses > 0")
+            .bind(&req.code)
+            .execute(pool)
+            .await
+            .map_err(|e| ServerFnError::new(e.to_string()))?;
+    }
+    if let Some(reign_id) = reign_id.filter(|_| matches!(joined, Ok(true))) {
+        reigns::notify_reign(pool, reign_id).await;
+    }
+
+    joined.map(|_| target)
+}
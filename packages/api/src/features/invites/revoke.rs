@@ -0,0 +1,43 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct InviteCodeRequest {
+    pub token: String,
+    pub code: String,
+}
+
+/// Stop an invite from being used. Allowed for its creator and for anyone who
+/// can manage the target's invites.
+#[post("/api/invites/revoke")]
+pub async fn revoke_invite(req: InviteCodeRequest) -> Result<(), ServerFnError> {
+    use super::{authorize_target, target_type};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    let pool = db::pool().await;
+    let (conversation_id, reign_id, created_by) = sqlx::query_as::<_, (Option<uuid::Uuid>, Option<uuid::Uuid>, uuid::Uuid)>(
+        "SELECT conversation_id, reign_id, created_by FROM invites WHERE code = $1 AND revoked_at IS NULL",
+    )
+    .bind(&req.code)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?
+    .ok_or_else(|| ServerFnError::new("Invite not found"))?;
+
+    if created_by != user_id {
+        let target_id = conversation_id.or(reign_id).unwrap_or_default().to_string();
+        authorize_target(pool, user_id, target_type(conversation_id, reign_id), &target_id, true).await?;
+    }
+
+    sqlx::query("UPDATE invites SET revoked_at = NOW() WHERE code = $1 AND revoked_at IS NULL")
+        .bind(&req.code)
+        .execute(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(())
+}
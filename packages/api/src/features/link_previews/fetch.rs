@@ -0,0 +1,489 @@
+//! Fetching linked pages without letting message authors reach internal
+//! services.
+//!
+//! Every hop is resolved here first and refused if any address is loopback,
+//! private, link-local or otherwise not publicly routable. The connection
+//! is then pinned to the checked addresses, so a second DNS answer can't
+//! point it somewhere else. Redirects are followed by hand for the same
+//! reason. Bodies are read up to a size cap, and the whole unfurl, redirects
+//! and oEmbed included, runs under one deadline.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use reqwest::header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, LOCATION};
+use reqwest::Url;
+use scraper::{Html, Selector};
+
+use super::LinkPreview;
+
+const USER_AGENT: &str = "ReignCloudBot/1.0 (link previews)";
+const HTML_TYPES: &[&str] = &["text/html", "application/xhtml+xml"];
+const JSON_TYPES: &[&str] = &["application/json", "text/json", "application/json+oembed"];
+const MAX_TITLE_CHARS: usize = 300;
+const MAX_DESCRIPTION_CHARS: usize = 500;
+const MAX_SITE_NAME_CHARS: usize = 100;
+const MAX_URL_CHARS: usize = 2048;
+
+/// Limits for one unfurl.
+#[derive(Debug, Clone)]
+pub(crate) struct FetchPolicy {
+    /// Deadline for the whole unfurl.
+    pub timeout: Duration,
+    /// Bytes read from each response; pages are parsed from what fits.
+    pub max_bytes: usize,
+    pub max_redirects: usize,
+    /// Reach private and loopback addresses. Only for local stand-ins.
+    pub allow_private: bool,
+}
+
+impl Default for FetchPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            max_bytes: 512 * 1024,
+            max_redirects: 3,
+            allow_private: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum FetchError {
+    InvalidUrl,
+    /// The host resolved to an address that isn't publicly routable.
+    Blocked(IpAddr),
+    Resolve(std::io::Error),
+    Request(reqwest::Error),
+    Status(u16),
+    UnsupportedType(String),
+    TooManyRedirects,
+    Timeout,
+    /// The page had neither a title nor a description.
+    NoMetadata,
+}
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidUrl => write!(f, "invalid URL"),
+            Self::Blocked(ip) => write!(f, "address {ip} is not public"),
+            Self::Resolve(e) => write!(f, "DNS lookup failed: {e}"),
+            Self::Request(e) => write!(f, "request failed: {e}"),
+            Self::Status(status) => write!(f, "server answered {status}"),
+            Self::UnsupportedType(t) => write!(f, "unsupported content type {t:?}"),
+            Self::TooManyRedirects => write!(f, "too many redirects"),
+            Self::Timeout => write!(f, "timed out"),
+            Self::NoMetadata => write!(f, "no title or description"),
+        }
+    }
+}
+
+/// What a page says about itself.
+#[derive(Debug, Default)]
+struct PageMeta {
+    title: Option<String>,
+    description: Option<String>,
+    image_url: Option<String>,
+    site_name: Option<String>,
+    oembed_url: Option<Url>,
+}
+
+/// Build a preview for `url` from its Open Graph tags, falling back to the
+/// page's oEmbed endpoint and then its `<title>` and description.
+pub(crate) async fn unfurl(policy: &FetchPolicy, url: &str) -> Result<LinkPreview, FetchError> {
+    let start = Url::parse(url).map_err(|_| FetchError::InvalidUrl)?;
+
+    tokio::time::timeout(policy.timeout, async {
+        let (page_url, html) = fetch(policy, start, HTML_TYPES).await?;
+        let mut meta = parse_page(&page_url, &html);
+
+        if meta.title.is_none() || meta.image_url.is_none() {
+            if let Some(oembed_url) = meta.oembed_url.take() {
+                // A broken oEmbed endpoint still leaves the page's own tags
+                if let Ok((_, json)) = fetch(policy, oembed_url, JSON_TYPES).await {
+                    merge_oembed(&mut meta, &page_url, &json);
+                }
+            }
+        }
+
+        if meta.title.is_none() && meta.description.is_none() {
+            return Err(FetchError::NoMetadata);
+        }
+        Ok(LinkPreview {
+            url: url.to_string(),
+            title: meta.title,
+            description: meta.description,
+            image_url: meta.image_url,
+            site_name: meta.site_name,
+        })
+    })
+    .await
+    .map_err(|_| FetchError::Timeout)?
+}
+
+/// GET `url`, following redirects, and return the final URL with the body
+/// decoded as text. The response must have one of the `accept`ed types.
+async fn fetch(policy: &FetchPolicy, mut url: Url, accept: &[&str]) -> Result<(Url, String), FetchError> {
+    for _ in 0..=policy.max_redirects {
+        if !matches!(url.scheme(), "http" | "https") || !url.username().is_empty() || url.password().is_some() {
+            return Err(FetchError::InvalidUrl);
+        }
+        let host = url.host_str().ok_or(FetchError::InvalidUrl)?.to_string();
+        let addrs = resolve(policy, &url).await?;
+
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .no_proxy()
+            .user_agent(USER_AGENT)
+            .resolve_to_addrs(&host, &addrs)
+            .build()
+            .map_err(FetchError::Request)?;
+        let mut response = client
+            .get(url.clone())
+            .header(ACCEPT, accept.join(", "))
+            .send()
+            .await
+            .map_err(FetchError::Request)?;
+
+        let status = response.status();
+        if status.is_redirection() {
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|l| l.to_str().ok())
+                .ok_or(FetchError::Status(status.as_u16()))?;
+            url = url.join(location).map_err(|_| FetchError::InvalidUrl)?;
+            continue;
+        }
+        if !status.is_success() {
+            return Err(FetchError::Status(status.as_u16()));
+        }
+
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|t| t.to_str().ok())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let essence = content_type.split(';').next().unwrap_or_default().trim();
+        if !accept.contains(&essence) {
+            return Err(FetchError::UnsupportedType(essence.to_string()));
+        }
+
+        // Stop reading at the cap; a page's metadata is near the top anyway
+        let declared = response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|l| l.to_str().ok())
+            .and_then(|l| l.parse::<usize>().ok());
+        let mut body = Vec::with_capacity(declared.unwrap_or(0).min(policy.max_bytes));
+        while body.len() < policy.max_bytes {
+            match response.chunk().await.map_err(FetchError::Request)? {
+                Some(chunk) => body.extend_from_slice(&chunk),
+                None => break,
+            }
+        }
+        body.truncate(policy.max_bytes);
+
+        return Ok((url, String::from_utf8_lossy(&body).into_owned()));
+    }
+
+    Err(FetchError::TooManyRedirects)
+}
+
+/// Every address `url`'s host resolves to, refusing the lot if any of them
+/// isn't public.
+async fn resolve(policy: &FetchPolicy, url: &Url) -> Result<Vec<SocketAddr>, FetchError> {
+    let host = url.host_str().ok_or(FetchError::InvalidUrl)?;
+    let port = url.port_or_known_default().ok_or(FetchError::InvalidUrl)?;
+    // IPv6 literals keep their brackets in the URL
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(FetchError::Resolve)?
+        .collect();
+    if addrs.is_empty() {
+        return Err(FetchError::Resolve(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no addresses",
+        )));
+    }
+    if !policy.allow_private {
+        if let Some(addr) = addrs.iter().find(|a| !is_public(a.ip())) {
+            return Err(FetchError::Blocked(addr.ip()));
+        }
+    }
+    Ok(addrs)
+}
+
+/// Whether `ip` is publicly routable. IPv6 addresses that embed an IPv4
+/// address are judged by the embedded one.
+pub(crate) fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_public_v4(v4),
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_v4(v4);
+            }
+            let s = v6.segments();
+            let embedded = Ipv4Addr::from(((s[6] as u32) << 16) | s[7] as u32);
+            if s[0] == 0x64 && s[1] == 0xff9b && s[2..6] == [0, 0, 0, 0] {
+                // NAT64
+                return is_public_v4(embedded);
+            }
+            if s[0] == 0x2002 {
+                // 6to4
+                return is_public_v4(Ipv4Addr::from(((s[1] as u32) << 16) | s[2] as u32));
+            }
+            !(v6.is_unspecified()
+                || v6.is_loopback()
+                || v6.is_multicast()
+                || s[..6] == [0, 0, 0, 0, 0, 0]
+                || (s[0] & 0xfe00) == 0xfc00
+                || (s[0] & 0xffc0) == 0xfe80
+                || (s[0] & 0xffc0) == 0xfec0
+                || (s[0] == 0x2001 && s[1] == 0x0db8))
+        }
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        // Carrier-grade NAT
+        || (a == 100 && (b & 0xc0) == 64)
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking
+        || (a == 198 && (b & 0xfe) == 18)
+        || a >= 240)
+}
+
+fn parse_page(page_url: &Url, html: &str) -> PageMeta {
+    let document = Html::parse_document(html);
+    let selector = |s: &str| Selector::parse(s).expect("static selector");
+
+    // Open Graph uses `property`, Twitter cards and plain descriptions `name`
+    let meta_tag = |keys: &[&str]| {
+        let tags = selector("meta[content]");
+        keys.iter().find_map(|key| {
+            document.select(&tags).find_map(|tag| {
+                let el = tag.value();
+                let name = el.attr("property").or_else(|| el.attr("name"))?;
+                name.eq_ignore_ascii_case(key)
+                    .then(|| el.attr("content"))
+                    .flatten()
+                    .and_then(non_empty)
+            })
+        })
+    };
+
+    let title = meta_tag(&["og:title", "twitter:title"]).or_else(|| {
+        document
+            .select(&selector("title"))
+            .next()
+            .and_then(|t| non_empty(&t.text().collect::<String>()))
+    });
+    let oembed_url = document
+        .select(&selector("link[href][type]"))
+        .find(|l| l.value().attr("type").is_some_and(|t| t.eq_ignore_ascii_case("application/json+oembed")))
+        .and_then(|l| page_url.join(l.value().attr("href")?).ok());
+
+    PageMeta {
+        title: title.map(|t| clip(&t, MAX_TITLE_CHARS)),
+        description: meta_tag(&["og:description", "twitter:description", "description"])
+            .map(|d| clip(&d, MAX_DESCRIPTION_CHARS)),
+        image_url: meta_tag(&["og:image:secure_url", "og:image", "twitter:image"])
+            .and_then(|i| absolute_url(page_url, &i)),
+        site_name: meta_tag(&["og:site_name"]).map(|s| clip(&s, MAX_SITE_NAME_CHARS)),
+        oembed_url,
+    }
+}
+
+/// Fill gaps in `meta` from an oEmbed response.
+fn merge_oembed(meta: &mut PageMeta, page_url: &Url, json: &str) {
+    let Ok(oembed) = serde_json::from_str::<serde_json::Value>(json) else {
+        return;
+    };
+    let field = |key: &str| oembed.get(key).and_then(|v| v.as_str()).and_then(non_empty);
+
+    if meta.title.is_none() {
+        meta.title = field("title").map(|t| clip(&t, MAX_TITLE_CHARS));
+    }
+    if meta.description.is_none() {
+        meta.description = field("author_name").map(|a| clip(&a, MAX_DESCRIPTION_CHARS));
+    }
+    if meta.image_url.is_none() {
+        meta.image_url = field("thumbnail_url").and_then(|i| absolute_url(page_url, &i));
+    }
+    if meta.site_name.is_none() {
+        meta.site_name = field("provider_name").map(|s| clip(&s, MAX_SITE_NAME_CHARS));
+    }
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!s.is_empty()).then_some(s)
+}
+
+fn clip(s: &str, max_chars: usize) -> String {
+    match s.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &s[..end]),
+        None => s.to_string(),
+    }
+}
+
+/// `href` resolved against the page, if it is a reasonable `http(s)` URL.
+fn absolute_url(page_url: &Url, href: &str) -> Option<String> {
+    let url = page_url.join(href).ok()?;
+    let url = url.as_str();
+    (matches!(&url[..url.find(':')?], "http" | "https") && url.len() <= MAX_URL_CHARS && crate::markdown::is_safe_url(url))
+        .then(|| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::http::header;
+    use axum::response::{IntoResponse, Redirect};
+    use axum::routing::get;
+    use axum::Router;
+
+    const PAGE: &str = r#"<!doctype html><html><head>
+        <title>Fallback title</title>
+        <meta property="og:title" content="A  page">
+        <meta property="og:description" content="About the page">
+        <meta property="og:image" content="/cover.png">
+        <meta property="og:site_name" content="Stand-in">
+        </head><body>Hello</body></html>"#;
+
+    const OEMBED_PAGE: &str = r#"<html><head>
+        <link rel="alternate" type="application/json+oembed" href="/oembed.json">
+        </head></html>"#;
+
+    /// Serve a handful of pages on a loopback port and return its base URL.
+    async fn stand_in() -> String {
+        let html = |body: &'static str| move || async move { ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], body) };
+        let app = Router::new()
+            .route("/page", get(html(PAGE)))
+            .route("/oembed", get(html(OEMBED_PAGE)))
+            .route(
+                "/oembed.json",
+                get(|| async {
+                    (
+                        [(header::CONTENT_TYPE, "application/json")],
+                        r#"{"title": "Embedded", "provider_name": "Tube", "thumbnail_url": "https://img.example/t.jpg"}"#,
+                    )
+                }),
+            )
+            .route("/redirect", get(|| async { Redirect::temporary("/page") }))
+            .route("/loop", get(|| async { Redirect::temporary("/loop") }))
+            .route("/image", get(|| async { ([(header::CONTENT_TYPE, "image/png")], "PNG").into_response() }))
+            .route(
+                "/huge",
+                get(|| async {
+                    let body = format!("<html><head><title>Huge</title></head><body>{}</body></html>", "x".repeat(4 << 20));
+                    ([(header::CONTENT_TYPE, "text/html")], body)
+                }),
+            )
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    ([(header::CONTENT_TYPE, "text/html")], PAGE)
+                }),
+            );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    fn local() -> FetchPolicy {
+        FetchPolicy {
+            timeout: Duration::from_secs(2),
+            allow_private: true,
+            ..FetchPolicy::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_open_graph_tags() {
+        let base = stand_in().await;
+        let preview = unfurl(&local(), &format!("{base}/page")).await.unwrap();
+        assert_eq!(preview.title.as_deref(), Some("A page"));
+        assert_eq!(preview.description.as_deref(), Some("About the page"));
+        assert_eq!(preview.image_url, Some(format!("{base}/cover.png")));
+        assert_eq!(preview.site_name.as_deref(), Some("Stand-in"));
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_oembed() {
+        let base = stand_in().await;
+        let preview = unfurl(&local(), &format!("{base}/oembed")).await.unwrap();
+        assert_eq!(preview.title.as_deref(), Some("Embedded"));
+        assert_eq!(preview.site_name.as_deref(), Some("Tube"));
+        assert_eq!(preview.image_url.as_deref(), Some("https://img.example/t.jpg"));
+    }
+
+    #[tokio::test]
+    async fn follows_redirects_up_to_the_limit() {
+        let base = stand_in().await;
+        let preview = unfurl(&local(), &format!("{base}/redirect")).await.unwrap();
+        assert_eq!(preview.url, format!("{base}/redirect"));
+        assert_eq!(preview.title.as_deref(), Some("A page"));
+
+        let looped = unfurl(&local(), &format!("{base}/loop")).await;
+        assert!(matches!(looped, Err(FetchError::TooManyRedirects)));
+    }
+
+    #[tokio::test]
+    async fn enforces_type_size_and_time_limits() {
+        let base = stand_in().await;
+        let image = unfurl(&local(), &format!("{base}/image")).await;
+        assert!(matches!(image, Err(FetchError::UnsupportedType(t)) if t == "image/png"));
+
+        let huge = unfurl(&local(), &format!("{base}/huge")).await.unwrap();
+        assert_eq!(huge.title.as_deref(), Some("Huge"));
+
+        let quick = FetchPolicy {
+            timeout: Duration::from_millis(200),
+            ..local()
+        };
+        let slow = unfurl(&quick, &format!("{base}/slow")).await;
+        assert!(matches!(slow, Err(FetchError::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn refuses_private_addresses() {
+        let base = stand_in().await;
+        let blocked = unfurl(&FetchPolicy::default(), &format!("{base}/page")).await;
+        assert!(matches!(blocked, Err(FetchError::Blocked(ip)) if ip.is_loopback()));
+
+        let creds = unfurl(&local(), &base.replace("http://", "http://user:pw@")).await;
+        assert!(matches!(creds, Err(FetchError::InvalidUrl)));
+    }
+
+    #[test]
+    fn classifies_addresses() {
+        for private in [
+            "127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.0.0.0",
+            "::1", "::", "fd00::1", "fe80::1", "::ffff:10.0.0.1", "64:ff9b::a9fe:a9fe", "2002:7f00:1::",
+        ] {
+            assert!(!is_public(private.parse().unwrap()), "{private} should be blocked");
+        }
+        for public in ["93.184.216.34", "1.1.1.1", "2606:4700:4700::1111", "::ffff:8.8.8.8"] {
+            assert!(is_public(public.parse().unwrap()), "{public} should be allowed");
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! Preview cards for links in messages.
+//!
+//! Creating or editing a message queues it for unfurling in the background.
+//! Each page is fetched once and cached in `link_previews`; messages point at
+//! the cached rows, and members get a `message_updated` event once a
+//! message's previews are in.
+
+#[cfg(feature = "server")]
+pub mod fetch;
+
+use serde::{Deserialize, Serialize};
+
+/// Most links previewed per message; later ones are left as plain links.
+pub const MAX_PREVIEWS_PER_MESSAGE: usize = 3;
+
+/// A card summarizing a linked page, from its Open Graph tags or oEmbed.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LinkPreview {
+    /// The link as written in the message, before any redirects.
+    pub url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// An absolute `http(s)` image URL on the linked site.
+    pub image_url: Option<String>,
+    pub site_name: Option<String>,
+}
+
+/// The `http(s)` links in `content` that get previews, in order and without
+/// duplicates. Links inside code are not previewed.
+pub fn preview_urls(content: &str) -> Vec<String> {
+    use crate::markdown::{parse, Block, Inline};
+
+    fn walk_inline(inlines: &[Inline], urls: &mut Vec<String>) {
+        for inline in inlines {
+            match inline {
+                Inline::Link { url, .. } => {
+                    let lower = url.to_ascii_lowercase();
+                    if (lower.starts_with("http://") || lower.starts_with("https://")) && !urls.contains(url) {
+                        urls.push(url.clone());
+                    }
+                }
+                Inline::Bold(inner) | Inline::Italic(inner) => walk_inline(inner, urls),
+                // Spoilers stay hidden, and so do their links
+                Inline::Spoiler(_) | Inline::Text(_) | Inline::Code(_) | Inline::LineBreak => {}
+            }
+        }
+    }
+
+    fn walk(blocks: &[Block], urls: &mut Vec<String>) {
+        for block in blocks {
+            match block {
+                Block::Paragraph(inlines) => walk_inline(inlines, urls),
+                Block::Quote(blocks) => walk(blocks, urls),
+                Block::CodeBlock { .. } => {}
+            }
+        }
+    }
+
+    let mut urls = Vec::new();
+    walk(&parse(content), &mut urls);
+    urls.truncate(MAX_PREVIEWS_PER_MESSAGE);
+    urls
+}
+
+/// How long a successful fetch is reused before the page is fetched again.
+#[cfg(feature = "server")]
+const CACHE_TTL: chrono::Duration = chrono::Duration::days(7);
+/// How long a failed fetch is remembered before the page is tried again.
+#[cfg(feature = "server")]
+const FAILURE_TTL: chrono::Duration = chrono::Duration::hours(1);
+
+/// Fill in `link_previews` on each message from the cache.
+#[cfg(feature = "server")]
+pub(crate) async fn attach_link_previews(
+    pool: &sqlx::PgPool,
+    messages: &mut [crate::features::messages::create::MessageResponse],
+) -> Result<(), sqlx::Error> {
+    let ids: Vec<uuid::Uuid> = messages.iter().filter_map(|m| m.id.parse().ok()).collect();
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    #[allow(clippy::type_complexity)]
+    let rows = sqlx::query_as::<
+        _,
+        (uuid::Uuid, String, Option<String>, Option<String>, Option<String>, Option<String>),
+    >(
+        "SELECT mp.message_id, lp.url, lp.title, lp.description, lp.image_url, lp.site_name
+         FROM message_link_previews mp
+         JOIN link_previews lp ON lp.url = mp.url
+         WHERE mp.message_id = ANY($1) AND lp.ok
+         ORDER BY mp.position ASC",
+    )
+    .bind(&ids)
+    .fetch_all(pool)
+    .await?;
+
+    let mut by_message: std::collections::HashMap<String, Vec<LinkPreview>> = std::collections::HashMap::new();
+    for (message_id, url, title, description, image_url, site_name) in rows {
+        by_message.entry(message_id.to_string()).or_default().push(LinkPreview {
+            url,
+            title,
+            description,
+            image_url,
+            site_name,
+        });
+    }
+
+    for m in messages.iter_mut() {
+        m.link_previews = by_message.remove(&m.id).unwrap_or_default();
+    }
+
+    Ok(())
+}
+
+/// Unfurl the links in a freshly created or edited message in the background.
+#[cfg(feature = "server")]
+pub(crate) fn spawn_unfurl(message_id: uuid::Uuid) {
+    tokio::spawn(async move {
+        let pool = crate::db::pool().await;
+        if let Err(e) = unfurl_message(pool, &fetch::FetchPolicy::default(), message_id).await {
+            println!("Unfurling links in message {message_id} failed: {e}");
+        }
+    });
+}
+
+/// Fetch or reuse a preview for each link in the message, link them to it
+/// and tell the conversation. Does nothing if the message was edited or
+/// deleted in the meantime; the edit queues its own run.
+#[cfg(feature = "server")]
+async fn unfurl_message(
+    pool: &sqlx::PgPool,
+    policy: &fetch::FetchPolicy,
+    message_id: uuid::Uuid,
+) -> Result<(), sqlx::Error> {
+    use crate::events::WsEvent;
+    use crate::features::messages::{message_from_row, MessageRow, MESSAGE_SELECT};
+    use crate::features::{attachments, conversations};
+
+    let Some((content, sender_id, conversation_id)) = sqlx::query_as::<_, (String, uuid::Uuid, uuid::Uuid)>(
+        "SELECT content, sender_id, conversation_id FROM messages WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(message_id)
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(());
+    };
+
+    let urls = preview_urls(&content);
+    if urls.is_empty() {
+        return Ok(());
+    }
+
+    let mut found = Vec::new();
+    for url in &urls {
+        if cached_or_fetch(pool, policy, url).await? {
+            found.push(url.clone());
+        }
+    }
+    if found.is_empty() {
+        return Ok(());
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // Lock the message so an edit can't slip in between the check and the insert
+    let current = sqlx::query_scalar::<_, String>(
+        "SELECT content FROM messages WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+    )
+    .bind(message_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if current.as_deref() != Some(content.as_str()) {
+        return Ok(());
+    }
+
+    sqlx::query("DELETE FROM message_link_previews WHERE message_id = $1")
+        .bind(message_id)
+        .execute(&mut *tx)
+        .await?;
+    for (position, url) in found.iter().enumerate() {
+        sqlx::query("INSERT INTO message_link_previews (message_id, url, position) VALUES ($1, $2, $3)")
+            .bind(message_id)
+            .bind(url)
+            .bind(position as i16)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let row = sqlx::query_as::<_, MessageRow>(&format!("{MESSAGE_SELECT} WHERE m.id = $1"))
+        .bind(message_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    // Clients keep their own reactions on updates, so the sender's view is fine
+    let mut response = message_from_row(row);
+    crate::features::reactions::attach_reactions(pool, sender_id, std::slice::from_mut(&mut response)).await?;
+    attachments::attach_attachments(pool, sender_id, std::slice::from_mut(&mut response)).await?;
+    attach_link_previews(pool, std::slice::from_mut(&mut response)).await?;
+
+    conversations::broadcast(pool, conversation_id, &WsEvent::MessageUpdated(response)).await;
+
+    Ok(())
+}
+
+/// Make sure `url` has a fresh cache entry, fetching the page if needed.
+/// Returns whether the entry has a preview to show.
+#[cfg(feature = "server")]
+async fn cached_or_fetch(pool: &sqlx::PgPool, policy: &fetch::FetchPolicy, url: &str) -> Result<bool, sqlx::Error> {
+    let cached = sqlx::query_scalar::<_, bool>(
+        "SELECT ok FROM link_previews
+         WHERE url = $1 AND fetched_at > NOW() - CASE WHEN ok THEN $2 ELSE $3 END",
+    )
+    .bind(url)
+    .bind(CACHE_TTL)
+    .bind(FAILURE_TTL)
+    .fetch_optional(pool)
+    .await?;
+    if let Some(ok) = cached {
+        return Ok(ok);
+    }
+
+    let preview = match fetch::unfurl(policy, url).await {
+        Ok(preview) => Some(preview),
+        Err(e) => {
+            println!("No preview for {url}: {e}");
+            None
+        }
+    };
+
+    sqlx::query(
+        "INSERT INTO link_previews (url, ok, title, description, image_url, site_name, fetched_at)
+         VALUES ($1, $2, $3, $4, $5, $6, NOW())
+         ON CONFLICT (url) DO UPDATE
+         SET ok = EXCLUDED.ok, title = EXCLUDED.title, description = EXCLUDED.description,
+             image_url = EXCLUDED.image_url, site_name = EXCLUDED.site_name, fetched_at = EXCLUDED.fetched_at",
+    )
+    .bind(url)
+    .bind(preview.is_some())
+    .bind(preview.as_ref().and_then(|p| p.title.as_deref()))
+    .bind(preview.as_ref().and_then(|p| p.description.as_deref()))
+    .bind(preview.as_ref().and_then(|p| p.image_url.as_deref()))
+    .bind(preview.as_ref().and_then(|p| p.site_name.as_deref()))
+    .execute(pool)
+    .await?;
+
+    Ok(preview.is_some())
+}
+
+/// Drop cache entries that are past their lifetime and no longer shown
+/// under any message. Returns how many were removed.
+#[cfg(feature = "server")]
+pub(crate) async fn purge_stale(pool: &sqlx::PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM link_previews lp
+         WHERE lp.fetched_at < NOW() - $1
+           AND NOT EXISTS (SELECT 1 FROM message_link_previews mp WHERE mp.url = lp.url)",
+    )
+    .bind(CACHE_TTL)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
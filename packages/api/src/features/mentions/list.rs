@@ -0,0 +1,93 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::features::messages::create::MessageResponse;
+
+/// Mentions per page when the request doesn't say.
+pub const DEFAULT_MENTIONS_LIMIT: i64 = 25;
+pub const MAX_MENTIONS_LIMIT: i64 = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ListMentionsRequest {
+    pub token: String,
+    /// `next_cursor` from the previous page.
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MentionPage {
+    /// Newest first.
+    pub messages: Vec<MessageResponse>,
+    /// Pass back as `cursor` for older mentions; `None` on the last page.
+    pub next_cursor: Option<String>,
+}
+
+/// Messages mentioning the caller by name or with `@everyone`, across every
+/// conversation they can currently view.
+#[post("/api/mentions/list")]
+pub async fn list_mentions(req: ListMentionsRequest) -> Result<MentionPage, ServerFnError> {
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::messages::{message_from_row, MessageRow, MESSAGE_SELECT};
+    use crate::features::{attachments, link_previews, reactions, roles};
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let cursor: Option<uuid::Uuid> = req
+        .cursor
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid cursor: {e}")))?;
+    let limit = req.limit.unwrap_or(DEFAULT_MENTIONS_LIMIT).clamp(1, MAX_MENTIONS_LIMIT);
+
+    let pool = db::pool().await;
+    let conversations = roles::viewable_conversations(pool, user_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    // One extra row tells whether there is another page
+    let rows = sqlx::query_as::<_, MessageRow>(&format!(
+        "{MESSAGE_SELECT}
+         WHERE m.conversation_id = ANY($2)
+           AND m.deleted_at IS NULL
+           AND (m.expires_at IS NULL OR m.expires_at > NOW())
+           AND m.sender_id <> $1
+           AND (m.mentions_everyone
+                OR EXISTS (SELECT 1 FROM message_mentions mm WHERE mm.message_id = m.id AND mm.user_id = $1))
+           AND NOT EXISTS (SELECT 1 FROM message_hidden h WHERE h.message_id = m.id AND h.user_id = $1)
+           AND ($3::uuid IS NULL OR (m.created_at, m.id) < (SELECT c.created_at, c.id FROM messages c WHERE c.id = $3))
+         ORDER BY m.created_at DESC, m.id DESC
+         LIMIT $4"
+    ))
+    .bind(user_id)
+    .bind(&conversations)
+    .bind(cursor)
+    .bind(limit + 1)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let mut messages: Vec<MessageResponse> = rows.into_iter().map(message_from_row).collect();
+    let next_cursor = if messages.len() as i64 > limit {
+        messages.truncate(limit as usize);
+        messages.last().map(|m| m.id.clone())
+    } else {
+        None
+    };
+
+    reactions::attach_reactions(pool, user_id, &mut messages)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    attachments::attach_attachments(pool, user_id, &mut messages)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    link_previews::attach_link_previews(pool, &mut messages)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(MentionPage { messages, next_cursor })
+}
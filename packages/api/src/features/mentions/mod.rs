@@ -0,0 +1,233 @@
+pub mod list;
+pub mod suggest;
+
+/// Mentioning this notifies everyone who can see the conversation. Only
+/// honoured for senders with `MENTION_EVERYONE`; otherwise it's plain text.
+pub const EVERYONE: &str = "everyone";
+/// Mentions beyond this many in one message are ignored.
+pub const MAX_MENTIONS_PER_MESSAGE: usize = 20;
+/// Most autocomplete suggestions returned at once.
+pub const MAX_SUGGESTIONS: i64 = 10;
+
+/// Characters a mentionable username is made of.
+pub fn is_username_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.' | '-')
+}
+
+/// Lowercased usernames mentioned as `@name`, in order and without duplicates.
+///
+/// An `@` only starts a mention at the beginning of the text or after a
+/// character that can't be part of a name, so email addresses don't count.
+/// Trailing dots are punctuation: "thanks @sam." mentions `sam`. Code spans
+/// and code blocks are skipped, so quoting a mention in code doesn't notify.
+///
+/// Shared with the UI so the composer agrees with the server.
+pub fn parse_mentions(content: &str) -> Vec<String> {
+    let content = &mentionable_text(content);
+    let mut mentions: Vec<String> = Vec::new();
+    let mut previous: Option<char> = None;
+
+    for (i, c) in content.char_indices() {
+        let starts_mention = c == '@' && !previous.is_some_and(is_username_char);
+        previous = Some(c);
+        if !starts_mention {
+            continue;
+        }
+
+        let rest = &content[i + 1..];
+        let end = rest.find(|c: char| !is_username_char(c)).unwrap_or(rest.len());
+        let name = rest[..end].trim_end_matches('.').to_lowercase();
+        if !name.is_empty() && !mentions.contains(&name) {
+            mentions.push(name);
+        }
+    }
+
+    mentions
+}
+
+/// The text of `content` outside code, as the reader sees it. Each code span
+/// or block leaves a newline behind so the text around it stays apart.
+fn mentionable_text(content: &str) -> String {
+    use crate::markdown::{parse, Block, Inline};
+
+    fn inlines(nodes: &[Inline], text: &mut String) {
+        for node in nodes {
+            match node {
+                Inline::Text(t) => text.push_str(t),
+                Inline::Bold(inner) | Inline::Italic(inner) | Inline::Spoiler(inner) => inlines(inner, text),
+                Inline::Link { label, .. } => inlines(label, text),
+                Inline::Code(_) | Inline::LineBreak => text.push('\n'),
+            }
+        }
+    }
+
+    fn blocks(nodes: &[Block], text: &mut String) {
+        for node in nodes {
+            match node {
+                Block::Paragraph(nodes) => inlines(nodes, text),
+                Block::CodeBlock { .. } => {}
+                Block::Quote(nodes) => blocks(nodes, text),
+            }
+            text.push('\n');
+        }
+    }
+
+    let mut text = String::new();
+    blocks(&parse(content), &mut text);
+    text
+}
+
+/// The partial username being typed at the end of `text`, for autocomplete.
+/// `Some("")` right after a bare `@`; `None` when the text doesn't end in a mention.
+pub fn trailing_mention(text: &str) -> Option<&str> {
+    let at = text.rfind('@')?;
+    let name = &text[at + 1..];
+    let starts_mention = !text[..at].chars().next_back().is_some_and(is_username_char);
+    (starts_mention && name.chars().all(is_username_char)).then_some(name)
+}
+
+/// Who a message mentions after an insert or edit, and who to notify.
+#[cfg(feature = "server")]
+pub(crate) struct RecordedMentions {
+    /// Everyone currently mentioned by name.
+    pub user_ids: Vec<uuid::Uuid>,
+    pub everyone: bool,
+    /// Members to notify: newly mentioned ones, plus every other member when
+    /// `@everyone` was just added. Paired with whether it was via `@everyone`.
+    pub notify: Vec<(uuid::Uuid, bool)>,
+}
+
+/// Resolve the mentions in `content` and store them for `message_id`,
+/// replacing any from a previous version. Only members who can view the
+/// conversation can be mentioned, and nobody is notified of their own message.
+#[cfg(feature = "server")]
+pub(crate) async fn record_mentions(
+    pool: &sqlx::PgPool,
+    conn: &mut sqlx::PgConnection,
+    conversation_id: uuid::Uuid,
+    message_id: uuid::Uuid,
+    sender_id: uuid::Uuid,
+    content: &str,
+) -> Result<RecordedMentions, sqlx::Error> {
+    use crate::permissions::Permissions;
+
+    let mut names = parse_mentions(content);
+    let wants_everyone = names.iter().any(|n| n == EVERYONE);
+    names.retain(|n| n != EVERYONE);
+    names.truncate(MAX_MENTIONS_PER_MESSAGE);
+
+    let everyone = wants_everyone
+        && crate::features::roles::user_permissions(pool, conversation_id, sender_id)
+            .await?
+            .is_some_and(|p| p.contains(Permissions::MENTION_EVERYONE));
+
+    let members = crate::features::conversations::member_ids(pool, conversation_id).await?;
+    let user_ids: Vec<uuid::Uuid> = if names.is_empty() {
+        Vec::new()
+    } else {
+        sqlx::query_scalar::<_, uuid::Uuid>("SELECT id FROM users WHERE LOWER(username) = ANY($1)")
+            .bind(&names)
+            .fetch_all(&mut *conn)
+            .await?
+            .into_iter()
+            .filter(|id| *id != sender_id && members.contains(id))
+            .collect()
+    };
+
+    let previous = sqlx::query_scalar::<_, uuid::Uuid>(
+        "DELETE FROM message_mentions WHERE message_id = $1 RETURNING user_id",
+    )
+    .bind(message_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    sqlx::query("INSERT INTO message_mentions (message_id, user_id) SELECT $1, UNNEST($2::uuid[])")
+        .bind(message_id)
+        .bind(&user_ids)
+        .execute(&mut *conn)
+        .await?;
+
+    let previously_everyone = sqlx::query_scalar::<_, bool>("SELECT mentions_everyone FROM messages WHERE id = $1")
+        .bind(message_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    if previously_everyone != everyone {
+        sqlx::query("UPDATE messages SET mentions_everyone = $2 WHERE id = $1")
+            .bind(message_id)
+            .bind(everyone)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    let mut notify: Vec<(uuid::Uuid, bool)> =
+        user_ids.iter().filter(|id| !previous.contains(id)).map(|id| (*id, false)).collect();
+    if everyone && !previously_everyone {
+        for member in members.iter().filter(|m| **m != sender_id && !user_ids.contains(m)) {
+            notify.push((*member, true));
+        }
+    }
+
+    Ok(RecordedMentions { user_ids, everyone, notify })
+}
+
+/// Push a mention event to each member in `notify`. Sent to the user
+/// directly rather than with the conversation's messages, so clients can
+/// alert even in conversations they otherwise keep quiet.
+#[cfg(feature = "server")]
+pub(crate) fn notify_mentions(message: &crate::features::messages::create::MessageResponse, notify: &[(uuid::Uuid, bool)]) {
+    use crate::events::{MentionNotice, WsEvent};
+    use crate::features::messages::REPLY_PREVIEW_CHARS;
+    use crate::markdown::to_plain_text;
+
+    for (user_id, everyone) in notify {
+        let notice = MentionNotice {
+            message_id: message.id.clone(),
+            conversation_id: message.conversation_id.clone(),
+            sender_id: message.sender_id.clone(),
+            content: to_plain_text(&message.content).chars().take(REPLY_PREVIEW_CHARS).collect(),
+            everyone: *everyone,
+        };
+        crate::ws::send_event(*user_id, &WsEvent::Mentioned(notice));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailing_punctuation_is_not_part_of_the_name() {
+        assert_eq!(parse_mentions("thanks @sam."), ["sam"]);
+        assert_eq!(parse_mentions("@sam, @alex! (@kim) @j.doe..."), ["sam", "alex", "kim", "j.doe"]);
+    }
+
+    #[test]
+    fn email_addresses_are_not_mentions() {
+        assert!(parse_mentions("mail sam@example.com").is_empty());
+        assert_eq!(parse_mentions("@sam@example.com"), ["sam"]);
+        assert!(parse_mentions("@ alone").is_empty());
+    }
+
+    #[test]
+    fn names_are_lowercased_and_deduplicated() {
+        assert_eq!(parse_mentions("@Sam and @sam and @SAM then @everyone @Everyone"), ["sam", EVERYONE]);
+    }
+
+    #[test]
+    fn mentions_inside_code_are_ignored() {
+        assert!(parse_mentions("run `notify @sam` later").is_empty());
+        assert!(parse_mentions("```\nping @sam\n```").is_empty());
+        assert_eq!(parse_mentions("`code`@sam **@alex** > @kim"), ["sam", "alex", "kim"]);
+        assert_eq!(parse_mentions("> quoting @kim\n\n||@lee||"), ["kim", "lee"]);
+    }
+
+    #[test]
+    fn trailing_mention_is_the_name_being_typed() {
+        assert_eq!(trailing_mention("hey @"), Some(""));
+        assert_eq!(trailing_mention("hey @sa"), Some("sa"));
+        assert_eq!(trailing_mention("@j.do"), Some("j.do"));
+        assert_eq!(trailing_mention("hey @sam "), None);
+        assert_eq!(trailing_mention("sam@exa"), None);
+        assert_eq!(trailing_mention("no mention"), None);
+    }
+}
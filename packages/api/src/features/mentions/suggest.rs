@@ -0,0 +1,62 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SuggestMentionsRequest {
+    pub token: String,
+    pub conversation_id: String,
+    /// What has been typed after the `@` so far; may be empty.
+    pub prefix: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MentionSuggestion {
+    pub user_id: String,
+    pub username: String,
+}
+
+/// Members of the conversation whose username starts with `prefix`, for
+/// composer autocomplete. Excludes the caller and usernames that can't be
+/// written as a mention.
+#[post("/api/mentions/suggest")]
+pub async fn suggest_mentions(req: SuggestMentionsRequest) -> Result<Vec<MentionSuggestion>, ServerFnError> {
+    use super::MAX_SUGGESTIONS;
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::{conversations, roles};
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let conversation_id: uuid::Uuid = req
+        .conversation_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid conversation_id: {e}")))?;
+
+    let pool = db::pool().await;
+    roles::require_permission(pool, conversation_id, user_id, Permissions::VIEW_CHANNEL).await?;
+    let members = conversations::member_ids(pool, conversation_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let rows = sqlx::query_as::<_, (uuid::Uuid, String)>(
+        "SELECT id, username FROM users
+         WHERE id = ANY($1) AND id <> $2
+           AND starts_with(LOWER(username), LOWER($3))
+           AND username ~ '^[[:alnum:]_.-]+$'
+         ORDER BY LOWER(username)
+         LIMIT $4",
+    )
+    .bind(members.as_slice())
+    .bind(user_id)
+    .bind(&req.prefix)
+    .bind(MAX_SUGGESTIONS)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, username)| MentionSuggestion { user_id: id.to_string(), username })
+        .collect())
+}
@@ -1,61 +1,297 @@
 use dioxus::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::features::attachments::Attachment;
+use crate::features::link_previews::LinkPreview;
+use crate::features::reactions::ReactionCount;
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct CreateMessageRequest {
     pub token: String,
-    pub recipient_id: String,
+    /// Post into this conversation. Either this or `recipient_id` is required.
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    /// Send a direct message, starting the conversation on first contact.
+    #[serde(default)]
+    pub recipient_id: Option<String>,
+    /// May be empty when the message carries attachments.
     pub content: String,
+    /// Uploads from `/api/attachments/upload` to send with the message.
+    #[serde(default)]
+    pub attachment_ids: Vec<String>,
+    /// Quote and thread under this message, which must be in the same conversation.
+    #[serde(default)]
+    pub reply_to_id: Option<String>,
+}
+
+/// The parent a reply quotes, reflecting the parent's current state.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReplyPreview {
+    pub id: String,
+    pub sender_id: String,
+    /// A truncated copy of the parent's content; `None` once it was deleted.
+    pub content: Option<String>,
+    pub edited: bool,
+    pub deleted: bool,
+}
+
+/// Where a forwarded message was first sent. Forwarding a forward keeps the
+/// original's provenance.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ForwardedFrom {
+    /// The original message; `None` once it is gone for good.
+    pub message_id: Option<String>,
+    /// Who wrote the original; `None` once their account is gone.
+    pub sender_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct MessageResponse {
     pub id: String,
+    pub conversation_id: String,
     pub sender_id: String,
-    pub recipient_id: String,
+    /// The other member of a direct conversation; `None` in groups.
+    pub recipient_id: Option<String>,
     pub content: String,
     pub created_at: String,
+    /// Set once the sender has edited the message; see `list_message_edits` for prior versions.
+    pub edited_at: Option<String>,
+    /// Set when the sender deleted the message for everyone; `content` is then empty.
+    pub deleted_at: Option<String>,
+    /// Whether a recipient device has acknowledged this message over WebSocket.
+    pub delivered: bool,
+    /// Whether another member's read marker has reached this message ("Seen").
+    pub read: bool,
+    /// Reactions grouped by emoji, in the order they were first used.
+    pub reactions: Vec<ReactionCount>,
+    pub attachments: Vec<Attachment>,
+    pub reply_to: Option<ReplyPreview>,
+    /// Number of live replies threaded under this message.
+    pub reply_count: i64,
+    /// Ids of the users mentioned by `@username`.
+    pub mentions: Vec<String>,
+    /// Whether the message notified everyone with `@everyone`.
+    pub mentions_everyone: bool,
+    /// Cards for the links in `content`; filled in by a `message_updated`
+    /// event shortly after the message is sent.
+    pub link_previews: Vec<LinkPreview>,
+    /// When the message disappears, if the conversation has a message timer.
+    pub expires_at: Option<String>,
+    /// `message` for what people send, or `pin_notice` for the notice posted
+    /// when `sender_id` pinned the message in `reply_to`.
+    pub kind: String,
+    /// Set when the message was forwarded from another conversation.
+    pub forwarded_from: Option<ForwardedFrom>,
 }
 
 #[post("/api/messages/create")]
 pub async fn create_message(req: CreateMessageRequest) -> Result<MessageResponse, ServerFnError> {
     use crate::auth::validate_token;
-    use crate::db;
+    use crate::features::attachments;
 
     let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
     let sender_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
-    let recipient_id: uuid::Uuid = req
-        .recipient_id
-        .parse()
-        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid recipient_id: {e}")))?;
 
-    if req.content.is_empty() {
+    let reply_to_id: Option<uuid::Uuid> = req
+        .reply_to_id
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid reply_to_id: {e}")))?;
+
+    let outgoing = Outgoing {
+        conversation_id: req.conversation_id,
+        recipient_id: req.recipient_id,
+        content: req.content,
+        attachment_ids: attachments::parse_attachment_ids(&req.attachment_ids)?,
+        reply_to_id,
+        scheduled_id: None,
+        forward_of: None,
+    };
+    let pool = crate::db::pool().await;
+    let response = send_message(pool, sender_id, outgoing).await?;
+    if let Ok(conversation_id) = response.conversation_id.parse() {
+        crate::features::drafts::clear_draft(pool, sender_id, conversation_id).await;
+    }
+    Ok(response)
+}
+
+#[cfg(feature = "server")]
+#[derive(sqlx::FromRow)]
+struct Inserted {
+    id: uuid::Uuid,
+    recipient_id: Option<uuid::Uuid>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    forwarded_from_id: Option<uuid::Uuid>,
+    forwarded_from_sender_id: Option<uuid::Uuid>,
+}
+
+/// A message to send as a user, already parsed from its request.
+#[cfg(feature = "server")]
+pub(crate) struct Outgoing {
+    pub conversation_id: Option<String>,
+    pub recipient_id: Option<String>,
+    pub content: String,
+    pub attachment_ids: Vec<uuid::Uuid>,
+    pub reply_to_id: Option<uuid::Uuid>,
+    /// The scheduled message being delivered. Stored on the message, where
+    /// it is unique, so a scheduled message can't be sent twice.
+    pub scheduled_id: Option<uuid::Uuid>,
+    /// The message being forwarded. Its attachments are copied onto the new
+    /// message and its provenance recorded; the caller checks it may be read
+    /// and charges the forward against the rate limits.
+    pub forward_of: Option<uuid::Uuid>,
+}
+
+/// Send `outgoing` as `sender_id`: check permissions, store the message with
+/// its attachments and mentions, and tell every member. Shared by
+/// [`create_message`], scheduled delivery and forwarding.
+#[cfg(feature = "server")]
+pub(crate) async fn send_message(
+    pool: &sqlx::PgPool,
+    sender_id: uuid::Uuid,
+    outgoing: Outgoing,
+) -> Result<MessageResponse, ServerFnError> {
+    use super::{forwarded_from, reply_preview};
+    use crate::content;
+    use crate::events::WsEvent;
+    use crate::features::{attachments, blocks, conversations, link_previews, mentions};
+    use crate::markdown;
+    use crate::permissions::Permissions;
+
+    let content = content::normalize_content(&outgoing.content).map_err(|e| ServerFnError::new(e.to_string()))?;
+    if content.is_empty() && outgoing.attachment_ids.is_empty() && outgoing.forward_of.is_none() {
         return Err(ServerFnError::new("Message content cannot be empty"));
     }
 
-    let row = sqlx::query_as::<_, (uuid::Uuid, chrono::DateTime<chrono::Utc>)>(
-        "INSERT INTO messages (sender_id, recipient_id, content) VALUES ($1, $2, $3) RETURNING id, created_at",
+    let conversation_id = conversations::resolve_conversation(
+        pool,
+        sender_id,
+        outgoing.conversation_id.as_deref(),
+        outgoing.recipient_id.as_deref(),
+        true,
+        Permissions::SEND_MESSAGES,
     )
+    .await?
+    .ok_or_else(|| ServerFnError::new("Conversation not found"))?;
+    blocks::require_not_blocked(pool, conversation_id, sender_id).await?;
+
+    let reply_to = match outgoing.reply_to_id {
+        Some(parent_id) => {
+            let parent = sqlx::query_as::<
+                _,
+                (uuid::Uuid, String, Option<chrono::DateTime<chrono::Utc>>, Option<chrono::DateTime<chrono::Utc>>),
+            >(
+                "SELECT sender_id, content, edited_at, deleted_at FROM messages
+                 WHERE id = $1 AND conversation_id = $2 AND kind = 'message'",
+            )
+            .bind(parent_id)
+            .bind(conversation_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| ServerFnError::new(e.to_string()))?
+            .ok_or_else(|| ServerFnError::new("The message you are replying to is not in this conversation"))?;
+
+            if parent.3.is_some() {
+                return Err(ServerFnError::new("Cannot reply to a deleted message"));
+            }
+            Some(reply_preview(parent_id, parent.0, parent.1, parent.2, parent.3))
+        }
+        None => None,
+    };
+
+    // Scheduled messages were limited by the pending cap when they were
+    // queued, and forwarding charges the whole forward once
+    let reservation = if outgoing.scheduled_id.is_none() && outgoing.forward_of.is_none() {
+        Some(super::rate_limit::reserve_send(pool, sender_id, conversation_id, &content).await?)
+    } else {
+        None
+    };
+
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    // Direct messages keep their single recipient; group messages have none.
+    // The conversation's timer, if any, starts when the message is sent.
+    let row = sqlx::query_as::<_, Inserted>(
+        "INSERT INTO messages
+             (conversation_id, sender_id, recipient_id, content, content_plain, reply_to_id, scheduled_message_id,
+              expires_at, forwarded_from_id, forwarded_from_sender_id)
+         SELECT c.id, $2,
+                CASE WHEN c.kind = 'direct' THEN COALESCE(
+                    (SELECT user_id FROM conversation_members WHERE conversation_id = c.id AND user_id <> $2 LIMIT 1),
+                    $2
+                ) END,
+                $3, $4, $5, $6, NOW() + make_interval(secs => c.message_ttl_secs),
+                CASE WHEN s.forwarded_from_id IS NULL AND s.forwarded_from_sender_id IS NULL
+                     THEN s.id ELSE s.forwarded_from_id END,
+                CASE WHEN s.forwarded_from_id IS NULL AND s.forwarded_from_sender_id IS NULL
+                     THEN s.sender_id ELSE s.forwarded_from_sender_id END
+         FROM conversations c
+         LEFT JOIN messages s ON s.id = $7
+         WHERE c.id = $1
+         RETURNING id, recipient_id, created_at, expires_at, forwarded_from_id, forwarded_from_sender_id",
+    )
+    .bind(conversation_id)
     .bind(sender_id)
-    .bind(recipient_id)
-    .bind(&req.content)
-    .fetch_one(db::pool().await)
+    .bind(&content)
+    .bind(markdown::to_plain_text(&content))
+    .bind(outgoing.reply_to_id)
+    .bind(outgoing.scheduled_id)
+    .bind(outgoing.forward_of)
+    .fetch_one(&mut *tx)
     .await
     .map_err(|e| ServerFnError::new(e.to_string()))?;
 
-    let response = MessageResponse {
-        id: row.0.to_string(),
+    attachments::link_attachments(&mut tx, conversation_id, sender_id, row.id, &outgoing.attachment_ids).await?;
+    if let Some(source_id) = outgoing.forward_of {
+        attachments::copy_attachments(&mut tx, source_id, conversation_id, sender_id, row.id).await?;
+    }
+
+    let mentioned = mentions::record_mentions(pool, &mut tx, conversation_id, row.id, sender_id, &content)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    conversations::record_message(&mut tx, conversation_id, row.id, row.created_at)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+    if let Some(reservation) = reservation {
+        reservation.commit();
+    }
+
+    let mut response = MessageResponse {
+        id: row.id.to_string(),
+        conversation_id: conversation_id.to_string(),
         sender_id: sender_id.to_string(),
-        recipient_id: recipient_id.to_string(),
-        content: req.content,
-        created_at: row.1.to_rfc3339(),
+        recipient_id: row.recipient_id.map(|id| id.to_string()),
+        content,
+        created_at: row.created_at.to_rfc3339(),
+        edited_at: None,
+        deleted_at: None,
+        delivered: false,
+        read: false,
+        reactions: Vec::new(),
+        attachments: Vec::new(),
+        reply_to,
+        reply_count: 0,
+        mentions: mentioned.user_ids.iter().map(|id| id.to_string()).collect(),
+        mentions_everyone: mentioned.everyone,
+        link_previews: Vec::new(),
+        expires_at: row.expires_at.map(|t| t.to_rfc3339()),
+        kind: "message".to_string(),
+        forwarded_from: forwarded_from(row.forwarded_from_id, row.forwarded_from_sender_id),
     };
+    attachments::attach_attachments(pool, sender_id, std::slice::from_mut(&mut response))
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
 
-    // Broadcast to recipient and sender via WebSocket
-    if let Ok(json) = serde_json::to_string(&response) {
-        crate::ws::broadcast_to_user(recipient_id, &json);
-        crate::ws::broadcast_to_user(sender_id, &json);
-    }
+    // Broadcast to every member, the sender's other devices included
+    conversations::broadcast(pool, conversation_id, &WsEvent::MessageCreated(response.clone())).await;
+    conversations::notify_all_summaries(pool, conversation_id);
+    mentions::notify_mentions(&response, &mentioned.notify);
+    link_previews::spawn_unfurl(row.id);
 
     Ok(response)
 }
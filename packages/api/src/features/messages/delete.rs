@@ -5,12 +5,20 @@ use serde::{Deserialize, Serialize};
 pub struct DeleteMessageRequest {
     pub token: String,
     pub message_id: String,
+    /// Replace the message with a tombstone for every member. Allowed for the
+    /// sender and for members who can manage messages in the conversation.
+    /// Otherwise the message is only hidden from the caller's own view.
+    #[serde(default)]
+    pub for_everyone: bool,
 }
 
 #[post("/api/messages/delete")]
 pub async fn delete_message(req: DeleteMessageRequest) -> Result<bool, ServerFnError> {
     use crate::auth::validate_token;
     use crate::db;
+    use crate::events::{MessageDeletion, WsEvent};
+    use crate::features::conversations;
+    use crate::permissions::Permissions;
 
     let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
     let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
@@ -19,16 +27,98 @@ pub async fn delete_message(req: DeleteMessageRequest) -> Result<bool, ServerFnE
         .parse()
         .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid message_id: {e}")))?;
 
-    let result = sqlx::query("DELETE FROM messages WHERE id = $1 AND sender_id = $2")
+    let pool = db::pool().await;
+    let (_, permissions) = super::message_conversation(pool, message_id, user_id, Permissions::VIEW_CHANNEL).await?;
+
+    if !req.for_everyone {
+        let hidden_at = sqlx::query_scalar::<_, chrono::DateTime<chrono::Utc>>(
+            "INSERT INTO message_hidden (user_id, message_id) VALUES ($2, $1)
+             ON CONFLICT (user_id, message_id) DO UPDATE SET hidden_at = message_hidden.hidden_at
+             RETURNING hidden_at",
+        )
         .bind(message_id)
         .bind(user_id)
-        .execute(db::pool().await)
+        .fetch_one(pool)
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))?;
 
-    if result.rows_affected() == 0 {
-        return Err(ServerFnError::new("Message not found or you are not the sender"));
+        // Only the caller's other devices need to know
+        let deletion = MessageDeletion {
+            message_id: message_id.to_string(),
+            for_everyone: false,
+            deleted_at: hidden_at.to_rfc3339(),
+            expired: false,
+        };
+        crate::ws::send_event(user_id, &WsEvent::MessageDeleted(deletion));
+        return Ok(true);
     }
 
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let (conversation_id, deleted_at) = sqlx::query_as::<_, (uuid::Uuid, chrono::DateTime<chrono::Utc>)>(
+        "UPDATE messages
+         SET content = '', content_plain = NULL, mentions_everyone = FALSE, deleted_at = NOW(), updated_at = NOW()
+         WHERE id = $1 AND (sender_id = $2 OR $3) AND deleted_at IS NULL
+         RETURNING conversation_id, deleted_at",
+    )
+    .bind(message_id)
+    .bind(user_id)
+    .bind(permissions.contains(Permissions::MANAGE_MESSAGES))
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?
+    .ok_or_else(|| ServerFnError::new("Message not found or you can't delete it"))?;
+
+    // Prior versions would otherwise still expose the deleted content
+    sqlx::query("DELETE FROM message_edits WHERE message_id = $1")
+        .bind(message_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    // Nobody can react to a tombstone, and it no longer mentions anyone
+    sqlx::query("DELETE FROM message_reactions WHERE message_id = $1")
+        .bind(message_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    sqlx::query("DELETE FROM message_mentions WHERE message_id = $1")
+        .bind(message_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    sqlx::query("DELETE FROM message_link_previews WHERE message_id = $1")
+        .bind(message_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    // A tombstone has nothing left to pin; clients refetch pins on deletion
+    sqlx::query("DELETE FROM pinned_messages WHERE message_id = $1")
+        .bind(message_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    // Attachments go with the content; their blobs are purged once unreferenced
+    sqlx::query("DELETE FROM attachments WHERE message_id = $1")
+        .bind(message_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let event = WsEvent::MessageDeleted(MessageDeletion {
+        message_id: message_id.to_string(),
+        for_everyone: true,
+        deleted_at: deleted_at.to_rfc3339(),
+        expired: false,
+    });
+    conversations::broadcast(pool, conversation_id, &event).await;
+    conversations::notify_all_summaries(pool, conversation_id);
+
     Ok(true)
 }
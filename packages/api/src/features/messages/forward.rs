@@ -0,0 +1,114 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::create::MessageResponse;
+
+/// Most messages one forward request can carry.
+pub const MAX_FORWARD_MESSAGES: usize = 10;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ForwardMessagesRequest {
+    pub token: String,
+    /// Messages to forward, from any conversations the caller can read.
+    pub message_ids: Vec<String>,
+    /// Forward into this conversation. Either this or `recipient_id` is required.
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    /// Forward as direct messages, starting the conversation on first contact.
+    #[serde(default)]
+    pub recipient_id: Option<String>,
+}
+
+/// Copy messages into another conversation, oldest first, as new messages
+/// from the caller. Attachments are copied by reference and each copy
+/// records the original in `forwarded_from`. Deleted messages, pin notices,
+/// messages the caller hid and messages whose author blocked the caller or
+/// a member of the target can't be forwarded. Members of the target
+/// receive `message_created` for each copy.
+#[post("/api/messages/forward")]
+pub async fn forward_messages(req: ForwardMessagesRequest) -> Result<Vec<MessageResponse>, ServerFnError> {
+    use super::create::{send_message, Outgoing};
+    use super::message_conversation;
+    use super::rate_limit;
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::{blocks, conversations};
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    let mut message_ids: Vec<uuid::Uuid> = Vec::with_capacity(req.message_ids.len());
+    for id in &req.message_ids {
+        let id: uuid::Uuid = id
+            .parse()
+            .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid message_id: {e}")))?;
+        if !message_ids.contains(&id) {
+            message_ids.push(id);
+        }
+    }
+    if message_ids.is_empty() {
+        return Err(ServerFnError::new("Choose at least one message to forward"));
+    }
+    if message_ids.len() > MAX_FORWARD_MESSAGES {
+        return Err(ServerFnError::new(format!(
+            "At most {MAX_FORWARD_MESSAGES} messages can be forwarded at once"
+        )));
+    }
+
+    let pool = db::pool().await;
+    for &message_id in &message_ids {
+        message_conversation(pool, message_id, user_id, Permissions::VIEW_CHANNEL).await?;
+    }
+
+    // Check every source up front so a bad one doesn't leave a partial forward
+    let sources = sqlx::query_as::<_, (uuid::Uuid, String)>(
+        "SELECT m.id, m.content FROM messages m
+         WHERE m.id = ANY($1) AND m.deleted_at IS NULL AND m.kind = 'message'
+           AND NOT EXISTS (SELECT 1 FROM message_hidden h WHERE h.message_id = m.id AND h.user_id = $2)
+         ORDER BY m.created_at ASC",
+    )
+    .bind(&message_ids)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if sources.len() != message_ids.len() {
+        return Err(ServerFnError::new("Some of these messages can't be forwarded"));
+    }
+
+    let conversation_id = conversations::resolve_conversation(
+        pool,
+        user_id,
+        req.conversation_id.as_deref(),
+        req.recipient_id.as_deref(),
+        true,
+        Permissions::SEND_MESSAGES,
+    )
+    .await?
+    .ok_or_else(|| ServerFnError::new("Conversation not found"))?;
+    blocks::require_forwardable(pool, &message_ids, user_id, conversation_id).await?;
+
+    // The forward counts as one send, so a batch within the limits can't be
+    // cut short by them halfway through
+    let texts: Vec<&str> = sources.iter().map(|(_, content)| content.as_str()).collect();
+    let reservation = rate_limit::reserve_send(pool, user_id, conversation_id, &texts.join("\n")).await?;
+
+    let mut forwarded = Vec::with_capacity(sources.len());
+    for (source_id, content) in sources {
+        let outgoing = Outgoing {
+            conversation_id: Some(conversation_id.to_string()),
+            recipient_id: None,
+            content,
+            attachment_ids: Vec::new(),
+            reply_to_id: None,
+            scheduled_id: None,
+            forward_of: Some(source_id),
+        };
+        forwarded.push(send_message(pool, user_id, outgoing).await?);
+    }
+    reservation.commit();
+
+    Ok(forwarded)
+}
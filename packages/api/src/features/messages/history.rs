@@ -0,0 +1,52 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MessageEditsRequest {
+    pub token: String,
+    pub message_id: String,
+}
+
+/// A previous version of a message, replaced at `edited_at`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MessageEdit {
+    pub content: String,
+    pub edited_at: String,
+}
+
+/// List a message's prior versions, oldest first. Any member of the conversation may view them.
+#[post("/api/messages/history")]
+pub async fn list_message_edits(req: MessageEditsRequest) -> Result<Vec<MessageEdit>, ServerFnError> {
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let message_id: uuid::Uuid = req
+        .message_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid message_id: {e}")))?;
+
+    let pool = db::pool().await;
+
+    super::message_conversation(pool, message_id, user_id, Permissions::VIEW_CHANNEL).await?;
+
+    let rows = sqlx::query_as::<_, (String, chrono::DateTime<chrono::Utc>)>(
+        "SELECT content, edited_at FROM message_edits WHERE message_id = $1 ORDER BY edited_at ASC",
+    )
+    .bind(message_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let edits = rows
+        .into_iter()
+        .map(|r| MessageEdit {
+            content: r.0,
+            edited_at: r.1.to_rfc3339(),
+        })
+        .collect();
+
+    Ok(edits)
+}
@@ -6,42 +6,70 @@ use super::create::MessageResponse;
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ListMessagesRequest {
     pub token: String,
-    pub other_user_id: String,
+    /// List this conversation. Either this or `other_user_id` is required.
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    /// List the direct conversation with this user.
+    #[serde(default)]
+    pub other_user_id: Option<String>,
 }
 
+/// Messages deleted for everyone come back as tombstones (empty `content`, `deleted_at` set);
+/// messages the caller deleted for themselves are omitted.
 #[post("/api/messages/list")]
 pub async fn list_messages(req: ListMessagesRequest) -> Result<Vec<MessageResponse>, ServerFnError> {
+    use super::{message_from_row, MessageRow, MESSAGE_SELECT};
     use crate::auth::validate_token;
     use crate::db;
+    use crate::features::{attachments, conversations, link_previews, reactions};
+    use crate::permissions::Permissions;
 
     let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
     let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
-    let other_id: uuid::Uuid = req
-        .other_user_id
-        .parse()
-        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid other_user_id: {e}")))?;
-
-    let rows = sqlx::query_as::<_, (uuid::Uuid, uuid::Uuid, uuid::Uuid, String, chrono::DateTime<chrono::Utc>)>(
-        "SELECT id, sender_id, recipient_id, content, created_at FROM messages
-         WHERE (sender_id = $1 AND recipient_id = $2) OR (sender_id = $2 AND recipient_id = $1)
-         ORDER BY created_at ASC",
+
+    let pool = db::pool().await;
+    let Some(conversation_id) = conversations::resolve_conversation(
+        pool,
+        user_id,
+        req.conversation_id.as_deref(),
+        req.other_user_id.as_deref(),
+        false,
+        Permissions::VIEW_CHANNEL,
     )
+    .await?
+    else {
+        return Ok(Vec::new());
+    };
+
+    // Typing relays only look members up in the cache, so fill it for the
+    // conversation the caller is about to type in
+    conversations::member_ids(pool, conversation_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let rows = sqlx::query_as::<_, MessageRow>(&format!(
+        "{MESSAGE_SELECT}
+         WHERE m.conversation_id = $2
+           AND (m.expires_at IS NULL OR m.expires_at > NOW())
+           AND NOT EXISTS (SELECT 1 FROM message_hidden h WHERE h.message_id = m.id AND h.user_id = $1)
+         ORDER BY m.created_at ASC"
+    ))
     .bind(user_id)
-    .bind(other_id)
-    .fetch_all(db::pool().await)
+    .bind(conversation_id)
+    .fetch_all(pool)
     .await
     .map_err(|e| ServerFnError::new(e.to_string()))?;
 
-    let messages = rows
-        .into_iter()
-        .map(|r| MessageResponse {
-            id: r.0.to_string(),
-            sender_id: r.1.to_string(),
-            recipient_id: r.2.to_string(),
-            content: r.3,
-            created_at: r.4.to_rfc3339(),
-        })
-        .collect();
+    let mut messages: Vec<MessageResponse> = rows.into_iter().map(message_from_row).collect();
+    reactions::attach_reactions(pool, user_id, &mut messages)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    attachments::attach_attachments(pool, user_id, &mut messages)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    link_previews::attach_link_previews(pool, &mut messages)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
 
     Ok(messages)
 }
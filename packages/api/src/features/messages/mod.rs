@@ -2,3 +2,336 @@ pub mod create;
 pub mod list;
 pub mod update;
 pub mod delete;
+pub mod history;
+pub mod thread;
+pub mod forward;
+pub mod rate_limit;
+pub mod search;
+
+#[cfg(feature = "server")]
+use create::MessageResponse;
+
+/// Characters of a parent message quoted in a reply preview.
+pub const REPLY_PREVIEW_CHARS: usize = 100;
+
+/// Selects everything needed to build a [`MessageResponse`] from `messages m`.
+/// Callers append their own `WHERE`/`ORDER BY`.
+#[cfg(feature = "server")]
+pub(crate) const MESSAGE_SELECT: &str =
+    "SELECT m.id, m.conversation_id, m.sender_id, m.recipient_id, m.content, m.created_at, m.edited_at, m.deleted_at,
+            m.delivered_at IS NOT NULL AS delivered,
+            EXISTS (SELECT 1 FROM conversation_members r
+                    WHERE r.conversation_id = m.conversation_id AND r.user_id <> m.sender_id
+                      AND r.last_read_message_at >= m.created_at) AS read,
+            p.id AS reply_id, p.sender_id AS reply_sender_id, p.content AS reply_content,
+            p.edited_at AS reply_edited_at, p.deleted_at AS reply_deleted_at,
+            (SELECT COUNT(*) FROM messages c
+             WHERE c.reply_to_id = m.id AND c.deleted_at IS NULL AND c.kind = 'message') AS reply_count,
+            ARRAY(SELECT mm.user_id FROM message_mentions mm WHERE mm.message_id = m.id) AS mentions,
+            m.mentions_everyone, m.expires_at, m.kind, m.forwarded_from_id, m.forwarded_from_sender_id
+     FROM messages m
+     LEFT JOIN messages p ON p.id = m.reply_to_id";
+
+#[cfg(feature = "server")]
+#[derive(sqlx::FromRow)]
+pub(crate) struct MessageRow {
+    id: uuid::Uuid,
+    conversation_id: uuid::Uuid,
+    sender_id: uuid::Uuid,
+    recipient_id: Option<uuid::Uuid>,
+    content: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+    edited_at: Option<chrono::DateTime<chrono::Utc>>,
+    deleted_at: Option<chrono::DateTime<chrono::Utc>>,
+    delivered: bool,
+    read: bool,
+    reply_id: Option<uuid::Uuid>,
+    reply_sender_id: Option<uuid::Uuid>,
+    reply_content: Option<String>,
+    reply_edited_at: Option<chrono::DateTime<chrono::Utc>>,
+    reply_deleted_at: Option<chrono::DateTime<chrono::Utc>>,
+    reply_count: i64,
+    mentions: Vec<uuid::Uuid>,
+    mentions_everyone: bool,
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    kind: String,
+    forwarded_from_id: Option<uuid::Uuid>,
+    forwarded_from_sender_id: Option<uuid::Uuid>,
+}
+
+#[cfg(feature = "server")]
+pub(crate) fn message_from_row(r: MessageRow) -> MessageResponse {
+    let reply_to = r.reply_id.zip(r.reply_sender_id).map(|(id, sender_id)| {
+        reply_preview(id, sender_id, r.reply_content.unwrap_or_default(), r.reply_edited_at, r.reply_deleted_at)
+    });
+
+    MessageResponse {
+        id: r.id.to_string(),
+        conversation_id: r.conversation_id.to_string(),
+        sender_id: r.sender_id.to_string(),
+        recipient_id: r.recipient_id.map(|id| id.to_string()),
+        content: r.content,
+        created_at: r.created_at.to_rfc3339(),
+        edited_at: r.edited_at.map(|t| t.to_rfc3339()),
+        deleted_at: r.deleted_at.map(|t| t.to_rfc3339()),
+        delivered: r.delivered,
+        read: r.read,
+        reactions: Vec::new(),
+        attachments: Vec::new(),
+        reply_to,
+        reply_count: r.reply_count,
+        // Tombstones deleted before their mentions were removed with them
+        // still have rows; never show those
+        mentions: match r.deleted_at {
+            Some(_) => Vec::new(),
+            None => r.mentions.iter().map(|id| id.to_string()).collect(),
+        },
+        mentions_everyone: r.mentions_everyone && r.deleted_at.is_none(),
+        link_previews: Vec::new(),
+        expires_at: r.expires_at.map(|t| t.to_rfc3339()),
+        kind: r.kind,
+        forwarded_from: forwarded_from(r.forwarded_from_id, r.forwarded_from_sender_id),
+    }
+}
+
+/// Provenance of a forwarded message, from its `forwarded_from_*` columns.
+#[cfg(feature = "server")]
+pub(crate) fn forwarded_from(
+    message_id: Option<uuid::Uuid>,
+    sender_id: Option<uuid::Uuid>,
+) -> Option<create::ForwardedFrom> {
+    if message_id.is_none() && sender_id.is_none() {
+        return None;
+    }
+    Some(create::ForwardedFrom {
+        message_id: message_id.map(|id| id.to_string()),
+        sender_id: sender_id.map(|id| id.to_string()),
+    })
+}
+
+/// Quote of a parent message, hiding its content once it was deleted for everyone.
+#[cfg(feature = "server")]
+pub(crate) fn reply_preview(
+    id: uuid::Uuid,
+    sender_id: uuid::Uuid,
+    content: String,
+    edited_at: Option<chrono::DateTime<chrono::Utc>>,
+    deleted_at: Option<chrono::DateTime<chrono::Utc>>,
+) -> create::ReplyPreview {
+    create::ReplyPreview {
+        id: id.to_string(),
+        sender_id: sender_id.to_string(),
+        content: match deleted_at {
+            Some(_) => None,
+            None => Some(crate::markdown::to_plain_text(&content).chars().take(REPLY_PREVIEW_CHARS).collect()),
+        },
+        edited: edited_at.is_some(),
+        deleted: deleted_at.is_some(),
+    }
+}
+
+/// How long after sending a message its sender may still edit it.
+/// Configured with `MESSAGE_EDIT_WINDOW_SECS` (default 15 minutes); `0` means no limit.
+#[cfg(feature = "server")]
+pub(crate) fn edit_window() -> Option<chrono::Duration> {
+    let secs = std::env::var("MESSAGE_EDIT_WINDOW_SECS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(15 * 60);
+    (secs > 0).then(|| chrono::Duration::seconds(secs))
+}
+
+/// The conversation a message belongs to and the caller's permissions there,
+/// checking they hold `needed`. Messages in conversations the caller can't
+/// view are reported as missing.
+#[cfg(feature = "server")]
+pub(crate) async fn message_conversation(
+    pool: &sqlx::PgPool,
+    message_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+    needed: crate::permissions::Permissions,
+) -> Result<(uuid::Uuid, crate::permissions::Permissions), dioxus::prelude::ServerFnError> {
+    use crate::permissions::Permissions;
+    use dioxus::prelude::ServerFnError;
+
+    // Expired messages are gone even before the reaper removes them
+    let conversation_id = sqlx::query_scalar::<_, uuid::Uuid>(
+        "SELECT conversation_id FROM messages WHERE id = $1 AND (expires_at IS NULL OR expires_at > NOW())",
+    )
+    .bind(message_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?
+    .ok_or_else(|| ServerFnError::new("Message not found"))?;
+
+    let permissions = crate::features::roles::user_permissions(pool, conversation_id, user_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .filter(|p| p.contains(Permissions::VIEW_CHANNEL))
+        .ok_or_else(|| ServerFnError::new("Message not found"))?;
+
+    if !permissions.contains(needed) {
+        return Err(ServerFnError::new("You don't have permission to do that here"));
+    }
+
+    Ok((conversation_id, permissions))
+}
+
+/// Record that a recipient device acknowledged a message and tell the sender.
+/// Only the first acknowledgement counts, so in groups "delivered" means
+/// delivered to at least one other member.
+#[cfg(feature = "server")]
+pub(crate) async fn mark_delivered(
+    pool: &sqlx::PgPool,
+    message_id: uuid::Uuid,
+    sender_id: uuid::Uuid,
+    recipient_id: uuid::Uuid,
+) -> Result<(), sqlx::Error> {
+    use crate::events::{DeliveryReceipt, WsEvent};
+
+    let row = sqlx::query_scalar::<_, chrono::DateTime<chrono::Utc>>(
+        "UPDATE messages SET delivered_at = NOW()
+         WHERE id = $1 AND sender_id = $2 AND delivered_at IS NULL
+         RETURNING delivered_at",
+    )
+    .bind(message_id)
+    .bind(sender_id)
+    .fetch_optional(pool)
+    .await?;
+
+    if let Some(delivered_at) = row {
+        let receipt = DeliveryReceipt {
+            message_id: message_id.to_string(),
+            recipient_id: recipient_id.to_string(),
+            delivered_at: delivered_at.to_rfc3339(),
+        };
+        crate::ws::send_event(sender_id, &WsEvent::MessageDelivered(receipt));
+    }
+
+    Ok(())
+}
+
+/// How long tombstones are kept before [`purge_deleted`] removes them.
+/// Configured with `DELETED_MESSAGE_RETENTION_DAYS` (default 30).
+#[cfg(feature = "server")]
+fn deleted_retention() -> chrono::Duration {
+    let days = std::env::var("DELETED_MESSAGE_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(30);
+    chrono::Duration::days(days.max(0))
+}
+
+/// Hard-delete tombstones older than the retention period, in batches so a
+/// large backlog never holds locks for long. Returns how many rows were removed.
+#[cfg(feature = "server")]
+pub(crate) async fn purge_deleted(pool: &sqlx::PgPool) -> Result<u64, sqlx::Error> {
+    const BATCH: i64 = 500;
+
+    let cutoff = chrono::Utc::now() - deleted_retention();
+    let mut total = 0;
+    loop {
+        let result = sqlx::query(
+            "DELETE FROM messages WHERE id IN (
+                 SELECT id FROM messages WHERE deleted_at < $1 LIMIT $2
+             )",
+        )
+        .bind(cutoff)
+        .bind(BATCH)
+        .execute(pool)
+        .await?;
+
+        total += result.rows_affected();
+        if result.rows_affected() < BATCH as u64 {
+            return Ok(total);
+        }
+    }
+}
+
+/// Hard-delete messages whose timer ran out, in batches, and tell each
+/// conversation they are gone. Attachment rows go with their messages; the
+/// blobs follow with the orphan purge. Returns how many messages were removed.
+#[cfg(feature = "server")]
+pub(crate) async fn purge_expired(pool: &sqlx::PgPool) -> Result<u64, sqlx::Error> {
+    use crate::events::{MessageDeletion, WsEvent};
+    use crate::features::conversations;
+
+    const BATCH: i64 = 500;
+
+    let mut total = 0;
+    loop {
+        let removed = sqlx::query_as::<_, (uuid::Uuid, uuid::Uuid, chrono::DateTime<chrono::Utc>)>(
+            "DELETE FROM messages WHERE id IN (
+                 SELECT id FROM messages WHERE expires_at <= NOW() LIMIT $1
+             )
+             RETURNING id, conversation_id, expires_at",
+        )
+        .bind(BATCH)
+        .fetch_all(pool)
+        .await?;
+
+        let mut touched = std::collections::HashSet::new();
+        for (message_id, conversation_id, expired_at) in &removed {
+            let event = WsEvent::MessageDeleted(MessageDeletion {
+                message_id: message_id.to_string(),
+                for_everyone: true,
+                deleted_at: expired_at.to_rfc3339(),
+                expired: true,
+            });
+            conversations::broadcast(pool, *conversation_id, &event).await;
+            touched.insert(*conversation_id);
+        }
+        for conversation_id in touched {
+            conversations::notify_all_summaries(pool, conversation_id);
+        }
+
+        total += removed.len() as u64;
+        if removed.len() < BATCH as usize {
+            return Ok(total);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    fn row(deleted_at: Option<chrono::DateTime<chrono::Utc>>) -> MessageRow {
+        MessageRow {
+            id: uuid::Uuid::new_v4(),
+            conversation_id: uuid::Uuid::new_v4(),
+            sender_id: uuid::Uuid::new_v4(),
+            recipient_id: None,
+            content: String::new(),
+            created_at: chrono::Utc::now(),
+            edited_at: None,
+            deleted_at,
+            delivered: false,
+            read: false,
+            reply_id: None,
+            reply_sender_id: None,
+            reply_content: None,
+            reply_edited_at: None,
+            reply_deleted_at: None,
+            reply_count: 0,
+            mentions: vec![uuid::Uuid::new_v4()],
+            mentions_everyone: true,
+            expires_at: None,
+            kind: "message".to_string(),
+            forwarded_from_id: None,
+            forwarded_from_sender_id: None,
+        }
+    }
+
+    #[test]
+    fn tombstones_carry_no_mentions_or_reactions() {
+        let tombstone = message_from_row(row(Some(chrono::Utc::now())));
+        assert!(tombstone.mentions.is_empty());
+        assert!(!tombstone.mentions_everyone);
+        assert!(tombstone.reactions.is_empty());
+
+        let live = message_from_row(row(None));
+        assert_eq!(live.mentions.len(), 1);
+        assert!(live.mentions_everyone);
+    }
+}
@@ -0,0 +1,331 @@
+//! Send rate limits and duplicate detection.
+//!
+//! Every sender has a token bucket, and so does every `(sender,
+//! conversation)` pair, the conversation standing in for the recipient, so
+//! one account can neither flood the server nor any one recipient. Accounts
+//! younger than [`NEW_ACCOUNT_HOURS`] and direct messages to someone who has
+//! never written back get smaller buckets.
+//! Sending the same text more than [`MAX_DUPLICATES`] times within
+//! [`DUPLICATE_WINDOW_SECS`] is refused regardless of the buckets.
+//!
+//! Buckets live in memory, so limits are per server process and reset on
+//! restart.
+
+use dioxus::prelude::ServerFnError;
+use serde::{Deserialize, Serialize};
+
+/// Accounts this young get the strict limits.
+pub const NEW_ACCOUNT_HOURS: i32 = 24;
+/// How far back identical messages are counted.
+pub const DUPLICATE_WINDOW_SECS: u64 = 60;
+/// Identical messages allowed within [`DUPLICATE_WINDOW_SECS`].
+pub const MAX_DUPLICATES: usize = 3;
+
+/// A token bucket's shape: `burst` sends at once, refilling at `per_minute`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limit {
+    pub burst: f64,
+    pub per_minute: f64,
+}
+
+/// Everything one account sends.
+pub const SENDER_LIMIT: Limit = Limit { burst: 10.0, per_minute: 60.0 };
+/// Everything one account sends to one conversation.
+pub const PAIR_LIMIT: Limit = Limit { burst: 5.0, per_minute: 30.0 };
+/// [`SENDER_LIMIT`] for new accounts.
+pub const NEW_ACCOUNT_SENDER_LIMIT: Limit = Limit { burst: 5.0, per_minute: 20.0 };
+/// [`PAIR_LIMIT`] for new accounts and for direct messages to non-contacts.
+pub const STRICT_PAIR_LIMIT: Limit = Limit { burst: 3.0, per_minute: 6.0 };
+
+/// A send refused by the limits above, carried in the `details` of the
+/// server function error so the UI can tell it apart from other failures.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SendError {
+    /// Try again in `retry_after` seconds.
+    RateLimited { retry_after: u64 },
+}
+
+impl SendError {
+    /// The send error behind a failed `create_message` or
+    /// `forward_messages` call, if that is why it failed.
+    pub fn from_server_error(error: &ServerFnError) -> Option<Self> {
+        match error {
+            ServerFnError::ServerError { details: Some(details), .. } => {
+                serde_json::from_value(details.clone()).ok()
+            }
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for SendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RateLimited { retry_after } => {
+                write!(f, "You're sending messages too quickly. Try again in {retry_after}s.")
+            }
+        }
+    }
+}
+
+impl From<SendError> for ServerFnError {
+    fn from(error: SendError) -> Self {
+        let code = match error {
+            SendError::RateLimited { .. } => 429,
+        };
+        ServerFnError::ServerError {
+            message: error.to_string(),
+            code,
+            details: serde_json::to_value(&error).ok(),
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+pub(crate) use limiter::{prune, reserve_send};
+
+#[cfg(feature = "server")]
+mod limiter {
+    use std::collections::VecDeque;
+    use std::hash::{DefaultHasher, Hash, Hasher};
+    use std::sync::OnceLock;
+    use std::time::{Duration, Instant};
+
+    use dashmap::DashMap;
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy)]
+    pub(super) struct Bucket {
+        tokens: f64,
+        updated: Instant,
+    }
+
+    impl Bucket {
+        pub(super) fn full(limit: Limit, now: Instant) -> Self {
+            Self { tokens: limit.burst, updated: now }
+        }
+
+        fn refill(&mut self, limit: Limit, now: Instant) {
+            let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * limit.per_minute / 60.0).min(limit.burst);
+            self.updated = now;
+        }
+
+        /// How long until a send is allowed; zero when one is allowed now.
+        pub(super) fn wait(&mut self, limit: Limit, now: Instant) -> Duration {
+            self.refill(limit, now);
+            if self.tokens >= 1.0 {
+                Duration::ZERO
+            } else {
+                Duration::from_secs_f64((1.0 - self.tokens) * 60.0 / limit.per_minute)
+            }
+        }
+
+        pub(super) fn take(&mut self) {
+            self.tokens -= 1.0;
+        }
+
+        /// Undo a [`Bucket::take`]; refilling still caps the bucket at its burst.
+        pub(super) fn give_back(&mut self) {
+            self.tokens += 1.0;
+        }
+    }
+
+    static SENDERS: OnceLock<DashMap<Uuid, Bucket>> = OnceLock::new();
+    static PAIRS: OnceLock<DashMap<(Uuid, Uuid), Bucket>> = OnceLock::new();
+    /// Content hashes a sender sent recently, oldest first.
+    static RECENT: OnceLock<DashMap<Uuid, VecDeque<(u64, Instant)>>> = OnceLock::new();
+
+    fn senders() -> &'static DashMap<Uuid, Bucket> {
+        SENDERS.get_or_init(DashMap::new)
+    }
+
+    fn pairs() -> &'static DashMap<(Uuid, Uuid), Bucket> {
+        PAIRS.get_or_init(DashMap::new)
+    }
+
+    fn recent() -> &'static DashMap<Uuid, VecDeque<(u64, Instant)>> {
+        RECENT.get_or_init(DashMap::new)
+    }
+
+    fn rate_limited(wait: Duration) -> ServerFnError {
+        SendError::RateLimited { retry_after: wait.as_secs_f64().ceil().max(1.0) as u64 }.into()
+    }
+
+    /// One send taken from a sender's allowance by [`reserve_send`]. Dropping
+    /// it without calling [`SendReservation::commit`] gives the send back, so
+    /// sends that fail after the check don't use up the sender's budget.
+    #[must_use = "dropping a reservation gives the send back"]
+    pub(crate) struct SendReservation {
+        sender_id: Uuid,
+        conversation_id: Uuid,
+        sent: Option<(u64, Instant)>,
+        committed: bool,
+    }
+
+    impl SendReservation {
+        /// Keep the send: the message went out.
+        pub(crate) fn commit(mut self) {
+            self.committed = true;
+        }
+    }
+
+    impl Drop for SendReservation {
+        fn drop(&mut self) {
+            if self.committed {
+                return;
+            }
+            if let Some(mut bucket) = senders().get_mut(&self.sender_id) {
+                bucket.give_back();
+            }
+            if let Some(mut bucket) = pairs().get_mut(&(self.sender_id, self.conversation_id)) {
+                bucket.give_back();
+            }
+            if let Some(sent) = self.sent {
+                if let Some(mut recent) = recent().get_mut(&self.sender_id) {
+                    if let Some(index) = recent.iter().rposition(|entry| *entry == sent) {
+                        recent.remove(index);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reserve one of `sender_id`'s sends to `conversation_id`, refusing it
+    /// when a limit is reached. Nothing is taken when the send is refused.
+    /// Call this once every other check on the send has passed and commit the
+    /// reservation once the message is stored.
+    pub(crate) async fn reserve_send(
+        pool: &sqlx::PgPool,
+        sender_id: Uuid,
+        conversation_id: Uuid,
+        content: &str,
+    ) -> Result<SendReservation, ServerFnError> {
+        // A direct conversation the other member has never written in
+        let (new_account, cold) = sqlx::query_as::<_, (bool, bool)>(
+            "SELECT u.created_at > NOW() - make_interval(hours => $3),
+                    c.kind = 'direct'
+                    AND EXISTS (SELECT 1 FROM conversation_members cm
+                                WHERE cm.conversation_id = c.id AND cm.user_id <> $1)
+                    AND NOT EXISTS (SELECT 1 FROM messages m
+                                    WHERE m.conversation_id = c.id AND m.sender_id <> $1)
+             FROM users u, conversations c
+             WHERE u.id = $1 AND c.id = $2",
+        )
+        .bind(sender_id)
+        .bind(conversation_id)
+        .bind(NEW_ACCOUNT_HOURS)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+        let sender_limit = if new_account { NEW_ACCOUNT_SENDER_LIMIT } else { SENDER_LIMIT };
+        let pair_limit = if new_account || cold { STRICT_PAIR_LIMIT } else { PAIR_LIMIT };
+        let now = Instant::now();
+
+        let digest = (!content.is_empty()).then(|| {
+            let mut hasher = DefaultHasher::new();
+            content.hash(&mut hasher);
+            hasher.finish()
+        });
+        let mut recent = recent().entry(sender_id).or_default();
+        let window = Duration::from_secs(DUPLICATE_WINDOW_SECS);
+        while recent.front().is_some_and(|(_, at)| now.duration_since(*at) >= window) {
+            recent.pop_front();
+        }
+        if let Some(digest) = digest {
+            let copies: Vec<Instant> = recent.iter().filter(|(d, _)| *d == digest).map(|(_, at)| *at).collect();
+            if copies.len() >= MAX_DUPLICATES {
+                // Allowed again once the earliest copy that counts ages out
+                let oldest = copies[copies.len() - MAX_DUPLICATES];
+                return Err(rate_limited(window - now.duration_since(oldest)));
+            }
+        }
+
+        let mut sender = senders().entry(sender_id).or_insert_with(|| Bucket::full(sender_limit, now));
+        let mut pair = pairs()
+            .entry((sender_id, conversation_id))
+            .or_insert_with(|| Bucket::full(pair_limit, now));
+        let wait = sender.wait(sender_limit, now).max(pair.wait(pair_limit, now));
+        if !wait.is_zero() {
+            return Err(rate_limited(wait));
+        }
+        sender.take();
+        pair.take();
+        let sent = digest.map(|digest| (digest, now));
+        recent.extend(sent);
+
+        Ok(SendReservation { sender_id, conversation_id, sent, committed: false })
+    }
+
+    /// Forget buckets that have refilled and duplicates past their window.
+    /// Returns how many entries were dropped.
+    pub(crate) fn prune() -> usize {
+        let now = Instant::now();
+        let idle = Duration::from_secs(60);
+        let window = Duration::from_secs(DUPLICATE_WINDOW_SECS);
+
+        // Every limit refills completely within a minute of the last send
+        let before = senders().len() + pairs().len() + recent().len();
+        senders().retain(|_, b| now.saturating_duration_since(b.updated) < idle);
+        pairs().retain(|_, b| now.saturating_duration_since(b.updated) < idle);
+        recent().retain(|_, r| r.back().is_some_and(|(_, at)| now.saturating_duration_since(*at) < window));
+        before - (senders().len() + pairs().len() + recent().len())
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::limiter::Bucket;
+    use super::*;
+
+    #[test]
+    fn bucket_allows_a_burst_then_waits_for_refill() {
+        let start = Instant::now();
+        let mut bucket = Bucket::full(PAIR_LIMIT, start);
+        for _ in 0..PAIR_LIMIT.burst as usize {
+            assert!(bucket.wait(PAIR_LIMIT, start).is_zero());
+            bucket.take();
+        }
+        // 30 per minute refills one send every two seconds
+        assert_eq!(bucket.wait(PAIR_LIMIT, start), Duration::from_secs(2));
+        assert!(bucket.wait(PAIR_LIMIT, start + Duration::from_secs(2)).is_zero());
+    }
+
+    #[test]
+    fn bucket_never_holds_more_than_its_burst() {
+        let start = Instant::now();
+        let mut bucket = Bucket::full(STRICT_PAIR_LIMIT, start);
+        let later = start + Duration::from_secs(3600);
+        for _ in 0..STRICT_PAIR_LIMIT.burst as usize {
+            assert!(bucket.wait(STRICT_PAIR_LIMIT, later).is_zero());
+            bucket.take();
+        }
+        assert!(!bucket.wait(STRICT_PAIR_LIMIT, later).is_zero());
+    }
+
+    #[test]
+    fn giving_back_a_send_allows_it_again() {
+        let start = Instant::now();
+        let mut bucket = Bucket::full(STRICT_PAIR_LIMIT, start);
+        for _ in 0..STRICT_PAIR_LIMIT.burst as usize {
+            bucket.take();
+        }
+        assert!(!bucket.wait(STRICT_PAIR_LIMIT, start).is_zero());
+        bucket.give_back();
+        assert!(bucket.wait(STRICT_PAIR_LIMIT, start).is_zero());
+    }
+
+    #[test]
+    fn rate_limited_round_trips_through_server_errors() {
+        let error: ServerFnError = SendError::RateLimited { retry_after: 7 }.into();
+        assert!(matches!(error, ServerFnError::ServerError { code: 429, .. }));
+        assert_eq!(SendError::from_server_error(&error), Some(SendError::RateLimited { retry_after: 7 }));
+        assert_eq!(SendError::from_server_error(&ServerFnError::new("nope")), None);
+    }
+}
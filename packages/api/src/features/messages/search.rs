@@ -0,0 +1,333 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::create::MessageResponse;
+
+/// Results per page when the request doesn't say.
+pub const DEFAULT_SEARCH_LIMIT: i64 = 25;
+pub const MAX_SEARCH_LIMIT: i64 = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SearchMessagesRequest {
+    pub token: String,
+    /// Free text plus optional filters: `from:<username or user id>`,
+    /// `in:<conversation id>`, `before:YYYY-MM-DD`, `after:YYYY-MM-DD` and
+    /// `has:attachment`. Text supports `"quoted phrases"`, `or` and `-excluded` words.
+    pub query: String,
+    /// `next_cursor` from the previous page.
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub limit: Option<i64>,
+}
+
+/// A run of snippet text, highlighted where it matched the query.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SnippetPart {
+    pub text: String,
+    pub highlighted: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SearchHit {
+    pub message: MessageResponse,
+    /// The best-matching fragments of the message.
+    pub snippet: Vec<SnippetPart>,
+    /// The group's or channel's name; `None` for direct messages.
+    pub conversation_name: Option<String>,
+    /// Set when the message is in a Reign channel.
+    pub reign_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SearchResults {
+    /// Newest first.
+    pub hits: Vec<SearchHit>,
+    /// Pass back as `cursor` for older results; `None` on the last page.
+    pub next_cursor: Option<String>,
+}
+
+/// Search hits, newest first. Only user messages match: pin notices,
+/// deletions, expired messages and ones hidden from the searcher are left out.
+#[cfg(feature = "server")]
+const HIT_SELECT: &str =
+    "SELECT m.id,
+            CASE WHEN $2 = '' THEN LEFT(translate(COALESCE(m.content_plain, m.content), $11, ''), 200)
+                 ELSE ts_headline('english', translate(COALESCE(m.content_plain, m.content), $11, ''), q, $10)
+            END AS headline,
+            c.name AS conversation_name, c.reign_id
+     FROM messages m
+     JOIN conversations c ON c.id = m.conversation_id
+     CROSS JOIN websearch_to_tsquery('english', $2) q
+     WHERE m.conversation_id = ANY($1)
+       AND m.kind = 'message'
+       AND m.deleted_at IS NULL
+       AND (m.expires_at IS NULL OR m.expires_at > NOW())
+       AND ($2 = '' OR m.content_tsv @@ q)
+       AND ($3::uuid IS NULL OR m.sender_id = $3)
+       AND ($4::timestamptz IS NULL OR m.created_at < $4)
+       AND ($5::timestamptz IS NULL OR m.created_at >= $5)
+       AND (NOT $6 OR EXISTS (SELECT 1 FROM attachments a WHERE a.message_id = m.id))
+       AND NOT EXISTS (SELECT 1 FROM message_hidden h WHERE h.message_id = m.id AND h.user_id = $7)
+       AND ($8::uuid IS NULL OR (m.created_at, m.id) < (SELECT p.created_at, p.id FROM messages p WHERE p.id = $8))
+     ORDER BY m.created_at DESC, m.id DESC
+     LIMIT $9";
+
+/// A search query split into its text and filters.
+#[cfg(feature = "server")]
+#[derive(Debug, Default, PartialEq)]
+struct ParsedQuery {
+    text: String,
+    from: Option<String>,
+    conversation_id: Option<uuid::Uuid>,
+    before: Option<chrono::DateTime<chrono::Utc>>,
+    after: Option<chrono::DateTime<chrono::Utc>>,
+    has_attachment: bool,
+}
+
+#[cfg(feature = "server")]
+fn parse_date(value: &str) -> Result<chrono::NaiveDate, ServerFnError> {
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| ServerFnError::new(format!("Invalid date {value:?}; use YYYY-MM-DD")))
+}
+
+/// Pull `key:value` filters out of the query, leaving the rest as text.
+/// Words inside a quoted phrase are always text. `before:` excludes its day
+/// and `after:` starts the day after, both in UTC.
+#[cfg(feature = "server")]
+fn parse_query(query: &str) -> Result<ParsedQuery, ServerFnError> {
+    let mut parsed = ParsedQuery::default();
+    let mut text = Vec::new();
+    let mut in_phrase = false;
+
+    for word in query.split_whitespace() {
+        let quoted = in_phrase || word.starts_with('"');
+        in_phrase ^= word.matches('"').count() % 2 == 1;
+        let filter = word.split_once(':').filter(|(_, v)| !v.is_empty() && !quoted);
+        let Some((key, value)) = filter else {
+            text.push(word);
+            continue;
+        };
+        match key.to_ascii_lowercase().as_str() {
+            "from" => parsed.from = Some(value.to_string()),
+            "in" => {
+                parsed.conversation_id = Some(
+                    value
+                        .parse()
+                        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid in: conversation: {e}")))?,
+                )
+            }
+            "before" => parsed.before = Some(parse_date(value)?.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc()),
+            "after" => {
+                let day = parse_date(value)?.succ_opt().unwrap_or(chrono::NaiveDate::MAX);
+                parsed.after = Some(day.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+            }
+            "has" if value.eq_ignore_ascii_case("attachment") => parsed.has_attachment = true,
+            "has" => return Err(ServerFnError::new(format!("Unknown filter has:{value}; try has:attachment"))),
+            // Anything else, e.g. a URL or a time, is just text
+            _ => text.push(word),
+        }
+    }
+
+    parsed.text = text.join(" ");
+    Ok(parsed)
+}
+
+/// Split a `ts_headline` result into plain and highlighted runs.
+#[cfg(feature = "server")]
+fn snippet_parts(headline: &str) -> Vec<SnippetPart> {
+    use crate::content::{HIGHLIGHT_START, HIGHLIGHT_STOP};
+
+    let mut parts = Vec::new();
+    let mut highlighted = false;
+    for (i, piece) in headline.split([HIGHLIGHT_START, HIGHLIGHT_STOP]).enumerate() {
+        if i > 0 {
+            highlighted = !highlighted;
+        }
+        if !piece.is_empty() {
+            parts.push(SnippetPart { text: piece.to_string(), highlighted });
+        }
+    }
+    parts
+}
+
+/// Search the caller's messages, newest first. Only conversations the caller
+/// can currently view are searched; deleted messages and ones the caller
+/// deleted for themselves never match.
+#[post("/api/messages/search")]
+pub async fn search_messages(req: SearchMessagesRequest) -> Result<SearchResults, ServerFnError> {
+    use super::{message_from_row, MessageRow, MESSAGE_SELECT};
+    use crate::auth::validate_token;
+    use crate::content::{HIGHLIGHT_START, HIGHLIGHT_STOP};
+    use crate::db;
+    use crate::features::{attachments, link_previews, reactions, roles};
+
+    #[derive(sqlx::FromRow)]
+    struct HitRow {
+        id: uuid::Uuid,
+        headline: String,
+        conversation_name: Option<String>,
+        reign_id: Option<uuid::Uuid>,
+    }
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    let query = parse_query(&req.query)?;
+    if query == ParsedQuery::default() {
+        return Err(ServerFnError::new("Enter something to search for"));
+    }
+    let cursor: Option<uuid::Uuid> = req
+        .cursor
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid cursor: {e}")))?;
+    let limit = req.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT);
+
+    let pool = db::pool().await;
+
+    let mut conversations = roles::viewable_conversations(pool, user_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    if let Some(only) = query.conversation_id {
+        conversations.retain(|id| *id == only);
+    }
+
+    let from: Option<uuid::Uuid> = match &query.from {
+        Some(from) => match from.parse() {
+            Ok(id) => Some(id),
+            Err(_) => {
+                let id = sqlx::query_scalar::<_, uuid::Uuid>("SELECT id FROM users WHERE LOWER(username) = LOWER($1)")
+                    .bind(from.trim_start_matches('@'))
+                    .fetch_optional(pool)
+                    .await
+                    .map_err(|e| ServerFnError::new(e.to_string()))?;
+                match id {
+                    Some(id) => Some(id),
+                    None => return Ok(SearchResults { hits: Vec::new(), next_cursor: None }),
+                }
+            }
+        },
+        None => None,
+    };
+
+    let headline_options = format!(
+        "StartSel={HIGHLIGHT_START}, StopSel={HIGHLIGHT_STOP}, MaxWords=30, MinWords=10, MaxFragments=2, FragmentDelimiter=\" … \""
+    );
+
+    // One extra row tells whether there is another page. Messages from
+    // before the highlight marks were refused have them removed first.
+    let mut rows = sqlx::query_as::<_, HitRow>(HIT_SELECT)
+        .bind(&conversations)
+        .bind(&query.text)
+        .bind(from)
+        .bind(query.before)
+        .bind(query.after)
+        .bind(query.has_attachment)
+        .bind(user_id)
+        .bind(cursor)
+        .bind(limit + 1)
+        .bind(&headline_options)
+        .bind(format!("{HIGHLIGHT_START}{HIGHLIGHT_STOP}"))
+        .fetch_all(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let next_cursor = if rows.len() as i64 > limit {
+        rows.truncate(limit as usize);
+        rows.last().map(|r| r.id.to_string())
+    } else {
+        None
+    };
+
+    let ids: Vec<uuid::Uuid> = rows.iter().map(|r| r.id).collect();
+    let message_rows = sqlx::query_as::<_, MessageRow>(&format!("{MESSAGE_SELECT} WHERE m.id = ANY($1)"))
+        .bind(&ids)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    let mut messages: Vec<MessageResponse> = message_rows.into_iter().map(message_from_row).collect();
+    reactions::attach_reactions(pool, user_id, &mut messages)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    attachments::attach_attachments(pool, user_id, &mut messages)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    link_previews::attach_link_previews(pool, &mut messages)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let mut by_id: std::collections::HashMap<String, MessageResponse> =
+        messages.into_iter().map(|m| (m.id.clone(), m)).collect();
+    let hits = rows
+        .into_iter()
+        .filter_map(|r| {
+            Some(SearchHit {
+                message: by_id.remove(&r.id.to_string())?,
+                snippet: snippet_parts(&r.headline),
+                conversation_name: r.conversation_name,
+                reign_id: r.reign_id.map(|id| id.to_string()),
+            })
+        })
+        .collect();
+
+    Ok(SearchResults { hits, next_cursor })
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_queries_have_no_text_or_filters() {
+        assert_eq!(parse_query("").unwrap(), ParsedQuery::default());
+        assert_eq!(parse_query("   ").unwrap(), ParsedQuery::default());
+    }
+
+    #[test]
+    fn pin_notices_never_match() {
+        let filters = &HIT_SELECT[HIT_SELECT.find("WHERE").unwrap()..];
+        assert!(filters.contains("AND m.kind = 'message'"));
+    }
+
+    #[test]
+    fn filters_are_pulled_out_of_the_text() {
+        let id = uuid::Uuid::new_v4();
+        let parsed = parse_query(&format!("launch FROM:sam in:{id} has:attachment plan")).unwrap();
+        assert_eq!(parsed.text, "launch plan");
+        assert_eq!(parsed.from.as_deref(), Some("sam"));
+        assert_eq!(parsed.conversation_id, Some(id));
+        assert!(parsed.has_attachment);
+
+        assert!(parse_query("in:not-a-uuid").is_err());
+        assert!(parse_query("has:link").is_err());
+        // Empty values and unknown keys are text
+        assert_eq!(parse_query("from: at 10:30 https://a.example").unwrap().text, "from: at 10:30 https://a.example");
+    }
+
+    #[test]
+    fn dates_bound_whole_utc_days() {
+        let parsed = parse_query("before:2024-03-10 after:2024-03-01").unwrap();
+        assert_eq!(parsed.before.unwrap().to_rfc3339(), "2024-03-10T00:00:00+00:00");
+        assert_eq!(parsed.after.unwrap().to_rfc3339(), "2024-03-02T00:00:00+00:00");
+        assert!(parse_query("before:yesterday").is_err());
+    }
+
+    #[test]
+    fn quoted_phrases_stay_text() {
+        let parsed = parse_query(r#""meet from:sam at noon" from:alex -lunch"#).unwrap();
+        assert_eq!(parsed.text, r#""meet from:sam at noon" -lunch"#);
+        assert_eq!(parsed.from.as_deref(), Some("alex"));
+        assert_eq!(parse_query(r#""from:sam""#).unwrap().from, None);
+    }
+
+    #[test]
+    fn snippets_split_on_highlight_marks() {
+        use crate::content::{HIGHLIGHT_START, HIGHLIGHT_STOP};
+
+        let parts = snippet_parts(&format!("the {HIGHLIGHT_START}launch{HIGHLIGHT_STOP} plan"));
+        let runs: Vec<(&str, bool)> = parts.iter().map(|p| (p.text.as_str(), p.highlighted)).collect();
+        assert_eq!(runs, [("the ", false), ("launch", true), (" plan", false)]);
+    }
+}
@@ -0,0 +1,59 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::create::MessageResponse;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ThreadRepliesRequest {
+    pub token: String,
+    /// The message whose replies to list.
+    pub message_id: String,
+}
+
+/// List the direct replies to a message, oldest first. The parent itself may
+/// since have been edited or deleted; its replies are still returned.
+#[post("/api/messages/thread")]
+pub async fn list_thread_replies(req: ThreadRepliesRequest) -> Result<Vec<MessageResponse>, ServerFnError> {
+    use super::{message_from_row, MessageRow, MESSAGE_SELECT};
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::{attachments, link_previews, reactions};
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let parent_id: uuid::Uuid = req
+        .message_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid message_id: {e}")))?;
+
+    let pool = db::pool().await;
+
+    super::message_conversation(pool, parent_id, user_id, Permissions::VIEW_CHANNEL).await?;
+
+    let rows = sqlx::query_as::<_, MessageRow>(&format!(
+        "{MESSAGE_SELECT}
+         WHERE m.reply_to_id = $1 AND m.kind = 'message'
+           AND (m.expires_at IS NULL OR m.expires_at > NOW())
+           AND NOT EXISTS (SELECT 1 FROM message_hidden h WHERE h.message_id = m.id AND h.user_id = $2)
+         ORDER BY m.created_at ASC"
+    ))
+    .bind(parent_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let mut replies: Vec<MessageResponse> = rows.into_iter().map(message_from_row).collect();
+    reactions::attach_reactions(pool, user_id, &mut replies)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    attachments::attach_attachments(pool, user_id, &mut replies)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    link_previews::attach_link_previews(pool, &mut replies)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(replies)
+}
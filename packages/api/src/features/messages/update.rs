@@ -12,8 +12,14 @@ pub struct UpdateMessageRequest {
 
 #[post("/api/messages/update")]
 pub async fn update_message(req: UpdateMessageRequest) -> Result<MessageResponse, ServerFnError> {
+    use super::{edit_window, message_from_row, MessageRow, MESSAGE_SELECT};
     use crate::auth::validate_token;
+    use crate::content;
     use crate::db;
+    use crate::events::WsEvent;
+    use crate::features::{attachments, conversations, link_previews, mentions, reactions};
+    use crate::markdown;
+    use crate::permissions::Permissions;
 
     let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
     let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
@@ -22,29 +28,102 @@ pub async fn update_message(req: UpdateMessageRequest) -> Result<MessageResponse
         .parse()
         .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid message_id: {e}")))?;
 
-    if req.content.is_empty() {
+    let content = content::normalize_content(&req.content).map_err(|e| ServerFnError::new(e.to_string()))?;
+    if content.is_empty() {
         return Err(ServerFnError::new("Message content cannot be empty"));
     }
 
-    let row = sqlx::query_as::<_, (uuid::Uuid, uuid::Uuid, uuid::Uuid, String, chrono::DateTime<chrono::Utc>)>(
-        "UPDATE messages SET content = $1, updated_at = NOW()
-         WHERE id = $2 AND sender_id = $3
-         RETURNING id, sender_id, recipient_id, content, created_at",
-    )
-    .bind(&req.content)
-    .bind(message_id)
-    .bind(user_id)
-    .fetch_optional(db::pool().await)
-    .await
-    .map_err(|e| ServerFnError::new(e.to_string()))?;
-
-    let r = row.ok_or_else(|| ServerFnError::new("Message not found or you are not the sender"))?;
-
-    Ok(MessageResponse {
-        id: r.0.to_string(),
-        sender_id: r.1.to_string(),
-        recipient_id: r.2.to_string(),
-        content: r.3,
-        created_at: r.4.to_rfc3339(),
-    })
+    let pool = db::pool().await;
+    super::message_conversation(pool, message_id, user_id, Permissions::VIEW_CHANNEL).await?;
+
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    // Lock the row so concurrent edits record history in order
+    let (old_content, created_at, conversation_id, forwarded) =
+        sqlx::query_as::<_, (String, chrono::DateTime<chrono::Utc>, uuid::Uuid, bool)>(
+            "SELECT m.content, m.created_at, m.conversation_id,
+                    m.forwarded_from_id IS NOT NULL OR m.forwarded_from_sender_id IS NOT NULL
+             FROM messages m
+             WHERE m.id = $1 AND m.sender_id = $2 AND m.deleted_at IS NULL AND m.kind = 'message'
+             FOR UPDATE OF m",
+        )
+        .bind(message_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .ok_or_else(|| ServerFnError::new("Message not found or you are not the sender"))?;
+
+    // A forward quotes someone else's words, so it stays as they wrote it
+    if forwarded {
+        return Err(ServerFnError::new("Forwarded messages can't be edited"));
+    }
+
+    if let Some(window) = edit_window() {
+        if chrono::Utc::now() > created_at + window {
+            return Err(ServerFnError::new("This message can no longer be edited"));
+        }
+    }
+
+    let mut mentioned = None;
+    if old_content != content {
+        sqlx::query("INSERT INTO message_edits (message_id, content) VALUES ($1, $2)")
+            .bind(message_id)
+            .bind(&old_content)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+        sqlx::query(
+            "UPDATE messages SET content = $1, content_plain = $2, edited_at = NOW(), updated_at = NOW() WHERE id = $3",
+        )
+        .bind(&content)
+        .bind(markdown::to_plain_text(&content))
+        .bind(message_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+        mentioned = Some(
+            mentions::record_mentions(pool, &mut tx, conversation_id, message_id, user_id, &content)
+                .await
+                .map_err(|e| ServerFnError::new(e.to_string()))?,
+        );
+
+        // Links still in the message keep their cards until the unfurl refreshes them
+        sqlx::query("DELETE FROM message_link_previews WHERE message_id = $1 AND NOT (url = ANY($2))")
+            .bind(message_id)
+            .bind(link_previews::preview_urls(&content))
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ServerFnError::new(e.to_string()))?;
+    }
+
+    let row = sqlx::query_as::<_, MessageRow>(&format!("{MESSAGE_SELECT} WHERE m.id = $1"))
+        .bind(message_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let mut response = message_from_row(row);
+    reactions::attach_reactions(pool, user_id, std::slice::from_mut(&mut response))
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    attachments::attach_attachments(pool, user_id, std::slice::from_mut(&mut response))
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    link_previews::attach_link_previews(pool, std::slice::from_mut(&mut response))
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if let Some(mentioned) = mentioned {
+        conversations::broadcast(pool, conversation_id, &WsEvent::MessageUpdated(response.clone())).await;
+        conversations::notify_all_summaries(pool, conversation_id);
+        mentions::notify_mentions(&response, &mentioned.notify);
+        link_previews::spawn_unfurl(message_id);
+    }
+
+    Ok(response)
 }
@@ -1,2 +1,15 @@
+pub mod attachments;
+pub mod blocks;
+pub mod channels;
+pub mod conversations;
+pub mod drafts;
+pub mod invites;
+pub mod link_previews;
+pub mod mentions;
 pub mod messages;
+pub mod pins;
+pub mod reactions;
+pub mod reigns;
+pub mod roles;
+pub mod scheduled;
 pub mod users;
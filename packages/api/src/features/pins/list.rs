@@ -0,0 +1,73 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::PinnedMessage;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ListPinsRequest {
+    pub token: String,
+    pub conversation_id: String,
+}
+
+/// A conversation's pinned messages, most recently pinned first.
+#[post("/api/pins/list")]
+pub async fn list_pins(req: ListPinsRequest) -> Result<Vec<PinnedMessage>, ServerFnError> {
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::messages::create::MessageResponse;
+    use crate::features::messages::{message_from_row, MessageRow, MESSAGE_SELECT};
+    use crate::features::{attachments, link_previews, reactions, roles};
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let conversation_id: uuid::Uuid = req
+        .conversation_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid conversation_id: {e}")))?;
+
+    let pool = db::pool().await;
+    roles::require_permission(pool, conversation_id, user_id, Permissions::VIEW_CHANNEL).await?;
+
+    #[allow(clippy::type_complexity)]
+    let pins = sqlx::query_as::<_, (uuid::Uuid, Option<uuid::Uuid>, chrono::DateTime<chrono::Utc>)>(
+        "SELECT p.message_id, p.pinned_by, p.pinned_at FROM pinned_messages p
+         JOIN messages m ON m.id = p.message_id
+         WHERE p.conversation_id = $1 AND (m.expires_at IS NULL OR m.expires_at > NOW())
+         ORDER BY p.pinned_at DESC",
+    )
+    .bind(conversation_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let ids: Vec<uuid::Uuid> = pins.iter().map(|p| p.0).collect();
+    let rows = sqlx::query_as::<_, MessageRow>(&format!("{MESSAGE_SELECT} WHERE m.id = ANY($1)"))
+        .bind(&ids)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let mut messages: Vec<MessageResponse> = rows.into_iter().map(message_from_row).collect();
+    reactions::attach_reactions(pool, user_id, &mut messages)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    attachments::attach_attachments(pool, user_id, &mut messages)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    link_previews::attach_link_previews(pool, &mut messages)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(pins
+        .into_iter()
+        .filter_map(|(message_id, pinned_by, pinned_at)| {
+            let index = messages.iter().position(|m| m.id == message_id.to_string())?;
+            Some(PinnedMessage {
+                message: messages.swap_remove(index),
+                pinned_by: pinned_by.map(|id| id.to_string()),
+                pinned_at: pinned_at.to_rfc3339(),
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,51 @@
+//! Messages pinned to the top of a conversation.
+//!
+//! Either participant of a direct conversation may pin; groups need an owner
+//! or admin and Reign channels need Manage Messages. Pinning posts a
+//! `pin_notice` message pointing at the pinned one, and every change is
+//! broadcast as a `pins_updated` event so pinned bars stay current.
+
+pub mod list;
+pub mod pin;
+
+use serde::{Deserialize, Serialize};
+
+use crate::features::messages::create::MessageResponse;
+
+/// Most messages pinned in one conversation at a time.
+pub const MAX_PINS_PER_CONVERSATION: i64 = 50;
+
+/// A pinned message with who pinned it and when.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PinnedMessage {
+    pub message: MessageResponse,
+    /// `None` once the pinning user's account is gone.
+    pub pinned_by: Option<String>,
+    pub pinned_at: String,
+}
+
+/// Look up a message's conversation and check that `user_id` may pin there.
+#[cfg(feature = "server")]
+pub(crate) async fn require_pin_permission(
+    pool: &sqlx::PgPool,
+    message_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+) -> Result<uuid::Uuid, dioxus::prelude::ServerFnError> {
+    use crate::features::messages::message_conversation;
+    use crate::permissions::Permissions;
+    use dioxus::prelude::ServerFnError;
+
+    let (conversation_id, permissions) =
+        message_conversation(pool, message_id, user_id, Permissions::VIEW_CHANNEL).await?;
+
+    let is_direct = sqlx::query_scalar::<_, bool>("SELECT kind = 'direct' FROM conversations WHERE id = $1")
+        .bind(conversation_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if !is_direct && !permissions.contains(Permissions::MANAGE_MESSAGES) {
+        return Err(ServerFnError::new("You don't have permission to do that here"));
+    }
+    Ok(conversation_id)
+}
@@ -0,0 +1,152 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PinRequest {
+    pub token: String,
+    pub message_id: String,
+}
+
+/// Pin a message and post a notice about it. Pinning a pinned message is a
+/// no-op. Members receive `pins_updated` and the notice's `message_created`.
+#[post("/api/pins/pin")]
+pub async fn pin_message(req: PinRequest) -> Result<(), ServerFnError> {
+    use super::{require_pin_permission, MAX_PINS_PER_CONVERSATION};
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::events::{PinUpdate, WsEvent};
+    use crate::features::conversations;
+    use crate::features::messages::{message_from_row, MessageRow, MESSAGE_SELECT};
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let message_id: uuid::Uuid = req
+        .message_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid message_id: {e}")))?;
+
+    let pool = db::pool().await;
+    let conversation_id = require_pin_permission(pool, message_id, user_id).await?;
+
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    // Lock the conversation so concurrent pins can't overshoot the limit
+    sqlx::query("SELECT id FROM conversations WHERE id = $1 FOR UPDATE")
+        .bind(conversation_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let (pinnable, already_pinned, pins) = sqlx::query_as::<_, (bool, bool, i64)>(
+        "SELECT m.deleted_at IS NULL AND m.kind = 'message',
+                EXISTS (SELECT 1 FROM pinned_messages p WHERE p.message_id = m.id),
+                (SELECT COUNT(*) FROM pinned_messages p WHERE p.conversation_id = m.conversation_id)
+         FROM messages m WHERE m.id = $1",
+    )
+    .bind(message_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if already_pinned {
+        return Ok(());
+    }
+    if !pinnable {
+        return Err(ServerFnError::new("This message can't be pinned"));
+    }
+    if pins >= MAX_PINS_PER_CONVERSATION {
+        return Err(ServerFnError::new(format!(
+            "Conversations can have at most {MAX_PINS_PER_CONVERSATION} pinned messages"
+        )));
+    }
+
+    sqlx::query("INSERT INTO pinned_messages (message_id, conversation_id, pinned_by) VALUES ($1, $2, $3)")
+        .bind(message_id)
+        .bind(conversation_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    // The notice quotes the pinned message the same way a reply does
+    let (notice_id, created_at) = sqlx::query_as::<_, (uuid::Uuid, chrono::DateTime<chrono::Utc>)>(
+        "INSERT INTO messages (conversation_id, sender_id, recipient_id, content, reply_to_id, kind, expires_at)
+         SELECT c.id, $2,
+                CASE WHEN c.kind = 'direct' THEN COALESCE(
+                    (SELECT user_id FROM conversation_members WHERE conversation_id = c.id AND user_id <> $2 LIMIT 1),
+                    $2
+                ) END,
+                '', m.id, 'pin_notice', NOW() + make_interval(secs => c.message_ttl_secs)
+         FROM messages m JOIN conversations c ON c.id = m.conversation_id WHERE m.id = $1
+         RETURNING id, created_at",
+    )
+    .bind(message_id)
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    conversations::record_message(&mut tx, conversation_id, notice_id, created_at)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let notice = sqlx::query_as::<_, MessageRow>(&format!("{MESSAGE_SELECT} WHERE m.id = $1"))
+        .bind(notice_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let update = WsEvent::PinsUpdated(PinUpdate {
+        conversation_id: conversation_id.to_string(),
+        message_id: message_id.to_string(),
+        pinned: true,
+        actor_id: user_id.to_string(),
+    });
+    conversations::broadcast(pool, conversation_id, &update).await;
+    conversations::broadcast(pool, conversation_id, &WsEvent::MessageCreated(message_from_row(notice))).await;
+    conversations::notify_all_summaries(pool, conversation_id);
+
+    Ok(())
+}
+
+/// Unpin a message. Unpinning one that isn't pinned is a no-op. Members
+/// receive `pins_updated`.
+#[post("/api/pins/unpin")]
+pub async fn unpin_message(req: PinRequest) -> Result<(), ServerFnError> {
+    use super::require_pin_permission;
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::events::{PinUpdate, WsEvent};
+    use crate::features::conversations;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let message_id: uuid::Uuid = req
+        .message_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid message_id: {e}")))?;
+
+    let pool = db::pool().await;
+    let conversation_id = require_pin_permission(pool, message_id, user_id).await?;
+
+    let removed = sqlx::query("DELETE FROM pinned_messages WHERE message_id = $1")
+        .bind(message_id)
+        .execute(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .rows_affected();
+
+    if removed > 0 {
+        let update = WsEvent::PinsUpdated(PinUpdate {
+            conversation_id: conversation_id.to_string(),
+            message_id: message_id.to_string(),
+            pinned: false,
+            actor_id: user_id.to_string(),
+        });
+        conversations::broadcast(pool, conversation_id, &update).await;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::ReactionCount;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReactionRequest {
+    pub token: String,
+    pub message_id: String,
+    /// A Unicode emoji or a `:custom_shortcode:`.
+    pub emoji: String,
+}
+
+/// React to a message. Adding a reaction the caller already left is a no-op.
+#[post("/api/reactions/add")]
+pub async fn add_reaction(req: ReactionRequest) -> Result<ReactionCount, ServerFnError> {
+    use crate::db;
+    use crate::permissions::Permissions;
+
+    let pool = db::pool().await;
+    let (user_id, message_id, conversation_id) = super::authorize(pool, &req, Permissions::SEND_MESSAGES).await?;
+
+    // The count and insert share one statement so concurrent adds can't both slip under the cap
+    let inserted = sqlx::query(
+        "INSERT INTO message_reactions (message_id, user_id, emoji)
+         SELECT $1, $2, $3
+         WHERE (SELECT COUNT(*) FROM message_reactions WHERE message_id = $1 AND user_id = $2) < $4
+         ON CONFLICT (message_id, user_id, emoji) DO NOTHING",
+    )
+    .bind(message_id)
+    .bind(user_id)
+    .bind(&req.emoji)
+    .bind(super::MAX_REACTIONS_PER_USER)
+    .execute(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?
+    .rows_affected()
+        > 0;
+
+    if !inserted {
+        let already = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM message_reactions WHERE message_id = $1 AND user_id = $2 AND emoji = $3)",
+        )
+        .bind(message_id)
+        .bind(user_id)
+        .bind(&req.emoji)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+        if !already {
+            return Err(ServerFnError::new(format!(
+                "You can leave at most {} reactions on a message",
+                super::MAX_REACTIONS_PER_USER
+            )));
+        }
+    }
+
+    super::broadcast_change(pool, user_id, message_id, conversation_id, &req.emoji, true)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
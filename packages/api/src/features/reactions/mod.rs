@@ -0,0 +1,201 @@
+pub mod add;
+pub mod remove;
+
+use serde::{Deserialize, Serialize};
+
+/// Longest accepted reaction, in bytes. Long enough for ZWJ family/flag sequences.
+pub const MAX_EMOJI_BYTES: usize = 64;
+/// Longest accepted custom shortcode name, excluding the surrounding colons.
+pub const MAX_SHORTCODE_CHARS: usize = 32;
+/// How many different reactions one user may leave on a single message.
+pub const MAX_REACTIONS_PER_USER: i64 = 20;
+
+/// Aggregated reactions with one emoji on a message, as seen by the caller.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReactionCount {
+    pub emoji: String,
+    pub count: i64,
+    /// Whether the caller is one of the reactors.
+    pub reacted: bool,
+}
+
+/// Check that `emoji` is either a single Unicode emoji sequence or a custom
+/// `:shortcode:` made of lowercase letters, digits, `_`, `+` and `-`.
+///
+/// Shared with the UI so the reaction picker can reject input before sending.
+pub fn validate_emoji(emoji: &str) -> Result<(), &'static str> {
+    if emoji.is_empty() {
+        return Err("Reaction cannot be empty");
+    }
+    if emoji.len() > MAX_EMOJI_BYTES {
+        return Err("Reaction is too long");
+    }
+
+    if let Some(name) = emoji.strip_prefix(':').and_then(|e| e.strip_suffix(':')) {
+        let valid = !name.is_empty()
+            && name.chars().count() <= MAX_SHORTCODE_CHARS
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '+' | '-'));
+        return if valid { Ok(()) } else { Err("Invalid custom emoji shortcode") };
+    }
+
+    let all_emoji = emoji.chars().all(|c| is_pictographic(c) || is_emoji_component(c));
+    let has_base = emoji.chars().any(|c| is_pictographic(c) || c == '\u{20E3}');
+    if all_emoji && has_base {
+        Ok(())
+    } else {
+        Err("Reaction must be an emoji or a :shortcode:")
+    }
+}
+
+/// Code points that render as an emoji on their own (possibly with VS16).
+fn is_pictographic(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF
+            | 0x2600..=0x27BF
+            | 0x2300..=0x23FF
+            | 0x2B00..=0x2BFF
+            | 0x2190..=0x21FF
+            | 0x25A0..=0x25FF
+            | 0x2934..=0x2935
+            | 0x3030
+            | 0x303D
+            | 0x3297
+            | 0x3299
+            | 0x00A9
+            | 0x00AE
+            | 0x203C
+            | 0x2049
+            | 0x2122
+            | 0x2139
+            | 0x24C2
+    )
+}
+
+/// Code points that only appear inside an emoji sequence: joiners, variation
+/// selectors, keycap parts and tag characters (subdivision flags).
+fn is_emoji_component(c: char) -> bool {
+    matches!(c, '\u{200D}' | '\u{FE0F}' | '\u{20E3}' | '0'..='9' | '#' | '*')
+        || matches!(c as u32, 0xE0020..=0xE007F)
+}
+
+/// Aggregate reactions for a batch of messages from `viewer_id`'s point of view
+/// and attach them to the matching responses.
+#[cfg(feature = "server")]
+pub(crate) async fn attach_reactions(
+    pool: &sqlx::PgPool,
+    viewer_id: uuid::Uuid,
+    messages: &mut [crate::features::messages::create::MessageResponse],
+) -> Result<(), sqlx::Error> {
+    // Tombstones carry no reactions, even ones left from before deleting cleared them
+    let ids: Vec<uuid::Uuid> =
+        messages.iter().filter(|m| m.deleted_at.is_none()).filter_map(|m| m.id.parse().ok()).collect();
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    let rows = sqlx::query_as::<_, (uuid::Uuid, String, i64, bool)>(
+        "SELECT message_id, emoji, COUNT(*), BOOL_OR(user_id = $2)
+         FROM message_reactions
+         WHERE message_id = ANY($1)
+         GROUP BY message_id, emoji
+         ORDER BY MIN(created_at) ASC",
+    )
+    .bind(&ids)
+    .bind(viewer_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut by_message: std::collections::HashMap<String, Vec<ReactionCount>> = std::collections::HashMap::new();
+    for (message_id, emoji, count, reacted) in rows {
+        by_message
+            .entry(message_id.to_string())
+            .or_default()
+            .push(ReactionCount { emoji, count, reacted });
+    }
+
+    for m in messages.iter_mut() {
+        m.reactions = by_message.remove(&m.id).unwrap_or_default();
+    }
+
+    Ok(())
+}
+
+/// Shared by add/remove: validate input, check the caller holds `needed` in the
+/// message's conversation, and return `(user_id, message_id, conversation_id)`.
+#[cfg(feature = "server")]
+pub(crate) async fn authorize(
+    pool: &sqlx::PgPool,
+    req: &add::ReactionRequest,
+    needed: crate::permissions::Permissions,
+) -> Result<(uuid::Uuid, uuid::Uuid, uuid::Uuid), dioxus::prelude::ServerFnError> {
+    use crate::auth::validate_token;
+    use dioxus::prelude::ServerFnError;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let message_id: uuid::Uuid = req
+        .message_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid message_id: {e}")))?;
+
+    validate_emoji(&req.emoji).map_err(ServerFnError::new)?;
+
+    let (conversation_id, _) =
+        crate::features::messages::message_conversation(pool, message_id, user_id, needed).await?;
+
+    let deleted = sqlx::query_scalar::<_, bool>("SELECT deleted_at IS NOT NULL FROM messages WHERE id = $1")
+        .bind(message_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    if deleted {
+        return Err(ServerFnError::new("Message not found"));
+    }
+
+    Ok((user_id, message_id, conversation_id))
+}
+
+/// Push the new aggregate for `emoji` to every member, each with their own
+/// `reacted` flag, and return the actor's view of it.
+#[cfg(feature = "server")]
+pub(crate) async fn broadcast_change(
+    pool: &sqlx::PgPool,
+    actor_id: uuid::Uuid,
+    message_id: uuid::Uuid,
+    conversation_id: uuid::Uuid,
+    emoji: &str,
+    added: bool,
+) -> Result<ReactionCount, sqlx::Error> {
+    use crate::events::{ReactionUpdate, WsEvent};
+
+    let reactors = sqlx::query_scalar::<_, uuid::Uuid>(
+        "SELECT user_id FROM message_reactions WHERE message_id = $1 AND emoji = $2",
+    )
+    .bind(message_id)
+    .bind(emoji)
+    .fetch_all(pool)
+    .await?;
+    let count = reactors.len() as i64;
+
+    let members = crate::features::conversations::member_ids(pool, conversation_id).await?;
+    for member in members.iter() {
+        let update = ReactionUpdate {
+            message_id: message_id.to_string(),
+            user_id: actor_id.to_string(),
+            emoji: emoji.to_string(),
+            added,
+            count,
+            reacted: reactors.contains(member),
+        };
+        crate::ws::send_event(*member, &WsEvent::ReactionUpdated(update));
+    }
+
+    Ok(ReactionCount {
+        emoji: emoji.to_string(),
+        count,
+        reacted: reactors.contains(&actor_id),
+    })
+}
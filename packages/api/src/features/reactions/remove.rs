@@ -0,0 +1,26 @@
+use dioxus::prelude::*;
+
+use super::add::ReactionRequest;
+use super::ReactionCount;
+
+/// Remove one of the caller's reactions. Removing one they never left is a no-op.
+#[post("/api/reactions/remove")]
+pub async fn remove_reaction(req: ReactionRequest) -> Result<ReactionCount, ServerFnError> {
+    use crate::db;
+    use crate::permissions::Permissions;
+
+    let pool = db::pool().await;
+    let (user_id, message_id, conversation_id) = super::authorize(pool, &req, Permissions::VIEW_CHANNEL).await?;
+
+    sqlx::query("DELETE FROM message_reactions WHERE message_id = $1 AND user_id = $2 AND emoji = $3")
+        .bind(message_id)
+        .bind(user_id)
+        .bind(&req.emoji)
+        .execute(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    super::broadcast_change(pool, user_id, message_id, conversation_id, &req.emoji, false)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))
+}
@@ -0,0 +1,93 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::kick::ReignMemberRequest;
+
+/// Longest accepted ban reason, in characters.
+pub const MAX_BAN_REASON_CHARS: usize = 512;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BanRequest {
+    pub token: String,
+    pub reign_id: String,
+    pub user_id: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Remove a user from a Reign and keep them from rejoining. Users who aren't
+/// members can be banned too; members must rank below the caller.
+#[post("/api/reigns/ban")]
+pub async fn ban_member(req: BanRequest) -> Result<(), ServerFnError> {
+    use super::{parse_reign_id, remove_member};
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let reign_id = parse_reign_id(&req.reign_id)?;
+    let target_id: uuid::Uuid = req
+        .user_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid user_id: {e}")))?;
+
+    let reason = req.reason.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    if reason.is_some_and(|r| r.chars().count() > MAX_BAN_REASON_CHARS) {
+        return Err(ServerFnError::new(format!("Ban reason is limited to {MAX_BAN_REASON_CHARS} characters")));
+    }
+
+    let pool = db::pool().await;
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+    let removed = remove_member(pool, &mut tx, reign_id, user_id, target_id, Permissions::BAN_MEMBERS).await?;
+
+    sqlx::query(
+        "INSERT INTO reign_bans (reign_id, user_id, banned_by, reason) VALUES ($1, $2, $3, $4)
+         ON CONFLICT (reign_id, user_id) DO UPDATE SET banned_by = EXCLUDED.banned_by, reason = EXCLUDED.reason",
+    )
+    .bind(reign_id)
+    .bind(target_id)
+    .bind(user_id)
+    .bind(reason)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if removed {
+        super::member_removed(pool, reign_id, target_id).await;
+    }
+
+    Ok(())
+}
+
+/// Let a banned user join again.
+#[post("/api/reigns/unban")]
+pub async fn unban_member(req: ReignMemberRequest) -> Result<(), ServerFnError> {
+    use super::parse_reign_id;
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::permissions::Permissions;
+    use crate::features::roles::require_reign_permission;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let reign_id = parse_reign_id(&req.reign_id)?;
+    let target_id: uuid::Uuid = req
+        .user_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid user_id: {e}")))?;
+
+    let pool = db::pool().await;
+    require_reign_permission(pool, reign_id, user_id, Permissions::BAN_MEMBERS).await?;
+
+    sqlx::query("DELETE FROM reign_bans WHERE reign_id = $1 AND user_id = $2")
+        .bind(reign_id)
+        .bind(target_id)
+        .execute(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::ReignSummary;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CreateReignRequest {
+    pub token: String,
+    pub name: String,
+}
+
+/// Create a Reign owned by the caller, with a "Text Channels" category
+/// holding a `general` channel.
+#[post("/api/reigns/create")]
+pub async fn create_reign(req: CreateReignRequest) -> Result<ReignSummary, ServerFnError> {
+    use super::{reign_from_row, validate_name, ReignRow, REIGN_SELECT};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let name = validate_name(&req.name, "Reign")?;
+
+    let pool = db::pool().await;
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let reign_id = sqlx::query_scalar::<_, uuid::Uuid>("INSERT INTO reigns (name, owner_id) VALUES ($1, $2) RETURNING id")
+        .bind(&name)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    sqlx::query("INSERT INTO reign_members (reign_id, user_id, role) VALUES ($1, $2, 'owner')")
+        .bind(reign_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    sqlx::query("INSERT INTO reign_roles (reign_id, name, permissions, is_default) VALUES ($1, '@everyone', $2, TRUE)")
+        .bind(reign_id)
+        .bind(crate::permissions::Permissions::DEFAULT.bits())
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let category_id = sqlx::query_scalar::<_, uuid::Uuid>(
+        "INSERT INTO channel_categories (reign_id, name) VALUES ($1, 'Text Channels') RETURNING id",
+    )
+    .bind(reign_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let channel_id = sqlx::query_scalar::<_, uuid::Uuid>(
+        "INSERT INTO conversations (kind, name, created_by, reign_id, category_id)
+         VALUES ('channel', 'general', $2, $1, $3) RETURNING id",
+    )
+    .bind(reign_id)
+    .bind(user_id)
+    .bind(category_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    sqlx::query("INSERT INTO conversation_members (conversation_id, user_id, role) VALUES ($1, $2, 'owner')")
+        .bind(channel_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let row = sqlx::query_as::<_, ReignRow>(&format!("{REIGN_SELECT} WHERE rm.reign_id = $1 AND rm.user_id = $2"))
+        .bind(reign_id)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(reign_from_row(row))
+}
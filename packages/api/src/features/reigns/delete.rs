@@ -0,0 +1,42 @@
+use dioxus::prelude::*;
+
+use super::join::ReignRequest;
+
+/// Delete a Reign with all of its channels and their messages. Owner only.
+#[post("/api/reigns/delete")]
+pub async fn delete_reign(req: ReignRequest) -> Result<(), ServerFnError> {
+    use super::{invalidate_channel_members, parse_reign_id, require_reign_member};
+    use crate::events::{ReignUpdate, WsEvent};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let reign_id = parse_reign_id(&req.reign_id)?;
+
+    let pool = db::pool().await;
+    if require_reign_member(pool, reign_id, user_id).await? != "owner" {
+        return Err(ServerFnError::new("Only the owner can delete a Reign"));
+    }
+
+    let members = sqlx::query_scalar::<_, uuid::Uuid>("SELECT user_id FROM reign_members WHERE reign_id = $1")
+        .bind(reign_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    invalidate_channel_members(pool, reign_id).await;
+
+    sqlx::query("DELETE FROM reigns WHERE id = $1")
+        .bind(reign_id)
+        .execute(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    // Clients find the Reign gone when they refetch it
+    let event = WsEvent::ReignUpdated(ReignUpdate { reign_id: reign_id.to_string() });
+    for member in members {
+        crate::ws::send_event(member, &event);
+    }
+
+    Ok(())
+}
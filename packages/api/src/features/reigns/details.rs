@@ -0,0 +1,88 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::ReignSummary;
+use crate::features::channels::{Channel, ChannelCategory};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReignDetailsRequest {
+    pub token: String,
+    pub reign_id: String,
+}
+
+/// Everything needed to draw a Reign's sidebar.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReignDetails {
+    pub reign: ReignSummary,
+    /// Ordered by `position`.
+    pub categories: Vec<ChannelCategory>,
+    /// Ordered by `position`; channels with no `category_id` are listed above all categories.
+    /// Channels the caller can't view are left out.
+    pub channels: Vec<Channel>,
+}
+
+#[post("/api/reigns/details")]
+pub async fn get_reign(req: ReignDetailsRequest) -> Result<ReignDetails, ServerFnError> {
+    use super::{parse_reign_id, reign_from_row, ReignRow, REIGN_SELECT};
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::channels::{channel_from_row, ChannelRow, CHANNEL_SELECT};
+    use crate::features::roles::reign_channel_permissions;
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let reign_id = parse_reign_id(&req.reign_id)?;
+
+    let pool = db::pool().await;
+
+    let reign = sqlx::query_as::<_, ReignRow>(&format!("{REIGN_SELECT} WHERE rm.reign_id = $1 AND rm.user_id = $2"))
+        .bind(reign_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .ok_or_else(|| ServerFnError::new("Reign not found"))?;
+
+    let categories = sqlx::query_as::<_, (uuid::Uuid, String, i32)>(
+        "SELECT id, name, position FROM channel_categories WHERE reign_id = $1 ORDER BY position ASC, created_at ASC",
+    )
+    .bind(reign_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let channels = sqlx::query_as::<_, ChannelRow>(&format!(
+        "{CHANNEL_SELECT} WHERE c.reign_id = $2 ORDER BY c.position ASC, c.created_at ASC"
+    ))
+    .bind(user_id)
+    .bind(reign_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let mut permissions = reign_channel_permissions(pool, reign_id, user_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(ReignDetails {
+        reign: reign_from_row(reign),
+        categories: categories
+            .into_iter()
+            .map(|(id, name, position)| ChannelCategory {
+                id: id.to_string(),
+                name,
+                position,
+            })
+            .collect(),
+        channels: channels
+            .into_iter()
+            .filter_map(|row| {
+                let permissions = permissions.remove(&row.id).unwrap_or_default();
+                permissions
+                    .contains(Permissions::VIEW_CHANNEL)
+                    .then(|| channel_from_row(row, permissions))
+            })
+            .collect(),
+    })
+}
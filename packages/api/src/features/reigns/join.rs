@@ -0,0 +1,36 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::ReignSummary;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReignRequest {
+    pub token: String,
+    pub reign_id: String,
+}
+
+/// Join a Reign and all of its channels. Existing channel history starts out read.
+/// Joining a Reign the caller is already in is a no-op; banned users are refused.
+#[post("/api/reigns/join")]
+pub async fn join_reign(req: ReignRequest) -> Result<ReignSummary, ServerFnError> {
+    use super::{add_to_reign, parse_reign_id, reign_from_row, ReignRow, REIGN_SELECT};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let reign_id = parse_reign_id(&req.reign_id)?;
+
+    let pool = db::pool().await;
+    add_to_reign(pool, reign_id, user_id).await?;
+
+    let row = sqlx::query_as::<_, ReignRow>(&format!("{REIGN_SELECT} WHERE rm.reign_id = $1 AND rm.user_id = $2"))
+        .bind(reign_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .ok_or_else(|| ServerFnError::new("Reign not found"))?;
+
+    Ok(reign_from_row(row))
+}
@@ -0,0 +1,38 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReignMemberRequest {
+    pub token: String,
+    pub reign_id: String,
+    pub user_id: String,
+}
+
+/// Remove a member ranked below the caller from a Reign. They can rejoin.
+#[post("/api/reigns/kick")]
+pub async fn kick_member(req: ReignMemberRequest) -> Result<(), ServerFnError> {
+    use super::{parse_reign_id, remove_member};
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let reign_id = parse_reign_id(&req.reign_id)?;
+    let target_id: uuid::Uuid = req
+        .user_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid user_id: {e}")))?;
+
+    let pool = db::pool().await;
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+    let removed = remove_member(pool, &mut tx, reign_id, user_id, target_id, Permissions::KICK_MEMBERS).await?;
+    if !removed {
+        return Err(ServerFnError::new("That user isn't in this Reign"));
+    }
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    super::member_removed(pool, reign_id, target_id).await;
+
+    Ok(())
+}
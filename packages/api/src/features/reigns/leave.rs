@@ -0,0 +1,32 @@
+use dioxus::prelude::*;
+
+use super::join::ReignRequest;
+
+/// Leave a Reign and all of its channels. The owner has to delete the Reign instead.
+#[post("/api/reigns/leave")]
+pub async fn leave_reign(req: ReignRequest) -> Result<(), ServerFnError> {
+    use super::{invalidate_channel_members, parse_reign_id, require_reign_member};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let reign_id = parse_reign_id(&req.reign_id)?;
+
+    let pool = db::pool().await;
+    if require_reign_member(pool, reign_id, user_id).await? == "owner" {
+        return Err(ServerFnError::new("The owner can't leave a Reign; delete it instead"));
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    super::remove_from_reign(&mut tx, reign_id, user_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    invalidate_channel_members(pool, reign_id).await;
+
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::ReignSummary;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ListReignsRequest {
+    pub token: String,
+}
+
+/// The Reigns the caller has joined, oldest membership first.
+#[post("/api/reigns/list")]
+pub async fn list_reigns(req: ListReignsRequest) -> Result<Vec<ReignSummary>, ServerFnError> {
+    use super::{reign_from_row, ReignRow, REIGN_SELECT};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    let rows = sqlx::query_as::<_, ReignRow>(&format!("{REIGN_SELECT} WHERE rm.user_id = $1 ORDER BY rm.joined_at ASC"))
+        .bind(user_id)
+        .fetch_all(db::pool().await)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(rows.into_iter().map(reign_from_row).collect())
+}
@@ -0,0 +1,270 @@
+pub mod ban;
+pub mod create;
+pub mod delete;
+pub mod details;
+pub mod join;
+pub mod kick;
+pub mod leave;
+pub mod list;
+
+use serde::{Deserialize, Serialize};
+
+use crate::permissions::Permissions;
+
+#[cfg(feature = "server")]
+use dioxus::prelude::ServerFnError;
+
+/// Longest accepted Reign name, in characters.
+pub const MAX_REIGN_NAME_CHARS: usize = 100;
+
+/// A Reign (community server) as seen by one of its members.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReignSummary {
+    pub id: String,
+    pub name: String,
+    pub icon_url: Option<String>,
+    pub owner_id: String,
+    /// `owner` or `member`; what the caller may do comes from `permissions`.
+    pub role: String,
+    /// The caller's Reign-wide permissions, before channel overwrites.
+    pub permissions: Permissions,
+    pub member_count: i64,
+}
+
+/// Selects a [`ReignSummary`] for the member `rm`. Callers append their own `WHERE`.
+#[cfg(feature = "server")]
+pub(crate) const REIGN_SELECT: &str =
+    "SELECT r.id, r.name, r.icon_url, r.owner_id, rm.role,
+            ARRAY(SELECT rr.permissions FROM reign_roles rr
+                  WHERE rr.reign_id = r.id
+                    AND (rr.is_default OR rr.id IN (SELECT role_id FROM reign_member_roles mr
+                                                   WHERE mr.reign_id = r.id AND mr.user_id = rm.user_id))) AS permissions,
+            (SELECT COUNT(*) FROM reign_members c WHERE c.reign_id = r.id) AS member_count
+     FROM reign_members rm
+     JOIN reigns r ON r.id = rm.reign_id";
+
+#[cfg(feature = "server")]
+pub(crate) type ReignRow = (uuid::Uuid, String, Option<String>, uuid::Uuid, String, Vec<i64>, i64);
+
+#[cfg(feature = "server")]
+pub(crate) fn reign_from_row(r: ReignRow) -> ReignSummary {
+    let roles: Vec<Permissions> = r.5.into_iter().map(Permissions::from_bits).collect();
+    let permissions = crate::permissions::base_permissions(r.4 == "owner", Permissions::NONE, &roles);
+    ReignSummary {
+        id: r.0.to_string(),
+        name: r.1,
+        icon_url: r.2,
+        owner_id: r.3.to_string(),
+        role: r.4,
+        permissions,
+        member_count: r.6,
+    }
+}
+
+#[cfg(feature = "server")]
+pub(crate) fn parse_reign_id(reign_id: &str) -> Result<uuid::Uuid, ServerFnError> {
+    reign_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid reign_id: {e}")))
+}
+
+/// The caller's role in a Reign, or an error if they haven't joined it.
+#[cfg(feature = "server")]
+pub(crate) async fn require_reign_member(
+    pool: &sqlx::PgPool,
+    reign_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+) -> Result<String, ServerFnError> {
+    sqlx::query_scalar::<_, String>("SELECT role FROM reign_members WHERE reign_id = $1 AND user_id = $2")
+        .bind(reign_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .ok_or_else(|| ServerFnError::new("Reign not found"))
+}
+
+/// Trim a Reign, channel or category name and check it is non-empty and short enough.
+#[cfg(feature = "server")]
+pub(crate) fn validate_name(name: &str, what: &str) -> Result<String, ServerFnError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ServerFnError::new(format!("{what} name cannot be empty")));
+    }
+    if name.chars().count() > MAX_REIGN_NAME_CHARS {
+        return Err(ServerFnError::new(format!("{what} name is limited to {MAX_REIGN_NAME_CHARS} characters")));
+    }
+    Ok(name.to_string())
+}
+
+/// Tell every member that a Reign's channels or categories changed.
+#[cfg(feature = "server")]
+pub(crate) async fn notify_reign(pool: &sqlx::PgPool, reign_id: uuid::Uuid) {
+    use crate::events::{ReignUpdate, WsEvent};
+
+    let members = sqlx::query_scalar::<_, uuid::Uuid>("SELECT user_id FROM reign_members WHERE reign_id = $1")
+        .bind(reign_id)
+        .fetch_all(pool)
+        .await;
+
+    match members {
+        Ok(members) => {
+            let event = WsEvent::ReignUpdated(ReignUpdate { reign_id: reign_id.to_string() });
+            for member in members {
+                crate::ws::send_event(member, &event);
+            }
+        }
+        Err(e) => println!("Notifying reign {reign_id} failed: {e}"),
+    }
+}
+
+/// Add a user to a Reign and all of its channels, with existing channel history
+/// marked as read. Returns whether they newly joined; banned users are refused.
+#[cfg(feature = "server")]
+pub(crate) async fn add_to_reign(
+    pool: &sqlx::PgPool,
+    reign_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+) -> Result<bool, ServerFnError> {
+    let banned = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM reign_bans WHERE reign_id = $1 AND user_id = $2)",
+    )
+    .bind(reign_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+    if banned {
+        return Err(ServerFnError::new("You are banned from this Reign"));
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let joined = sqlx::query(
+        "INSERT INTO reign_members (reign_id, user_id) SELECT id, $2 FROM reigns WHERE id = $1
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(reign_id)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?
+    .rows_affected()
+        > 0;
+
+    if joined {
+        sqlx::query(
+            "INSERT INTO conversation_members (conversation_id, user_id, last_read_message_id, last_read_message_at)
+             SELECT id, $2, last_message_id, last_message_at FROM conversations WHERE reign_id = $1
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(reign_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    }
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if joined {
+        invalidate_channel_members(pool, reign_id).await;
+    }
+
+    Ok(joined)
+}
+
+/// Remove a member from a Reign, its channels and its roles.
+#[cfg(feature = "server")]
+pub(crate) async fn remove_from_reign(
+    conn: &mut sqlx::PgConnection,
+    reign_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+) -> Result<(), sqlx::Error> {
+    // Role assignments go with the membership through the foreign key
+    sqlx::query("DELETE FROM reign_members WHERE reign_id = $1 AND user_id = $2")
+        .bind(reign_id)
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        "DELETE FROM conversation_members
+         WHERE user_id = $2 AND conversation_id IN (SELECT id FROM conversations WHERE reign_id = $1)",
+    )
+    .bind(reign_id)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        "DELETE FROM channel_overwrites
+         WHERE target_type = 'member' AND target_id = $2
+           AND channel_id IN (SELECT id FROM conversations WHERE reign_id = $1)",
+    )
+    .bind(reign_id)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Shared by kick and ban: check the caller holds `needed` and outranks the
+/// target, then remove the target if they are a member. Returns whether they were.
+#[cfg(feature = "server")]
+pub(crate) async fn remove_member(
+    pool: &sqlx::PgPool,
+    conn: &mut sqlx::PgConnection,
+    reign_id: uuid::Uuid,
+    actor_id: uuid::Uuid,
+    target_id: uuid::Uuid,
+    needed: Permissions,
+) -> Result<bool, ServerFnError> {
+    use crate::features::roles::{reign_rank, require_reign_permission};
+
+    if actor_id == target_id {
+        return Err(ServerFnError::new("You can't remove yourself; leave the Reign instead"));
+    }
+    let actor = require_reign_permission(pool, reign_id, actor_id, needed).await?;
+    let Some(target) = reign_rank(pool, reign_id, target_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+    else {
+        return Ok(false);
+    };
+    if !actor.outranks(&target) {
+        return Err(ServerFnError::new("You can only remove members ranked below you"));
+    }
+
+    remove_from_reign(conn, reign_id, target_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    Ok(true)
+}
+
+/// After a kick or ban: refresh the remaining members and tell the removed
+/// user's clients, which will find the Reign gone when they refetch it.
+#[cfg(feature = "server")]
+pub(crate) async fn member_removed(pool: &sqlx::PgPool, reign_id: uuid::Uuid, user_id: uuid::Uuid) {
+    use crate::events::{ReignUpdate, WsEvent};
+
+    invalidate_channel_members(pool, reign_id).await;
+    notify_reign(pool, reign_id).await;
+    crate::ws::send_event(user_id, &WsEvent::ReignUpdated(ReignUpdate { reign_id: reign_id.to_string() }));
+}
+
+/// Forget cached channel audiences after someone joins or leaves a Reign,
+/// or roles or overwrites change.
+#[cfg(feature = "server")]
+pub(crate) async fn invalidate_channel_members(pool: &sqlx::PgPool, reign_id: uuid::Uuid) {
+    let channels = sqlx::query_scalar::<_, uuid::Uuid>("SELECT id FROM conversations WHERE reign_id = $1")
+        .bind(reign_id)
+        .fetch_all(pool)
+        .await
+        .unwrap_or_default();
+
+    for channel in channels {
+        crate::features::conversations::invalidate_members(channel);
+    }
+}
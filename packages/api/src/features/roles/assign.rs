@@ -0,0 +1,74 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AssignRoleRequest {
+    pub token: String,
+    pub role_id: String,
+    pub user_id: String,
+}
+
+/// Give a Reign member a role below the caller's highest role.
+#[post("/api/roles/assign")]
+pub async fn assign_role(req: AssignRoleRequest) -> Result<(), ServerFnError> {
+    set_assignment(req, true).await
+}
+
+/// Take a role below the caller's highest role away from a member.
+#[post("/api/roles/unassign")]
+pub async fn unassign_role(req: AssignRoleRequest) -> Result<(), ServerFnError> {
+    set_assignment(req, false).await
+}
+
+#[cfg(feature = "server")]
+async fn set_assignment(req: AssignRoleRequest, assign: bool) -> Result<(), ServerFnError> {
+    use super::{fetch_role, permissions_changed, require_reign_permission};
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::permissions::Permissions;
+    use crate::features::reigns::require_reign_member;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let target_id: uuid::Uuid = req
+        .user_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid user_id: {e}")))?;
+
+    let pool = db::pool().await;
+    let role = fetch_role(pool, &req.role_id).await?;
+    let role_id: uuid::Uuid = role.id.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let reign_id: uuid::Uuid = role.reign_id.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let rank = require_reign_permission(pool, reign_id, user_id, Permissions::MANAGE_ROLES).await?;
+
+    if role.is_default {
+        return Err(ServerFnError::new("Everyone has @everyone"));
+    }
+    if !rank.can_manage_role(role.position) {
+        return Err(ServerFnError::new("You can only assign roles below your highest role"));
+    }
+    require_reign_member(pool, reign_id, target_id)
+        .await
+        .map_err(|_| ServerFnError::new("That user isn't in this Reign"))?;
+
+    let query = if assign {
+        "INSERT INTO reign_member_roles (reign_id, user_id, role_id) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING"
+    } else {
+        "DELETE FROM reign_member_roles WHERE reign_id = $1 AND user_id = $2 AND role_id = $3"
+    };
+    let changed = sqlx::query(query)
+        .bind(reign_id)
+        .bind(target_id)
+        .bind(role_id)
+        .execute(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .rows_affected()
+        > 0;
+
+    if changed {
+        permissions_changed(pool, reign_id).await;
+    }
+
+    Ok(())
+}
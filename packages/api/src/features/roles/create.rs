@@ -0,0 +1,76 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::Role;
+use crate::permissions::Permissions;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CreateRoleRequest {
+    pub token: String,
+    pub reign_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub permissions: Permissions,
+}
+
+/// Create a role at the bottom of the hierarchy, just above `@everyone`.
+/// Callers can only grant permissions they have themselves.
+#[post("/api/roles/create")]
+pub async fn create_role(req: CreateRoleRequest) -> Result<Role, ServerFnError> {
+    use super::{permissions_changed, require_reign_permission, role_from_row, validate_role_name, RoleRow, MAX_ROLES_PER_REIGN};
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::reigns::parse_reign_id;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let reign_id = parse_reign_id(&req.reign_id)?;
+    let name = validate_role_name(&req.name)?;
+
+    let pool = db::pool().await;
+    let rank = require_reign_permission(pool, reign_id, user_id, Permissions::MANAGE_ROLES).await?;
+    if !rank.can_grant(req.permissions) {
+        return Err(ServerFnError::new("You can't grant permissions you don't have"));
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    // Serialize role changes per Reign so positions stay distinct
+    sqlx::query("SELECT id FROM reigns WHERE id = $1 FOR UPDATE")
+        .bind(reign_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM reign_roles WHERE reign_id = $1")
+        .bind(reign_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    if count >= MAX_ROLES_PER_REIGN {
+        return Err(ServerFnError::new(format!("A Reign can have at most {MAX_ROLES_PER_REIGN} roles")));
+    }
+
+    sqlx::query("UPDATE reign_roles SET position = position + 1 WHERE reign_id = $1 AND NOT is_default")
+        .bind(reign_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let row = sqlx::query_as::<_, RoleRow>(
+        "INSERT INTO reign_roles (reign_id, name, permissions, position) VALUES ($1, $2, $3, 1)
+         RETURNING id, reign_id, name, permissions, position, is_default",
+    )
+    .bind(reign_id)
+    .bind(&name)
+    .bind(req.permissions.bits())
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    permissions_changed(pool, reign_id).await;
+
+    Ok(role_from_row(row))
+}
@@ -0,0 +1,54 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RoleRequest {
+    pub token: String,
+    pub role_id: String,
+}
+
+/// Delete a role, unassigning it and dropping its channel overwrites.
+#[post("/api/roles/delete")]
+pub async fn delete_role(req: RoleRequest) -> Result<(), ServerFnError> {
+    use super::{fetch_role, permissions_changed, require_reign_permission};
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    let pool = db::pool().await;
+    let role = fetch_role(pool, &req.role_id).await?;
+    let role_id: uuid::Uuid = role.id.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let reign_id: uuid::Uuid = role.reign_id.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let rank = require_reign_permission(pool, reign_id, user_id, Permissions::MANAGE_ROLES).await?;
+
+    if role.is_default {
+        return Err(ServerFnError::new("@everyone can't be deleted"));
+    }
+    if !rank.can_manage_role(role.position) {
+        return Err(ServerFnError::new("You can only delete roles below your highest role"));
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    sqlx::query("DELETE FROM channel_overwrites WHERE target_type = 'role' AND target_id = $1")
+        .bind(role_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    // Assignments go with it through the foreign key
+    sqlx::query("DELETE FROM reign_roles WHERE id = $1")
+        .bind(role_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    permissions_changed(pool, reign_id).await;
+
+    Ok(())
+}
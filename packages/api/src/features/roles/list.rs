@@ -0,0 +1,36 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::Role;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ListRolesRequest {
+    pub token: String,
+    pub reign_id: String,
+}
+
+/// A Reign's roles, highest first; `@everyone` is always last.
+#[post("/api/roles/list")]
+pub async fn list_roles(req: ListRolesRequest) -> Result<Vec<Role>, ServerFnError> {
+    use super::{role_from_row, RoleRow, ROLE_SELECT};
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::reigns::{parse_reign_id, require_reign_member};
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let reign_id = parse_reign_id(&req.reign_id)?;
+
+    let pool = db::pool().await;
+    require_reign_member(pool, reign_id, user_id).await?;
+
+    let rows = sqlx::query_as::<_, RoleRow>(&format!(
+        "{ROLE_SELECT} WHERE reign_id = $1 ORDER BY position DESC, created_at ASC"
+    ))
+    .bind(reign_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(rows.into_iter().map(role_from_row).collect())
+}
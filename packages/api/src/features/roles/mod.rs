@@ -0,0 +1,454 @@
+pub mod assign;
+pub mod create;
+pub mod delete;
+pub mod list;
+pub mod overwrites;
+pub mod update;
+
+use serde::{Deserialize, Serialize};
+
+use crate::permissions::Permissions;
+
+#[cfg(feature = "server")]
+use std::collections::HashMap;
+#[cfg(feature = "server")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "server")]
+use dioxus::prelude::ServerFnError;
+#[cfg(feature = "server")]
+use crate::permissions::{base_permissions, channel_permissions, MemberOverwrites, Overwrite};
+
+/// How many roles one Reign may have, including `@everyone`.
+pub const MAX_ROLES_PER_REIGN: i64 = 250;
+
+/// A Reign role. Every member implicitly has the `is_default` (`@everyone`) role.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Role {
+    pub id: String,
+    pub reign_id: String,
+    pub name: String,
+    pub permissions: Permissions,
+    /// Higher positions outrank lower ones. `@everyone` is always 0.
+    pub position: i32,
+    pub is_default: bool,
+}
+
+/// A channel's permission adjustment for a role or a single member.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChannelOverwrite {
+    /// `role` or `member`.
+    pub target_type: String,
+    /// A role id or a user id, depending on `target_type`.
+    pub target_id: String,
+    pub allow: Permissions,
+    pub deny: Permissions,
+}
+
+#[cfg(feature = "server")]
+pub(crate) const ROLE_SELECT: &str =
+    "SELECT id, reign_id, name, permissions, position, is_default FROM reign_roles";
+
+#[cfg(feature = "server")]
+pub(crate) type RoleRow = (uuid::Uuid, uuid::Uuid, String, i64, i32, bool);
+
+#[cfg(feature = "server")]
+pub(crate) fn role_from_row(r: RoleRow) -> Role {
+    Role {
+        id: r.0.to_string(),
+        reign_id: r.1.to_string(),
+        name: r.2,
+        permissions: Permissions::from_bits(r.3),
+        position: r.4,
+        is_default: r.5,
+    }
+}
+
+/// A role looked up by id, with the Reign it belongs to.
+#[cfg(feature = "server")]
+pub(crate) async fn fetch_role(pool: &sqlx::PgPool, role_id: &str) -> Result<Role, ServerFnError> {
+    let role_id: uuid::Uuid = role_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid role_id: {e}")))?;
+
+    sqlx::query_as::<_, RoleRow>(&format!("{ROLE_SELECT} WHERE id = $1"))
+        .bind(role_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .map(role_from_row)
+        .ok_or_else(|| ServerFnError::new("Role not found"))
+}
+
+/// What a member may do across a Reign, and how high they rank in it.
+#[cfg(feature = "server")]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ReignRank {
+    pub is_owner: bool,
+    pub permissions: Permissions,
+    /// Position of the member's highest role; 0 with only `@everyone`.
+    pub position: i32,
+}
+
+#[cfg(feature = "server")]
+impl ReignRank {
+    /// Owners outrank everyone; otherwise a strictly higher top role is needed.
+    pub fn outranks(&self, other: &ReignRank) -> bool {
+        self.is_owner || (!other.is_owner && self.position > other.position)
+    }
+
+    /// Roles can only be managed, assigned or granted below one's own top role.
+    pub fn can_manage_role(&self, position: i32) -> bool {
+        self.is_owner || position < self.position
+    }
+
+    /// Administrators may hand out anything; others only what they have.
+    pub fn can_grant(&self, permissions: Permissions) -> bool {
+        self.permissions.contains(permissions)
+    }
+}
+
+/// A Reign member's rank, or `None` if they haven't joined.
+#[cfg(feature = "server")]
+pub(crate) async fn reign_rank(
+    pool: &sqlx::PgPool,
+    reign_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+) -> Result<Option<ReignRank>, sqlx::Error> {
+    let row = sqlx::query_as::<_, (bool, Vec<i64>, i32)>(
+        "SELECT r.owner_id = rm.user_id,
+                ARRAY(SELECT rr.permissions FROM reign_roles rr
+                      WHERE rr.reign_id = r.id
+                        AND (rr.is_default OR rr.id IN (SELECT role_id FROM reign_member_roles mr
+                                                       WHERE mr.reign_id = r.id AND mr.user_id = rm.user_id))),
+                COALESCE((SELECT MAX(rr.position) FROM reign_member_roles mr
+                          JOIN reign_roles rr ON rr.id = mr.role_id
+                          WHERE mr.reign_id = r.id AND mr.user_id = rm.user_id), 0)
+         FROM reign_members rm
+         JOIN reigns r ON r.id = rm.reign_id
+         WHERE rm.reign_id = $1 AND rm.user_id = $2",
+    )
+    .bind(reign_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|(is_owner, roles, position)| {
+        let roles: Vec<Permissions> = roles.into_iter().map(Permissions::from_bits).collect();
+        ReignRank {
+            is_owner,
+            permissions: base_permissions(is_owner, Permissions::NONE, &roles),
+            position: if is_owner { i32::MAX } else { position },
+        }
+    }))
+}
+
+/// The caller's rank in a Reign, checking they hold `needed` Reign-wide.
+#[cfg(feature = "server")]
+pub(crate) async fn require_reign_permission(
+    pool: &sqlx::PgPool,
+    reign_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+    needed: Permissions,
+) -> Result<ReignRank, ServerFnError> {
+    let rank = reign_rank(pool, reign_id, user_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .ok_or_else(|| ServerFnError::new("Reign not found"))?;
+
+    if !rank.permissions.contains(needed) {
+        return Err(ServerFnError::new("You don't have permission to do that in this Reign"));
+    }
+    Ok(rank)
+}
+
+/// Roles and ownership of one Reign, loaded once and resolved per member.
+#[cfg(feature = "server")]
+struct ReignRoles {
+    owner_id: uuid::Uuid,
+    everyone_id: uuid::Uuid,
+    everyone: Permissions,
+    roles: HashMap<uuid::Uuid, Permissions>,
+}
+
+#[cfg(feature = "server")]
+impl ReignRoles {
+    async fn load(pool: &sqlx::PgPool, reign_id: uuid::Uuid) -> Result<Self, sqlx::Error> {
+        let owner_id = sqlx::query_scalar::<_, uuid::Uuid>("SELECT owner_id FROM reigns WHERE id = $1")
+            .bind(reign_id)
+            .fetch_one(pool)
+            .await?;
+
+        let rows = sqlx::query_as::<_, (uuid::Uuid, i64, bool)>(
+            "SELECT id, permissions, is_default FROM reign_roles WHERE reign_id = $1",
+        )
+        .bind(reign_id)
+        .fetch_all(pool)
+        .await?;
+
+        let (everyone_id, everyone) = rows
+            .iter()
+            .find(|r| r.2)
+            .map(|r| (r.0, Permissions::from_bits(r.1)))
+            .unwrap_or_default();
+
+        Ok(Self {
+            owner_id,
+            everyone_id,
+            everyone,
+            roles: rows.into_iter().filter(|r| !r.2).map(|r| (r.0, Permissions::from_bits(r.1))).collect(),
+        })
+    }
+
+    /// The one place a member's channel permissions are worked out.
+    /// `overwrites` are all of the channel's `(target_type, target_id, allow, deny)` rows.
+    fn resolve(&self, user_id: uuid::Uuid, role_ids: &[uuid::Uuid], overwrites: &[OverwriteRow]) -> Permissions {
+        let roles: Vec<Permissions> = role_ids.iter().filter_map(|id| self.roles.get(id).copied()).collect();
+        let base = base_permissions(user_id == self.owner_id, self.everyone, &roles);
+
+        let mut applicable = MemberOverwrites::default();
+        for (target_type, target_id, allow, deny) in overwrites {
+            let overwrite = Overwrite {
+                allow: Permissions::from_bits(*allow),
+                deny: Permissions::from_bits(*deny),
+            };
+            match target_type.as_str() {
+                "role" if *target_id == self.everyone_id => applicable.everyone = Some(overwrite),
+                "role" if role_ids.contains(target_id) => applicable.roles.push(overwrite),
+                "member" if *target_id == user_id => applicable.member = Some(overwrite),
+                _ => {}
+            }
+        }
+
+        channel_permissions(base, &applicable)
+    }
+}
+
+#[cfg(feature = "server")]
+type OverwriteRow = (String, uuid::Uuid, i64, i64);
+
+/// Permissions of a conversation's members, or only of `user_id` when given.
+/// Non-members are absent from the map.
+///
+/// Direct and group conversations have no roles: every member gets
+/// [`Permissions::DEFAULT`], and group owners and admins may also manage
+/// messages. Reign channels resolve roles and overwrites.
+#[cfg(feature = "server")]
+pub(crate) async fn member_permissions(
+    pool: &sqlx::PgPool,
+    conversation_id: uuid::Uuid,
+    user_id: Option<uuid::Uuid>,
+) -> Result<HashMap<uuid::Uuid, Permissions>, sqlx::Error> {
+    let reign_id = sqlx::query_scalar::<_, Option<uuid::Uuid>>("SELECT reign_id FROM conversations WHERE id = $1")
+        .bind(conversation_id)
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+
+    let Some(reign_id) = reign_id else {
+        let members = sqlx::query_as::<_, (uuid::Uuid, String)>(
+            "SELECT user_id, role FROM conversation_members
+             WHERE conversation_id = $1 AND ($2::uuid IS NULL OR user_id = $2)",
+        )
+        .bind(conversation_id)
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+        return Ok(members
+            .into_iter()
+            .map(|(member, role)| {
+                let permissions = match role.as_str() {
+                    "owner" | "admin" => Permissions::DEFAULT | Permissions::MANAGE_MESSAGES,
+                    _ => Permissions::DEFAULT,
+                };
+                (member, permissions)
+            })
+            .collect());
+    };
+
+    let roles = ReignRoles::load(pool, reign_id).await?;
+
+    let members = sqlx::query_as::<_, (uuid::Uuid, Vec<uuid::Uuid>)>(
+        "SELECT cm.user_id,
+                ARRAY(SELECT role_id FROM reign_member_roles mr WHERE mr.reign_id = $2 AND mr.user_id = cm.user_id)
+         FROM conversation_members cm
+         WHERE cm.conversation_id = $1 AND ($3::uuid IS NULL OR cm.user_id = $3)",
+    )
+    .bind(conversation_id)
+    .bind(reign_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let overwrites = sqlx::query_as::<_, OverwriteRow>(
+        "SELECT target_type, target_id, allow, deny FROM channel_overwrites WHERE channel_id = $1",
+    )
+    .bind(conversation_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(members
+        .into_iter()
+        .map(|(member, role_ids)| (member, roles.resolve(member, &role_ids, &overwrites)))
+        .collect())
+}
+
+/// `user_id`'s permissions in every channel of a Reign, keyed by channel id.
+#[cfg(feature = "server")]
+pub(crate) async fn reign_channel_permissions(
+    pool: &sqlx::PgPool,
+    reign_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+) -> Result<HashMap<uuid::Uuid, Permissions>, sqlx::Error> {
+    let roles = ReignRoles::load(pool, reign_id).await?;
+
+    let role_ids = sqlx::query_scalar::<_, uuid::Uuid>(
+        "SELECT role_id FROM reign_member_roles WHERE reign_id = $1 AND user_id = $2",
+    )
+    .bind(reign_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let rows = sqlx::query_as::<_, (uuid::Uuid, Option<String>, Option<uuid::Uuid>, Option<i64>, Option<i64>)>(
+        "SELECT c.id, o.target_type, o.target_id, o.allow, o.deny
+         FROM conversations c
+         LEFT JOIN channel_overwrites o ON o.channel_id = c.id
+         WHERE c.reign_id = $1",
+    )
+    .bind(reign_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut overwrites: HashMap<uuid::Uuid, Vec<OverwriteRow>> = HashMap::new();
+    for (channel_id, target_type, target_id, allow, deny) in rows {
+        let entry = overwrites.entry(channel_id).or_default();
+        if let (Some(target_type), Some(target_id)) = (target_type, target_id) {
+            entry.push((target_type, target_id, allow.unwrap_or(0), deny.unwrap_or(0)));
+        }
+    }
+
+    Ok(overwrites
+        .into_iter()
+        .map(|(channel_id, overwrites)| (channel_id, roles.resolve(user_id, &role_ids, &overwrites)))
+        .collect())
+}
+
+/// Resolved permissions per conversation, then per member.
+#[cfg(feature = "server")]
+type PermissionCache = dashmap::DashMap<uuid::Uuid, HashMap<uuid::Uuid, Permissions>>;
+
+#[cfg(feature = "server")]
+static PERMISSION_CACHE: OnceLock<PermissionCache> = OnceLock::new();
+
+#[cfg(feature = "server")]
+fn permission_cache() -> &'static PermissionCache {
+    PERMISSION_CACHE.get_or_init(dashmap::DashMap::new)
+}
+
+/// `user_id`'s permissions in a conversation, or `None` if they aren't a
+/// member. Cached alongside the conversation's audience and dropped with it
+/// by [`crate::features::conversations::invalidate_members`].
+#[cfg(feature = "server")]
+pub(crate) async fn user_permissions(
+    pool: &sqlx::PgPool,
+    conversation_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+) -> Result<Option<Permissions>, sqlx::Error> {
+    if let Some(permissions) = cached_permissions(conversation_id, user_id) {
+        return Ok(Some(permissions));
+    }
+
+    let permissions = member_permissions(pool, conversation_id, Some(user_id)).await?.remove(&user_id);
+    if let Some(permissions) = permissions {
+        permission_cache().entry(conversation_id).or_default().insert(user_id, permissions);
+    }
+    Ok(permissions)
+}
+
+/// `user_id`'s cached permissions in a conversation, without going to the
+/// database.
+#[cfg(feature = "server")]
+pub(crate) fn cached_permissions(conversation_id: uuid::Uuid, user_id: uuid::Uuid) -> Option<Permissions> {
+    permission_cache().get(&conversation_id)?.get(&user_id).copied()
+}
+
+#[cfg(feature = "server")]
+pub(crate) fn invalidate_permissions(conversation_id: uuid::Uuid) {
+    permission_cache().remove(&conversation_id);
+}
+
+/// The caller's permissions in a conversation, checking they can see it and
+/// hold `needed`. Conversations the caller can't see are reported as missing.
+#[cfg(feature = "server")]
+pub(crate) async fn require_permission(
+    pool: &sqlx::PgPool,
+    conversation_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+    needed: Permissions,
+) -> Result<Permissions, ServerFnError> {
+    let permissions = user_permissions(pool, conversation_id, user_id)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .filter(|p| p.contains(Permissions::VIEW_CHANNEL))
+        .ok_or_else(|| ServerFnError::new("Conversation not found"))?;
+
+    if !permissions.contains(needed) {
+        return Err(ServerFnError::new("You don't have permission to do that here"));
+    }
+    Ok(permissions)
+}
+
+/// Every conversation `user_id` can view: their direct and group
+/// conversations, plus the Reign channels their roles let them see.
+#[cfg(feature = "server")]
+pub(crate) async fn viewable_conversations(
+    pool: &sqlx::PgPool,
+    user_id: uuid::Uuid,
+) -> Result<Vec<uuid::Uuid>, sqlx::Error> {
+    let memberships = sqlx::query_as::<_, (uuid::Uuid, Option<uuid::Uuid>)>(
+        "SELECT c.id, c.reign_id FROM conversation_members cm
+         JOIN conversations c ON c.id = cm.conversation_id
+         WHERE cm.user_id = $1",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut viewable = Vec::with_capacity(memberships.len());
+    let mut reigns: HashMap<uuid::Uuid, HashMap<uuid::Uuid, Permissions>> = HashMap::new();
+    for (conversation_id, reign_id) in memberships {
+        let Some(reign_id) = reign_id else {
+            viewable.push(conversation_id);
+            continue;
+        };
+        let channels = match reigns.entry(reign_id) {
+            std::collections::hash_map::Entry::Occupied(e) => e.into_mut(),
+            std::collections::hash_map::Entry::Vacant(e) => {
+                e.insert(reign_channel_permissions(pool, reign_id, user_id).await?)
+            }
+        };
+        if channels.get(&conversation_id).is_some_and(|p| p.contains(Permissions::VIEW_CHANNEL)) {
+            viewable.push(conversation_id);
+        }
+    }
+    Ok(viewable)
+}
+
+/// Role and overwrite changes can show or hide channels: drop the cached
+/// audiences and have members refetch the sidebar.
+#[cfg(feature = "server")]
+pub(crate) async fn permissions_changed(pool: &sqlx::PgPool, reign_id: uuid::Uuid) {
+    crate::features::reigns::invalidate_channel_members(pool, reign_id).await;
+    crate::features::reigns::notify_reign(pool, reign_id).await;
+}
+
+#[cfg(feature = "server")]
+pub(crate) fn validate_role_name(name: &str) -> Result<String, ServerFnError> {
+    let name = crate::features::reigns::validate_name(name, "Role")?;
+    if name.starts_with('@') {
+        return Err(ServerFnError::new("Role names can't start with @"));
+    }
+    Ok(name)
+}
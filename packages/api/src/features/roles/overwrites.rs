@@ -0,0 +1,136 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::ChannelOverwrite;
+use crate::permissions::Permissions;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ListOverwritesRequest {
+    pub token: String,
+    pub channel_id: String,
+}
+
+/// Setting both `allow` and `deny` to nothing removes the overwrite.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SetOverwriteRequest {
+    pub token: String,
+    pub channel_id: String,
+    /// `role` or `member`.
+    pub target_type: String,
+    pub target_id: String,
+    #[serde(default)]
+    pub allow: Permissions,
+    #[serde(default)]
+    pub deny: Permissions,
+}
+
+#[post("/api/channels/overwrites")]
+pub async fn list_overwrites(req: ListOverwritesRequest) -> Result<Vec<ChannelOverwrite>, ServerFnError> {
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::channels::channel_reign;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    let pool = db::pool().await;
+    let (channel_id, _) = channel_reign(pool, &req.channel_id).await?;
+    super::require_permission(pool, channel_id, user_id, Permissions::MANAGE_ROLES).await?;
+
+    let rows = sqlx::query_as::<_, (String, uuid::Uuid, i64, i64)>(
+        "SELECT target_type, target_id, allow, deny FROM channel_overwrites WHERE channel_id = $1
+         ORDER BY target_type DESC, target_id",
+    )
+    .bind(channel_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(target_type, target_id, allow, deny)| ChannelOverwrite {
+            target_type,
+            target_id: target_id.to_string(),
+            allow: Permissions::from_bits(allow),
+            deny: Permissions::from_bits(deny),
+        })
+        .collect())
+}
+
+/// Create, replace or remove a channel's overwrite for a role or member.
+///
+/// Callers need Manage Roles in the channel, can only target roles below their
+/// highest role, and can only allow or deny permissions they hold there.
+#[post("/api/channels/overwrites/set")]
+pub async fn set_overwrite(req: SetOverwriteRequest) -> Result<(), ServerFnError> {
+    use super::{fetch_role, permissions_changed, require_permission, require_reign_permission};
+    use crate::auth::validate_token;
+    use crate::db;
+    use crate::features::channels::channel_reign;
+    use crate::features::reigns::require_reign_member;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    let pool = db::pool().await;
+    let (channel_id, reign_id) = channel_reign(pool, &req.channel_id).await?;
+    let held = require_permission(pool, channel_id, user_id, Permissions::MANAGE_ROLES).await?;
+    let rank = require_reign_permission(pool, reign_id, user_id, Permissions::NONE).await?;
+
+    if !(req.allow & req.deny).is_empty() {
+        return Err(ServerFnError::new("A permission can't be both allowed and denied"));
+    }
+    if !held.contains(req.allow | req.deny) {
+        return Err(ServerFnError::new("You can't change permissions you don't have"));
+    }
+
+    let target_id: uuid::Uuid = match req.target_type.as_str() {
+        "role" => {
+            let role = fetch_role(pool, &req.target_id).await?;
+            if role.reign_id != reign_id.to_string() {
+                return Err(ServerFnError::new("Role not found"));
+            }
+            if !rank.can_manage_role(role.position) {
+                return Err(ServerFnError::new("You can only change roles below your highest role"));
+            }
+            role.id.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?
+        }
+        "member" => {
+            let target_id = req
+                .target_id
+                .parse()
+                .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid target_id: {e}")))?;
+            require_reign_member(pool, reign_id, target_id)
+                .await
+                .map_err(|_| ServerFnError::new("That user isn't in this Reign"))?;
+            target_id
+        }
+        _ => return Err(ServerFnError::new("target_type must be role or member")),
+    };
+
+    let result = if req.allow.is_empty() && req.deny.is_empty() {
+        sqlx::query("DELETE FROM channel_overwrites WHERE channel_id = $1 AND target_type = $2 AND target_id = $3")
+            .bind(channel_id)
+            .bind(&req.target_type)
+            .bind(target_id)
+            .execute(pool)
+            .await
+    } else {
+        sqlx::query(
+            "INSERT INTO channel_overwrites (channel_id, target_type, target_id, allow, deny) VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (channel_id, target_type, target_id) DO UPDATE SET allow = EXCLUDED.allow, deny = EXCLUDED.deny",
+        )
+        .bind(channel_id)
+        .bind(&req.target_type)
+        .bind(target_id)
+        .bind(req.allow.bits())
+        .bind(req.deny.bits())
+        .execute(pool)
+        .await
+    };
+    result.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    permissions_changed(pool, reign_id).await;
+
+    Ok(())
+}
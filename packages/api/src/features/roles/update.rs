@@ -0,0 +1,74 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::Role;
+use crate::permissions::Permissions;
+
+/// Fields left as `None` are unchanged. `@everyone` can only have its permissions changed.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct UpdateRoleRequest {
+    pub token: String,
+    pub role_id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub permissions: Option<Permissions>,
+    /// Must be at least 1 and below the caller's own highest role.
+    #[serde(default)]
+    pub position: Option<i32>,
+}
+
+#[post("/api/roles/update")]
+pub async fn update_role(req: UpdateRoleRequest) -> Result<Role, ServerFnError> {
+    use super::{fetch_role, permissions_changed, require_reign_permission, role_from_row, validate_role_name, RoleRow};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    let pool = db::pool().await;
+    let role = fetch_role(pool, &req.role_id).await?;
+    let reign_id: uuid::Uuid = role.reign_id.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let rank = require_reign_permission(pool, reign_id, user_id, Permissions::MANAGE_ROLES).await?;
+
+    if !rank.can_manage_role(role.position) {
+        return Err(ServerFnError::new("You can only change roles below your highest role"));
+    }
+    if role.is_default && (req.name.is_some() || req.position.is_some()) {
+        return Err(ServerFnError::new("@everyone can't be renamed or moved"));
+    }
+    if let Some(position) = req.position {
+        if position < 1 || !rank.can_manage_role(position) {
+            return Err(ServerFnError::new("Roles can only be moved below your highest role"));
+        }
+    }
+    if let Some(permissions) = req.permissions {
+        // Leaving bits the caller lacks untouched is fine; adding them is not
+        let added = permissions & !role.permissions;
+        if !rank.can_grant(added) {
+            return Err(ServerFnError::new("You can't grant permissions you don't have"));
+        }
+    }
+    let name = req.name.as_deref().map(validate_role_name).transpose()?;
+
+    let row = sqlx::query_as::<_, RoleRow>(
+        "UPDATE reign_roles SET
+             name = COALESCE($2, name),
+             permissions = COALESCE($3, permissions),
+             position = COALESCE($4, position)
+         WHERE id = $1
+         RETURNING id, reign_id, name, permissions, position, is_default",
+    )
+    .bind(role.id.parse::<uuid::Uuid>().map_err(|e| ServerFnError::new(e.to_string()))?)
+    .bind(&name)
+    .bind(req.permissions.map(Permissions::bits))
+    .bind(req.position)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    permissions_changed(pool, reign_id).await;
+
+    Ok(role_from_row(row))
+}
@@ -0,0 +1,38 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CancelScheduledRequest {
+    pub token: String,
+    pub scheduled_id: String,
+}
+
+/// Drop a scheduled message before it is sent. Its attachments go back to
+/// being unsent uploads.
+#[post("/api/scheduled/cancel")]
+pub async fn cancel_scheduled_message(req: CancelScheduledRequest) -> Result<(), ServerFnError> {
+    use super::lock_for_change;
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let scheduled_id: uuid::Uuid = req
+        .scheduled_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid scheduled_id: {e}")))?;
+
+    let pool = db::pool().await;
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+    lock_for_change(&mut tx, scheduled_id, user_id).await?;
+
+    sqlx::query("DELETE FROM scheduled_messages WHERE id = $1")
+        .bind(scheduled_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::ScheduledMessage;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ListScheduledRequest {
+    pub token: String,
+    /// Only messages scheduled for this conversation.
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+}
+
+/// The caller's scheduled messages, soonest first. Includes ones that
+/// failed to send, with their `error`.
+#[post("/api/scheduled/list")]
+pub async fn list_scheduled_messages(req: ListScheduledRequest) -> Result<Vec<ScheduledMessage>, ServerFnError> {
+    use super::{scheduled_from_row, ScheduledRow, SCHEDULED_COLUMNS};
+    use crate::auth::validate_token;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let conversation_id: Option<uuid::Uuid> = req
+        .conversation_id
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid conversation_id: {e}")))?;
+
+    let pool = db::pool().await;
+    let rows = sqlx::query_as::<_, ScheduledRow>(&format!(
+        "SELECT {SCHEDULED_COLUMNS} FROM scheduled_messages s
+         WHERE s.sender_id = $1 AND ($2::uuid IS NULL OR s.conversation_id = $2)
+         ORDER BY s.send_at ASC, s.id ASC"
+    ))
+    .bind(user_id)
+    .bind(conversation_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(rows.into_iter().map(scheduled_from_row).collect())
+}
@@ -0,0 +1,209 @@
+//! "Send later": messages stored now and sent at a chosen time.
+//!
+//! A worker inside the server process delivers due messages through the same
+//! path as `create_message`, so permissions are checked again at send time
+//! and members get the usual `message_created` event. Workers claim rows
+//! before sending, and every delivered message records the scheduled id it
+//! came from under a unique constraint, so several server instances never
+//! send one twice, even when a worker dies between sending and cleaning up.
+
+pub mod cancel;
+pub mod list;
+pub mod schedule;
+pub mod update;
+
+use serde::{Deserialize, Serialize};
+
+/// Most messages one user can have waiting to be sent.
+pub const MAX_PENDING_PER_USER: i64 = 100;
+/// How far ahead a message can be scheduled.
+pub const MAX_SCHEDULE_DAYS: i64 = 365;
+
+/// A message waiting to be sent. Delivered messages disappear from the
+/// schedule; failed ones stay, with `error` set, until edited or cancelled.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ScheduledMessage {
+    pub id: String,
+    pub conversation_id: String,
+    pub content: String,
+    pub attachment_ids: Vec<String>,
+    pub reply_to_id: Option<String>,
+    pub send_at: String,
+    pub created_at: String,
+    /// Why delivery failed for good; editing the message queues it again.
+    pub error: Option<String>,
+}
+
+/// Delivery attempts before a scheduled message is marked failed.
+#[cfg(feature = "server")]
+const MAX_ATTEMPTS: i32 = 3;
+/// Due messages claimed per run of the worker.
+#[cfg(feature = "server")]
+const BATCH: i64 = 50;
+/// Claims older than this are assumed abandoned by their worker.
+#[cfg(feature = "server")]
+const CLAIM_TIMEOUT: &str = "5 minutes";
+
+/// Columns of `scheduled_messages s` read into a [`ScheduledRow`], for
+/// `SELECT` and `RETURNING` alike.
+#[cfg(feature = "server")]
+pub(crate) const SCHEDULED_COLUMNS: &str =
+    "s.id, s.conversation_id, s.content, s.attachment_ids, s.reply_to_id, s.send_at, s.created_at, s.error";
+
+#[cfg(feature = "server")]
+#[derive(sqlx::FromRow)]
+pub(crate) struct ScheduledRow {
+    id: uuid::Uuid,
+    conversation_id: uuid::Uuid,
+    content: String,
+    attachment_ids: Vec<uuid::Uuid>,
+    reply_to_id: Option<uuid::Uuid>,
+    send_at: chrono::DateTime<chrono::Utc>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    error: Option<String>,
+}
+
+#[cfg(feature = "server")]
+pub(crate) fn scheduled_from_row(r: ScheduledRow) -> ScheduledMessage {
+    ScheduledMessage {
+        id: r.id.to_string(),
+        conversation_id: r.conversation_id.to_string(),
+        content: r.content,
+        attachment_ids: r.attachment_ids.iter().map(|id| id.to_string()).collect(),
+        reply_to_id: r.reply_to_id.map(|id| id.to_string()),
+        send_at: r.send_at.to_rfc3339(),
+        created_at: r.created_at.to_rfc3339(),
+        error: r.error,
+    }
+}
+
+/// Parse a requested send time, which must be in the future and within
+/// [`MAX_SCHEDULE_DAYS`].
+#[cfg(feature = "server")]
+pub(crate) fn parse_send_at(send_at: &str) -> Result<chrono::DateTime<chrono::Utc>, dioxus::prelude::ServerFnError> {
+    use dioxus::prelude::ServerFnError;
+
+    let send_at = chrono::DateTime::parse_from_rfc3339(send_at)
+        .map_err(|e| ServerFnError::new(format!("Invalid send_at: {e}")))?
+        .with_timezone(&chrono::Utc);
+    let now = chrono::Utc::now();
+    if send_at <= now {
+        return Err(ServerFnError::new("send_at must be in the future"));
+    }
+    if send_at > now + chrono::Duration::days(MAX_SCHEDULE_DAYS) {
+        return Err(ServerFnError::new(format!(
+            "Messages can be scheduled at most {MAX_SCHEDULE_DAYS} days ahead"
+        )));
+    }
+    Ok(send_at)
+}
+
+/// Lock one of `sender_id`'s scheduled messages for a change, refusing
+/// while a worker is sending it.
+#[cfg(feature = "server")]
+pub(crate) async fn lock_for_change(
+    conn: &mut sqlx::PgConnection,
+    id: uuid::Uuid,
+    sender_id: uuid::Uuid,
+) -> Result<(), dioxus::prelude::ServerFnError> {
+    use dioxus::prelude::ServerFnError;
+
+    let sending = sqlx::query_scalar::<_, bool>(&format!(
+        "SELECT COALESCE(claimed_at > NOW() - INTERVAL '{CLAIM_TIMEOUT}', FALSE) FROM scheduled_messages
+         WHERE id = $1 AND sender_id = $2
+         FOR UPDATE"
+    ))
+    .bind(id)
+    .bind(sender_id)
+    .fetch_optional(conn)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?
+    .ok_or_else(|| ServerFnError::new("Scheduled message not found"))?;
+
+    if sending {
+        return Err(ServerFnError::new("This message is being sent"));
+    }
+    Ok(())
+}
+
+/// A due message claimed for delivery.
+#[cfg(feature = "server")]
+#[derive(sqlx::FromRow)]
+struct Claimed {
+    id: uuid::Uuid,
+    sender_id: uuid::Uuid,
+    conversation_id: uuid::Uuid,
+    content: String,
+    attachment_ids: Vec<uuid::Uuid>,
+    reply_to_id: Option<uuid::Uuid>,
+    attempts: i32,
+}
+
+/// Send every due scheduled message. Returns how many were delivered.
+#[cfg(feature = "server")]
+pub(crate) async fn deliver_due(pool: &sqlx::PgPool) -> Result<u64, sqlx::Error> {
+    use crate::features::messages::create::{send_message, Outgoing};
+
+    let claimed = sqlx::query_as::<_, Claimed>(&format!(
+        "UPDATE scheduled_messages SET claimed_at = NOW(), attempts = attempts + 1
+         WHERE id IN (
+             SELECT id FROM scheduled_messages
+             WHERE failed_at IS NULL AND send_at <= NOW()
+               AND (claimed_at IS NULL OR claimed_at < NOW() - INTERVAL '{CLAIM_TIMEOUT}')
+             ORDER BY send_at ASC
+             LIMIT $1
+             FOR UPDATE SKIP LOCKED
+         )
+         RETURNING id, sender_id, conversation_id, content, attachment_ids, reply_to_id, attempts"
+    ))
+    .bind(BATCH)
+    .fetch_all(pool)
+    .await?;
+
+    let mut delivered = 0;
+    for message in claimed {
+        let id = message.id;
+        let outgoing = Outgoing {
+            conversation_id: Some(message.conversation_id.to_string()),
+            recipient_id: None,
+            content: message.content,
+            attachment_ids: message.attachment_ids,
+            reply_to_id: message.reply_to_id,
+            scheduled_id: Some(id),
+            forward_of: None,
+        };
+        let result = send_message(pool, message.sender_id, outgoing).await;
+
+        // A worker that died after sending leaves the message behind; that counts too
+        let sent = result.is_ok()
+            || sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM messages WHERE scheduled_message_id = $1)")
+                .bind(id)
+                .fetch_one(pool)
+                .await?;
+
+        if sent {
+            sqlx::query("DELETE FROM scheduled_messages WHERE id = $1")
+                .bind(id)
+                .execute(pool)
+                .await?;
+            delivered += 1;
+        } else if let Err(e) = result {
+            println!("Delivering scheduled message {id} failed (attempt {}): {e}", message.attempts);
+            // Give up after a few tries; until then the next run picks it up again
+            sqlx::query(
+                "UPDATE scheduled_messages
+                 SET claimed_at = NULL, updated_at = NOW(),
+                     failed_at = CASE WHEN $2 THEN NOW() END,
+                     error = CASE WHEN $2 THEN $3 END
+                 WHERE id = $1",
+            )
+            .bind(id)
+            .bind(message.attempts >= MAX_ATTEMPTS)
+            .bind(e.to_string())
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    Ok(delivered)
+}
@@ -0,0 +1,134 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::ScheduledMessage;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ScheduleMessageRequest {
+    pub token: String,
+    /// Post into this conversation. Either this or `recipient_id` is required.
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    /// Send a direct message, starting the conversation now if needed.
+    #[serde(default)]
+    pub recipient_id: Option<String>,
+    /// May be empty when the message carries attachments.
+    pub content: String,
+    /// Unsent uploads to send with the message; they are held until then.
+    #[serde(default)]
+    pub attachment_ids: Vec<String>,
+    #[serde(default)]
+    pub reply_to_id: Option<String>,
+    /// When to send, as an RFC 3339 timestamp.
+    pub send_at: String,
+}
+
+/// Queue a message to be sent at `send_at`. Permissions are checked now and
+/// again when the message is sent.
+#[post("/api/scheduled/create")]
+pub async fn schedule_message(req: ScheduleMessageRequest) -> Result<ScheduledMessage, ServerFnError> {
+    use super::{parse_send_at, scheduled_from_row, ScheduledRow, MAX_PENDING_PER_USER, SCHEDULED_COLUMNS};
+    use crate::auth::validate_token;
+    use crate::content;
+    use crate::db;
+    use crate::features::{attachments, conversations};
+    use crate::permissions::Permissions;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let sender_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+
+    let send_at = parse_send_at(&req.send_at)?;
+    let attachment_ids = attachments::parse_attachment_ids(&req.attachment_ids)?;
+    let content = content::normalize_content(&req.content).map_err(|e| ServerFnError::new(e.to_string()))?;
+    if content.is_empty() && attachment_ids.is_empty() {
+        return Err(ServerFnError::new("Message content cannot be empty"));
+    }
+    let reply_to_id: Option<uuid::Uuid> = req
+        .reply_to_id
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid reply_to_id: {e}")))?;
+
+    let pool = db::pool().await;
+    let conversation_id = conversations::resolve_conversation(
+        pool,
+        sender_id,
+        req.conversation_id.as_deref(),
+        req.recipient_id.as_deref(),
+        true,
+        Permissions::SEND_MESSAGES,
+    )
+    .await?
+    .ok_or_else(|| ServerFnError::new("Conversation not found"))?;
+
+    if let Some(parent_id) = reply_to_id {
+        let live = sqlx::query_scalar::<_, bool>(
+            "SELECT deleted_at IS NULL FROM messages WHERE id = $1 AND conversation_id = $2",
+        )
+        .bind(parent_id)
+        .bind(conversation_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .ok_or_else(|| ServerFnError::new("The message you are replying to is not in this conversation"))?;
+        if !live {
+            return Err(ServerFnError::new("Cannot reply to a deleted message"));
+        }
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    // Serialize scheduling per user so the pending limit holds
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext('scheduled:' || $1::text))")
+        .bind(sender_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let pending = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM scheduled_messages WHERE sender_id = $1")
+        .bind(sender_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+    if pending >= MAX_PENDING_PER_USER {
+        return Err(ServerFnError::new(format!(
+            "You can have at most {MAX_PENDING_PER_USER} scheduled messages"
+        )));
+    }
+
+    // Attachments must be the sender's unsent uploads here, not held by another scheduled message
+    let available = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM attachments a
+         WHERE a.id = ANY($1) AND a.conversation_id = $2 AND a.uploader_id = $3 AND a.message_id IS NULL
+           AND NOT EXISTS (SELECT 1 FROM scheduled_messages s WHERE a.id = ANY(s.attachment_ids))",
+    )
+    .bind(&attachment_ids)
+    .bind(conversation_id)
+    .bind(sender_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+    if available != attachment_ids.len() as i64 {
+        return Err(ServerFnError::new("Attachment not found or already sent"));
+    }
+
+    let row = sqlx::query_as::<_, ScheduledRow>(&format!(
+        "INSERT INTO scheduled_messages AS s (sender_id, conversation_id, content, attachment_ids, reply_to_id, send_at)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING {SCHEDULED_COLUMNS}"
+    ))
+    .bind(sender_id)
+    .bind(conversation_id)
+    .bind(&content)
+    .bind(&attachment_ids)
+    .bind(reply_to_id)
+    .bind(send_at)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(scheduled_from_row(row))
+}
@@ -0,0 +1,67 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::ScheduledMessage;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct UpdateScheduledRequest {
+    pub token: String,
+    pub scheduled_id: String,
+    /// New content; unchanged when `None`.
+    #[serde(default)]
+    pub content: Option<String>,
+    /// New send time as an RFC 3339 timestamp; unchanged when `None`.
+    #[serde(default)]
+    pub send_at: Option<String>,
+}
+
+/// Change a scheduled message's content or time. A message that failed to
+/// send is queued again.
+#[post("/api/scheduled/update")]
+pub async fn update_scheduled_message(req: UpdateScheduledRequest) -> Result<ScheduledMessage, ServerFnError> {
+    use super::{lock_for_change, parse_send_at, scheduled_from_row, ScheduledRow, SCHEDULED_COLUMNS};
+    use crate::auth::validate_token;
+    use crate::content;
+    use crate::db;
+
+    let claims = validate_token(&req.token).map_err(|e| ServerFnError::new(format!("Unauthorized: {e}")))?;
+    let user_id: uuid::Uuid = claims.sub.parse().map_err(|e: uuid::Error| ServerFnError::new(e.to_string()))?;
+    let scheduled_id: uuid::Uuid = req
+        .scheduled_id
+        .parse()
+        .map_err(|e: uuid::Error| ServerFnError::new(format!("Invalid scheduled_id: {e}")))?;
+    let send_at = req.send_at.as_deref().map(parse_send_at).transpose()?;
+    let content = req
+        .content
+        .as_deref()
+        .map(content::normalize_content)
+        .transpose()
+        .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    let pool = db::pool().await;
+    let mut tx = pool.begin().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+    lock_for_change(&mut tx, scheduled_id, user_id).await?;
+
+    // A failed message whose time has passed goes out on the next run
+    let row = sqlx::query_as::<_, ScheduledRow>(&format!(
+        "UPDATE scheduled_messages s
+         SET content = COALESCE($2, s.content), send_at = COALESCE($3, s.send_at),
+             failed_at = NULL, error = NULL, attempts = 0, updated_at = NOW()
+         WHERE s.id = $1
+         RETURNING {SCHEDULED_COLUMNS}"
+    ))
+    .bind(scheduled_id)
+    .bind(&content)
+    .bind(send_at)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    if row.content.is_empty() && row.attachment_ids.is_empty() {
+        return Err(ServerFnError::new("Message content cannot be empty"));
+    }
+
+    tx.commit().await.map_err(|e| ServerFnError::new(e.to_string()))?;
+
+    Ok(scheduled_from_row(row))
+}
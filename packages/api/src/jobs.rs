@@ -0,0 +1,129 @@
+//! Background maintenance tasks that run inside the server process.
+
+use std::sync::Once;
+use std::time::Duration;
+
+/// How often deleted-message tombstones past their retention period are purged.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// How often messages past their disappearing timer are removed.
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(30);
+/// How often due scheduled messages are sent.
+const SCHEDULED_INTERVAL: Duration = Duration::from_secs(15);
+/// How often images left unprocessed by a restart or crash are picked up.
+const PREVIEW_INTERVAL: Duration = Duration::from_secs(60);
+/// How often idle send rate limit buckets are forgotten.
+const RATE_LIMIT_PRUNE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// How often idle WebSocket devices and outboxes are forgotten.
+const OUTBOX_PRUNE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+static STARTED: Once = Once::new();
+
+/// Spawn every background job onto the current Tokio runtime.
+/// Safe to call more than once; only the first call starts anything.
+pub fn spawn_background_jobs() {
+    STARTED.call_once(|| {
+        tokio::spawn(purge_deleted_messages());
+        tokio::spawn(purge_expired_messages());
+        tokio::spawn(purge_orphan_attachments());
+        tokio::spawn(purge_stale_link_previews());
+        tokio::spawn(process_pending_previews());
+        tokio::spawn(deliver_scheduled_messages());
+        tokio::spawn(prune_rate_limits());
+        tokio::spawn(prune_outboxes());
+    });
+}
+
+async fn purge_deleted_messages() {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let pool = crate::db::pool().await;
+        match crate::features::messages::purge_deleted(pool).await {
+            Ok(0) => {}
+            Ok(n) => println!("Purged {n} deleted messages"),
+            Err(e) => println!("Deleted message purge failed: {e}"),
+        }
+    }
+}
+
+async fn purge_expired_messages() {
+    let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+    loop {
+        interval.tick().await;
+        let pool = crate::db::pool().await;
+        match crate::features::messages::purge_expired(pool).await {
+            Ok(0) => {}
+            Ok(n) => println!("Removed {n} expired messages"),
+            Err(e) => println!("Expired message purge failed: {e}"),
+        }
+    }
+}
+
+async fn purge_orphan_attachments() {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let pool = crate::db::pool().await;
+        match crate::features::attachments::purge_orphans(pool).await {
+            Ok(0) => {}
+            Ok(n) => println!("Purged {n} unreferenced blobs"),
+            Err(e) => println!("Attachment purge failed: {e}"),
+        }
+    }
+}
+
+async fn purge_stale_link_previews() {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let pool = crate::db::pool().await;
+        match crate::features::link_previews::purge_stale(pool).await {
+            Ok(0) => {}
+            Ok(n) => println!("Purged {n} cached link previews"),
+            Err(e) => println!("Link preview purge failed: {e}"),
+        }
+    }
+}
+
+async fn process_pending_previews() {
+    let mut interval = tokio::time::interval(PREVIEW_INTERVAL);
+    loop {
+        interval.tick().await;
+        let pool = crate::db::pool().await;
+        if let Err(e) = crate::features::attachments::previews::process_pending(pool).await {
+            println!("Image processing failed: {e}");
+        }
+    }
+}
+
+async fn deliver_scheduled_messages() {
+    let mut interval = tokio::time::interval(SCHEDULED_INTERVAL);
+    loop {
+        interval.tick().await;
+        let pool = crate::db::pool().await;
+        match crate::features::scheduled::deliver_due(pool).await {
+            Ok(0) => {}
+            Ok(n) => println!("Delivered {n} scheduled messages"),
+            Err(e) => println!("Scheduled message delivery failed: {e}"),
+        }
+    }
+}
+
+async fn prune_rate_limits() {
+    let mut interval = tokio::time::interval(RATE_LIMIT_PRUNE_INTERVAL);
+    loop {
+        interval.tick().await;
+        crate::features::messages::rate_limit::prune();
+    }
+}
+
+async fn prune_outboxes() {
+    let mut interval = tokio::time::interval(OUTBOX_PRUNE_INTERVAL);
+    loop {
+        interval.tick().await;
+        match crate::ws::prune_outboxes() {
+            0 => {}
+            n => println!("Dropped {n} idle WebSocket outboxes"),
+        }
+    }
+}
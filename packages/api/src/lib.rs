@@ -6,10 +6,18 @@
 use dioxus::prelude::*;
 
 pub mod auth;
+pub mod content;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod db;
+pub mod events;
 pub mod features;
-#[cfg(not(target_arch = "wasm32"))]
+pub mod markdown;
+#[cfg(feature = "server")]
+pub mod jobs;
+pub mod permissions;
+#[cfg(feature = "server")]
+pub mod storage;
+#[cfg(feature = "server")]
 pub mod ws;
 
 // Re-export feature endpoints so consumers can reference them directly.
@@ -19,6 +27,59 @@ pub use features::messages::create::create_message;
 pub use features::messages::list::list_messages;
 pub use features::messages::update::update_message;
 pub use features::messages::delete::delete_message;
+pub use features::messages::history::list_message_edits;
+pub use features::messages::thread::list_thread_replies;
+pub use features::messages::search::search_messages;
+pub use features::messages::forward::forward_messages;
+pub use features::reactions::add::add_reaction;
+pub use features::reactions::remove::remove_reaction;
+pub use features::conversations::list::list_conversations;
+pub use features::conversations::mark_read::mark_conversation_read;
+pub use features::conversations::create_group::create_group;
+pub use features::conversations::add_member::add_member;
+pub use features::conversations::remove_member::remove_member;
+pub use features::conversations::leave::leave_conversation;
+pub use features::conversations::rename::rename_conversation;
+pub use features::conversations::set_avatar::set_conversation_avatar;
+pub use features::conversations::set_message_ttl::set_message_ttl;
+pub use features::conversations::members::list_members;
+pub use features::reigns::create::create_reign;
+pub use features::reigns::list::list_reigns;
+pub use features::reigns::details::get_reign;
+pub use features::reigns::join::join_reign;
+pub use features::reigns::leave::leave_reign;
+pub use features::reigns::delete::delete_reign;
+pub use features::reigns::kick::kick_member;
+pub use features::reigns::ban::{ban_member, unban_member};
+pub use features::channels::create::create_channel;
+pub use features::channels::update::update_channel;
+pub use features::channels::delete::delete_channel;
+pub use features::channels::create_category::create_category;
+pub use features::channels::delete_category::delete_category;
+pub use features::invites::create::create_invite;
+pub use features::invites::list::list_invites;
+pub use features::invites::revoke::revoke_invite;
+pub use features::invites::redeem::redeem_invite;
+pub use features::invites::preview::preview_invite;
+pub use features::roles::list::list_roles;
+pub use features::roles::create::create_role;
+pub use features::roles::update::update_role;
+pub use features::roles::delete::delete_role;
+pub use features::roles::assign::{assign_role, unassign_role};
+pub use features::roles::overwrites::{list_overwrites, set_overwrite};
+pub use features::attachments::download::attachment_url;
+pub use features::mentions::list::list_mentions;
+pub use features::mentions::suggest::suggest_mentions;
+pub use features::scheduled::schedule::schedule_message;
+pub use features::scheduled::list::list_scheduled_messages;
+pub use features::scheduled::update::update_scheduled_message;
+pub use features::scheduled::cancel::cancel_scheduled_message;
+pub use features::pins::pin::{pin_message, unpin_message};
+pub use features::pins::list::list_pins;
+pub use features::drafts::save::save_draft;
+pub use features::drafts::list::list_drafts;
+pub use features::blocks::block::{block_user, unblock_user};
+pub use features::blocks::list::list_blocks;
 
 /// Echo the user input on the server (kept for testing).
 #[post("/api/echo")]
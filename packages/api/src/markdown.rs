@@ -0,0 +1,428 @@
+//! The Markdown subset messages are written in.
+//!
+//! Shared between the server, which derives plain text for notifications and
+//! search, and the UI, which renders the parsed tree, so this module must stay
+//! WASM-compatible. There is no raw HTML: the tree only holds text and the
+//! elements below, and links are limited to safe schemes. Parsing never
+//! fails; anything that isn't valid markup stays as text.
+//!
+//! Supported: `**bold**`, `*italic*` or `_italic_`, `` `code` ``, fenced
+//! code blocks with an optional language tag, `[label](url)` and bare
+//! `http(s)://` links, `||spoilers||` and `> quotes`. A backslash escapes
+//! the next punctuation character. Single newlines are kept as line breaks.
+
+/// Deepest nesting of quotes or inline markup that is still parsed.
+pub const MAX_NESTING: usize = 8;
+/// Longest accepted code block language tag.
+pub const MAX_LANGUAGE_CHARS: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Paragraph(Vec<Inline>),
+    /// A block fenced by lines starting with three backticks. `language` is
+    /// the tag after the opening fence, if any.
+    CodeBlock { language: Option<String>, code: String },
+    /// Consecutive lines starting with `>`.
+    Quote(Vec<Block>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inline {
+    Text(String),
+    Bold(Vec<Inline>),
+    Italic(Vec<Inline>),
+    Code(String),
+    /// Hidden until the reader reveals it.
+    Spoiler(Vec<Inline>),
+    /// `url` always passes [`is_safe_url`].
+    Link { url: String, label: Vec<Inline> },
+    LineBreak,
+}
+
+/// Parse message content into blocks.
+pub fn parse(content: &str) -> Vec<Block> {
+    parse_blocks(content, 0)
+}
+
+/// Whether a link target may be rendered: `http`, `https` or `mailto`, with
+/// no whitespace or control characters.
+pub fn is_safe_url(url: &str) -> bool {
+    let lower = url.to_ascii_lowercase();
+    let scheme_ok = ["http://", "https://", "mailto:"]
+        .iter()
+        .any(|scheme| lower.starts_with(scheme) && lower.len() > scheme.len());
+    scheme_ok && !url.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+/// The content with markup removed, for notifications and search indexing.
+/// Spoilers are replaced with `[spoiler]` so they aren't given away.
+pub fn to_plain_text(content: &str) -> String {
+    blocks_to_plain(&parse(content))
+}
+
+fn parse_blocks(content: &str, depth: usize) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut lines = content.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        if let Some(language) = fence(line) {
+            flush_paragraph(&mut paragraph, &mut blocks, depth);
+            let mut code = Vec::new();
+            // An unclosed fence runs to the end of the message
+            for line in lines.by_ref() {
+                if fence(line).is_some() {
+                    break;
+                }
+                code.push(line);
+            }
+            blocks.push(Block::CodeBlock {
+                language,
+                code: code.join("\n"),
+            });
+        } else if let Some(first) = quoted(line).filter(|_| depth < MAX_NESTING) {
+            flush_paragraph(&mut paragraph, &mut blocks, depth);
+            let mut quote = vec![first];
+            while let Some(next) = lines.peek().and_then(|l| quoted(l)) {
+                quote.push(next);
+                lines.next();
+            }
+            blocks.push(Block::Quote(parse_blocks(&quote.join("\n"), depth + 1)));
+        } else if line.trim().is_empty() {
+            flush_paragraph(&mut paragraph, &mut blocks, depth);
+        } else {
+            paragraph.push(line);
+        }
+    }
+    flush_paragraph(&mut paragraph, &mut blocks, depth);
+
+    blocks
+}
+
+fn flush_paragraph(lines: &mut Vec<&str>, blocks: &mut Vec<Block>, depth: usize) {
+    if !lines.is_empty() {
+        blocks.push(Block::Paragraph(parse_inline(&lines.join("\n"), depth)));
+        lines.clear();
+    }
+}
+
+/// For a fence line, the language tag it carries: `Some(None)` for a bare
+/// fence. A line like ```` ```code``` ```` is inline code, not a fence.
+fn fence(line: &str) -> Option<Option<String>> {
+    let tag = line.trim_start().strip_prefix("```")?.trim();
+    if tag.contains('`') {
+        return None;
+    }
+    let valid = !tag.is_empty()
+        && tag.chars().count() <= MAX_LANGUAGE_CHARS
+        && tag.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '#' | '.' | '_' | '-'));
+    Some(valid.then(|| tag.to_ascii_lowercase()))
+}
+
+/// A quote line without its `>` marker and the space after it.
+fn quoted(line: &str) -> Option<&str> {
+    let rest = line.trim_start().strip_prefix('>')?;
+    Some(rest.strip_prefix(' ').unwrap_or(rest))
+}
+
+fn parse_inline(text: &str, depth: usize) -> Vec<Inline> {
+    let mut nodes = Vec::new();
+    let mut buffer = String::new();
+    let mut i = 0;
+
+    while let Some(c) = text[i..].chars().next() {
+        if c == '\\' {
+            if let Some(escaped) = text[i + 1..].chars().next().filter(|n| n.is_ascii_punctuation()) {
+                buffer.push(escaped);
+                i += 1 + escaped.len_utf8();
+                continue;
+            }
+        }
+
+        let markup = if c == '\n' {
+            Some((Inline::LineBreak, 1))
+        } else if depth < MAX_NESTING {
+            inline_at(text, i, depth)
+        } else {
+            None
+        };
+
+        match markup {
+            Some((node, len)) => {
+                if !buffer.is_empty() {
+                    nodes.push(Inline::Text(std::mem::take(&mut buffer)));
+                }
+                nodes.push(node);
+                i += len;
+            }
+            None => {
+                buffer.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    if !buffer.is_empty() {
+        nodes.push(Inline::Text(buffer));
+    }
+
+    nodes
+}
+
+/// Markup starting at byte `i`, with the number of bytes it spans.
+fn inline_at(text: &str, i: usize, depth: usize) -> Option<(Inline, usize)> {
+    let rest = &text[i..];
+    let after_word = text[..i].chars().next_back().is_some_and(char::is_alphanumeric);
+
+    // A code span closes with as many backticks as it opened with
+    if rest.starts_with('`') {
+        let ticks = rest.len() - rest.trim_start_matches('`').len();
+        let marker = &rest[..ticks];
+        let after = &rest[ticks..];
+        let end = after.find(marker).filter(|end| *end > 0)?;
+        return Some((Inline::Code(after[..end].to_string()), end + 2 * ticks));
+    }
+    if let Some((inner, len)) = delimited(rest, "||", false) {
+        return Some((Inline::Spoiler(parse_inline(inner, depth + 1)), len));
+    }
+    if let Some((inner, len)) = delimited(rest, "**", false) {
+        return Some((Inline::Bold(parse_inline(inner, depth + 1)), len));
+    }
+    if let Some((inner, len)) = delimited(rest, "*", false) {
+        return Some((Inline::Italic(parse_inline(inner, depth + 1)), len));
+    }
+    // Underscores inside words, as in snake_case, are left alone
+    if !after_word {
+        if let Some((inner, len)) = delimited(rest, "_", true) {
+            return Some((Inline::Italic(parse_inline(inner, depth + 1)), len));
+        }
+    }
+    if rest.starts_with('[') {
+        return link(rest, depth);
+    }
+    if !after_word && (rest.starts_with("http://") || rest.starts_with("https://")) {
+        return autolink(rest);
+    }
+    None
+}
+
+/// `marker`-delimited text at the start of `rest`, with the total length.
+/// The content can't start or end with whitespace; `word_bound` also
+/// requires the closing marker not to be followed by a letter or digit.
+fn delimited<'a>(rest: &'a str, marker: &str, word_bound: bool) -> Option<(&'a str, usize)> {
+    let after = rest.strip_prefix(marker)?;
+    if after.starts_with(char::is_whitespace) || after.starts_with(marker) {
+        return None;
+    }
+
+    let end = after.match_indices(marker).map(|(end, _)| end).find(|&end| {
+        end > 0
+            && !after[..end].ends_with(char::is_whitespace)
+            && !(word_bound && after[end + marker.len()..].starts_with(char::is_alphanumeric))
+    })?;
+    Some((&after[..end], end + 2 * marker.len()))
+}
+
+/// `[label](url)`, kept as text unless the URL is safe.
+fn link(rest: &str, depth: usize) -> Option<(Inline, usize)> {
+    let label_end = rest.find("](")?;
+    let label = &rest[1..label_end];
+    let url_start = label_end + 2;
+    let url_len = rest[url_start..].find(')')?;
+    let url = rest[url_start..url_start + url_len].trim();
+
+    if label.trim().is_empty() || label.contains('\n') || !is_safe_url(url) {
+        return None;
+    }
+    let node = Inline::Link {
+        url: url.to_string(),
+        label: parse_inline(label, depth + 1),
+    };
+    Some((node, url_start + url_len + 1))
+}
+
+/// A bare URL, ending at whitespace. Trailing punctuation is left out, as is
+/// a closing parenthesis without a matching opening one.
+fn autolink(rest: &str) -> Option<(Inline, usize)> {
+    let mut url = &rest[..rest.find(char::is_whitespace).unwrap_or(rest.len())];
+    loop {
+        let unbalanced = url.ends_with(')') && url.matches('(').count() < url.matches(')').count();
+        if unbalanced || url.ends_with(['.', ',', ';', ':', '!', '?', '\'', '"', '*', '_', '|', '`']) {
+            url = &url[..url.len() - 1];
+        } else {
+            break;
+        }
+    }
+
+    if !is_safe_url(url) {
+        return None;
+    }
+    let node = Inline::Link {
+        url: url.to_string(),
+        label: vec![Inline::Text(url.to_string())],
+    };
+    Some((node, url.len()))
+}
+
+fn blocks_to_plain(blocks: &[Block]) -> String {
+    let parts: Vec<String> = blocks
+        .iter()
+        .map(|block| match block {
+            Block::Paragraph(inlines) => inlines_to_plain(inlines),
+            Block::CodeBlock { code, .. } => code.clone(),
+            Block::Quote(blocks) => blocks_to_plain(blocks)
+                .lines()
+                .map(|line| format!("> {line}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        })
+        .collect();
+    parts.join("\n")
+}
+
+fn inlines_to_plain(inlines: &[Inline]) -> String {
+    let mut text = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text(t) | Inline::Code(t) => text.push_str(t),
+            Inline::Bold(inner) | Inline::Italic(inner) => text.push_str(&inlines_to_plain(inner)),
+            Inline::Spoiler(_) => text.push_str("[spoiler]"),
+            Inline::Link { url, label } => {
+                let label = inlines_to_plain(label);
+                if label == *url {
+                    text.push_str(url);
+                } else {
+                    text.push_str(&format!("{label} ({url})"));
+                }
+            }
+            Inline::LineBreak => text.push('\n'),
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(t: &str) -> Inline {
+        Inline::Text(t.to_string())
+    }
+
+    fn inline(content: &str) -> Vec<Inline> {
+        match parse(content).as_slice() {
+            [Block::Paragraph(inlines)] => inlines.clone(),
+            other => panic!("expected one paragraph, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn unsafe_link_schemes_stay_text() {
+        for content in [
+            "[click](javascript:alert(1))",
+            "[click](JavaScript:alert(1))",
+            "[click](data:text/html;base64,PHNjcmlwdD4=)",
+            "[click](vbscript:msgbox)",
+            "[click](//evil.example)",
+        ] {
+            assert!(
+                !inline(content).iter().any(|node| matches!(node, Inline::Link { .. })),
+                "{content} became a link"
+            );
+        }
+        assert!(!is_safe_url("https://"));
+        assert!(!is_safe_url("https://a.example/\u{0}"));
+        assert!(!is_safe_url("https://a.example/ b"));
+        assert!(is_safe_url("HTTPS://a.example"));
+        assert!(is_safe_url("mailto:someone@example.com"));
+    }
+
+    #[test]
+    fn safe_links_keep_their_label_markup() {
+        assert_eq!(
+            inline("[**docs**](https://docs.example)"),
+            [Inline::Link {
+                url: "https://docs.example".to_string(),
+                label: vec![Inline::Bold(vec![text("docs")])],
+            }]
+        );
+    }
+
+    #[test]
+    fn backslashes_escape_punctuation_only() {
+        assert_eq!(inline(r"\*not italic\*"), [text("*not italic*")]);
+        // An escaped label is no link, though the bare URL still is one
+        assert_eq!(
+            inline(r"\[x\](https://a.example)"),
+            [
+                text("[x]("),
+                Inline::Link { url: "https://a.example".to_string(), label: vec![text("https://a.example")] },
+                text(")"),
+            ]
+        );
+        assert_eq!(inline(r"C:\temp"), [text(r"C:\temp")]);
+    }
+
+    #[test]
+    fn markup_nests() {
+        assert_eq!(
+            inline("**bold _and italic_** ||secret `code`||"),
+            [
+                Inline::Bold(vec![text("bold "), Inline::Italic(vec![text("and italic")])]),
+                text(" "),
+                Inline::Spoiler(vec![text("secret "), Inline::Code("code".to_string())]),
+            ]
+        );
+        // Nothing inside a code span is markup
+        assert_eq!(inline("`**x**`"), [Inline::Code("**x**".to_string())]);
+    }
+
+    #[test]
+    fn nesting_stops_at_the_limit() {
+        let content = format!("{} deep", ">".repeat(MAX_NESTING + 2));
+        let mut depth = 0;
+        let mut blocks = parse(&content);
+        while let [Block::Quote(inner)] = blocks.as_slice() {
+            depth += 1;
+            blocks = inner.clone();
+        }
+        assert_eq!(depth, MAX_NESTING);
+        assert_eq!(blocks, [Block::Paragraph(vec![text(">> deep")])]);
+    }
+
+    #[test]
+    fn unclosed_markers_stay_text() {
+        assert_eq!(inline("**open and `tick"), [text("**open and `tick")]);
+        assert_eq!(inline("snake_case_name"), [text("snake_case_name")]);
+        assert_eq!(inline("2 * 3 * 4"), [text("2 * 3 * 4")]);
+        assert_eq!(
+            parse("```rust\nfn main() {}"),
+            [Block::CodeBlock { language: Some("rust".to_string()), code: "fn main() {}".to_string() }]
+        );
+    }
+
+    #[test]
+    fn autolinks_leave_trailing_punctuation_out() {
+        let link = |url: &str| Inline::Link { url: url.to_string(), label: vec![text(url)] };
+        assert_eq!(
+            inline("see https://a.example/page."),
+            [text("see "), link("https://a.example/page"), text(".")]
+        );
+        assert_eq!(
+            inline("(https://a.example/x)!"),
+            [text("("), link("https://a.example/x"), text(")!")]
+        );
+        assert_eq!(
+            inline("https://en.example/wiki/Rust_(language)"),
+            [link("https://en.example/wiki/Rust_(language)")]
+        );
+    }
+
+    #[test]
+    fn plain_text_hides_spoilers() {
+        assert_eq!(
+            to_plain_text("> **the butler** did it: ||it was the butler||"),
+            "> the butler did it: [spoiler]"
+        );
+        assert_eq!(to_plain_text("[docs](https://docs.example)"), "docs (https://docs.example)");
+    }
+}
@@ -0,0 +1,259 @@
+//! Role permissions and how they combine with per-channel overwrites.
+//!
+//! Shared between the server, which enforces them, and the UI, which hides
+//! controls the user can't use, so this module must stay WASM-compatible.
+
+use std::ops::{BitAnd, BitOr, BitOrAssign, Not};
+
+use serde::{Deserialize, Serialize};
+
+/// A set of permission bits, stored as `BIGINT` and sent over the wire as a number.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(transparent)]
+pub struct Permissions(u64);
+
+impl Permissions {
+    pub const VIEW_CHANNEL: Self = Self(1 << 0);
+    pub const SEND_MESSAGES: Self = Self(1 << 1);
+    /// Delete other people's messages.
+    pub const MANAGE_MESSAGES: Self = Self(1 << 2);
+    /// Create, edit and delete channels and categories.
+    pub const MANAGE_CHANNELS: Self = Self(1 << 3);
+    pub const KICK_MEMBERS: Self = Self(1 << 4);
+    pub const BAN_MEMBERS: Self = Self(1 << 5);
+    pub const MENTION_EVERYONE: Self = Self(1 << 6);
+    /// Every permission, in every channel, regardless of overwrites.
+    pub const ADMINISTRATOR: Self = Self(1 << 7);
+    /// Create and assign roles below one's own, and edit channel overwrites.
+    pub const MANAGE_ROLES: Self = Self(1 << 8);
+
+    pub const NONE: Self = Self(0);
+    pub const ALL: Self = Self((1 << 9) - 1);
+
+    /// What a new Reign's `@everyone` role, and every member of a direct or
+    /// group conversation, may do.
+    pub const DEFAULT: Self = Self(Self::VIEW_CHANNEL.0 | Self::SEND_MESSAGES.0 | Self::MENTION_EVERYONE.0);
+
+    /// Build from stored bits, dropping any this version doesn't know about.
+    pub const fn from_bits(bits: i64) -> Self {
+        Self(bits as u64 & Self::ALL.0)
+    }
+
+    /// The bits as stored in Postgres.
+    pub const fn bits(self) -> i64 {
+        self.0 as i64
+    }
+
+    /// Whether every permission in `other` is set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for Permissions {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Permissions {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for Permissions {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl Not for Permissions {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0 & Self::ALL.0)
+    }
+}
+
+/// A channel's adjustment for one role or member: `deny` bits are removed,
+/// then `allow` bits are added.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Overwrite {
+    pub allow: Permissions,
+    pub deny: Permissions,
+}
+
+impl Overwrite {
+    fn apply(self, permissions: Permissions) -> Permissions {
+        (permissions & !self.deny) | self.allow
+    }
+}
+
+/// The overwrites on one channel that apply to one member.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemberOverwrites {
+    /// The overwrite for the Reign's `@everyone` role.
+    pub everyone: Option<Overwrite>,
+    /// Overwrites for the other roles the member has.
+    pub roles: Vec<Overwrite>,
+    /// The overwrite for the member themselves.
+    pub member: Option<Overwrite>,
+}
+
+/// A member's Reign-wide permissions: everything for the owner, otherwise
+/// the union of `@everyone` and their other roles. `ADMINISTRATOR` grants
+/// everything.
+pub fn base_permissions(is_owner: bool, everyone: Permissions, roles: &[Permissions]) -> Permissions {
+    if is_owner {
+        return Permissions::ALL;
+    }
+    let permissions = roles.iter().fold(everyone, |acc, role| acc | *role);
+    if permissions.contains(Permissions::ADMINISTRATOR) {
+        Permissions::ALL
+    } else {
+        permissions
+    }
+}
+
+/// A member's permissions in one channel.
+///
+/// Administrators ignore overwrites. Everyone else has overwrites applied from
+/// least to most specific: `@everyone`, then all of their roles' overwrites
+/// together (denies before allows, so any role allowing a bit wins over another
+/// denying it), then the member's own overwrite. Without `VIEW_CHANNEL` the
+/// member can do nothing in the channel.
+pub fn channel_permissions(base: Permissions, overwrites: &MemberOverwrites) -> Permissions {
+    if base.contains(Permissions::ADMINISTRATOR) {
+        return Permissions::ALL;
+    }
+
+    let mut permissions = base;
+    if let Some(everyone) = overwrites.everyone {
+        permissions = everyone.apply(permissions);
+    }
+    let roles = overwrites.roles.iter().fold(Overwrite::default(), |acc, o| Overwrite {
+        allow: acc.allow | o.allow,
+        deny: acc.deny | o.deny,
+    });
+    permissions = roles.apply(permissions);
+    if let Some(member) = overwrites.member {
+        permissions = member.apply(permissions);
+    }
+
+    if permissions.contains(Permissions::VIEW_CHANNEL) {
+        permissions
+    } else {
+        Permissions::NONE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIEW: Permissions = Permissions::VIEW_CHANNEL;
+    const SEND: Permissions = Permissions::SEND_MESSAGES;
+
+    fn allow(p: Permissions) -> Overwrite {
+        Overwrite { allow: p, deny: Permissions::NONE }
+    }
+
+    fn deny(p: Permissions) -> Overwrite {
+        Overwrite { allow: Permissions::NONE, deny: p }
+    }
+
+    #[test]
+    fn owner_has_everything() {
+        assert_eq!(base_permissions(true, Permissions::NONE, &[]), Permissions::ALL);
+    }
+
+    #[test]
+    fn roles_are_combined_with_everyone() {
+        let base = base_permissions(false, VIEW, &[SEND, Permissions::KICK_MEMBERS]);
+        assert_eq!(base, VIEW | SEND | Permissions::KICK_MEMBERS);
+    }
+
+    #[test]
+    fn administrator_grants_everything_and_ignores_overwrites() {
+        let base = base_permissions(false, Permissions::DEFAULT, &[Permissions::ADMINISTRATOR]);
+        assert_eq!(base, Permissions::ALL);
+
+        let overwrites = MemberOverwrites {
+            everyone: Some(deny(VIEW)),
+            roles: vec![],
+            member: Some(deny(Permissions::ALL)),
+        };
+        assert_eq!(channel_permissions(base, &overwrites), Permissions::ALL);
+    }
+
+    #[test]
+    fn no_overwrites_keeps_base() {
+        let base = Permissions::DEFAULT;
+        assert_eq!(channel_permissions(base, &MemberOverwrites::default()), base);
+    }
+
+    #[test]
+    fn everyone_deny_hides_channel() {
+        let overwrites = MemberOverwrites { everyone: Some(deny(VIEW)), ..Default::default() };
+        assert_eq!(channel_permissions(Permissions::DEFAULT, &overwrites), Permissions::NONE);
+    }
+
+    #[test]
+    fn role_allow_beats_everyone_deny() {
+        let overwrites = MemberOverwrites {
+            everyone: Some(deny(VIEW | SEND)),
+            roles: vec![allow(VIEW)],
+            member: None,
+        };
+        assert_eq!(channel_permissions(Permissions::DEFAULT, &overwrites), VIEW | Permissions::MENTION_EVERYONE);
+    }
+
+    #[test]
+    fn role_allow_beats_another_roles_deny() {
+        let overwrites = MemberOverwrites {
+            everyone: None,
+            roles: vec![deny(SEND), allow(SEND)],
+            member: None,
+        };
+        assert!(channel_permissions(VIEW, &overwrites).contains(SEND));
+    }
+
+    #[test]
+    fn member_overwrite_beats_roles() {
+        let overwrites = MemberOverwrites {
+            everyone: Some(allow(SEND)),
+            roles: vec![allow(SEND)],
+            member: Some(deny(SEND)),
+        };
+        assert_eq!(channel_permissions(VIEW, &overwrites), VIEW);
+
+        let overwrites = MemberOverwrites {
+            everyone: Some(deny(VIEW)),
+            roles: vec![deny(VIEW)],
+            member: Some(allow(VIEW)),
+        };
+        assert_eq!(channel_permissions(VIEW, &overwrites), VIEW);
+    }
+
+    #[test]
+    fn no_view_means_nothing_else() {
+        let overwrites = MemberOverwrites { everyone: Some(deny(VIEW)), ..Default::default() };
+        let base = VIEW | SEND | Permissions::MANAGE_MESSAGES;
+        assert_eq!(channel_permissions(base, &overwrites), Permissions::NONE);
+    }
+
+    #[test]
+    fn unknown_bits_are_dropped() {
+        assert_eq!(Permissions::from_bits(-1), Permissions::ALL);
+        assert_eq!(Permissions::from_bits(Permissions::DEFAULT.bits()), Permissions::DEFAULT);
+    }
+}
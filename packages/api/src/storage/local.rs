@@ -0,0 +1,104 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tokio_util::io::ReaderStream;
+
+use super::{check_key, BlobStore, ByteStream};
+
+/// Blobs as files on the local filesystem, fanned out into subdirectories by
+/// the first two hex digits of their key.
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("BLOB_DIR").unwrap_or_else(|_| "./data/blobs".to_string()))
+    }
+
+    fn path(&self, key: &str) -> io::Result<PathBuf> {
+        check_key(key)?;
+        Ok(self.root.join(&key[..2]).join(key))
+    }
+}
+
+#[async_trait::async_trait]
+impl BlobStore for LocalBlobStore {
+    async fn put_file(&self, key: &str, path: &Path, _size: u64, _content_type: &str) -> io::Result<()> {
+        let target = self.path(key)?;
+        if let Some(dir) = target.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+
+        // Copy to a sibling temp file first so readers never see a partial blob
+        let partial = target.with_extension(format!("{}.partial", uuid::Uuid::new_v4()));
+        tokio::fs::copy(path, &partial).await?;
+        tokio::fs::rename(&partial, &target).await
+    }
+
+    async fn get(&self, key: &str) -> io::Result<ByteStream> {
+        let file = tokio::fs::File::open(self.path(key)?).await?;
+        Ok(Box::pin(ReaderStream::new(file)))
+    }
+
+    async fn delete(&self, key: &str) -> io::Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+
+    async fn read(store: &LocalBlobStore, key: &str) -> io::Result<Vec<u8>> {
+        let mut stream = store.get(key).await?;
+        let mut bytes = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            bytes.extend_from_slice(&chunk?);
+        }
+        Ok(bytes)
+    }
+
+    #[tokio::test]
+    async fn puts_gets_and_deletes_blobs() {
+        let root = std::env::temp_dir().join(format!("reigncloud-blobs-test-{}", uuid::Uuid::new_v4()));
+        let store = LocalBlobStore::new(&root);
+        let key = "ab".repeat(32);
+        let source = root.with_extension("source");
+
+        tokio::fs::write(&source, b"first").await.unwrap();
+        store.put_file(&key, &source, 5, "text/plain").await.unwrap();
+        assert_eq!(read(&store, &key).await.unwrap(), b"first");
+        assert!(root.join("ab").join(&key).is_file());
+
+        // Writing the same key again replaces the blob
+        tokio::fs::write(&source, b"second").await.unwrap();
+        store.put_file(&key, &source, 6, "text/plain").await.unwrap();
+        assert_eq!(read(&store, &key).await.unwrap(), b"second");
+
+        store.delete(&key).await.unwrap();
+        assert_eq!(read(&store, &key).await.unwrap_err().kind(), io::ErrorKind::NotFound);
+        store.delete(&key).await.unwrap();
+
+        let _ = tokio::fs::remove_file(&source).await;
+        let _ = tokio::fs::remove_dir_all(&root).await;
+    }
+
+    #[tokio::test]
+    async fn refuses_keys_that_are_not_digests() {
+        let store = LocalBlobStore::new(std::env::temp_dir());
+        for key in ["../../etc/passwd", "AB".repeat(32).as_str(), "abc"] {
+            assert_eq!(read(&store, key).await.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+            assert_eq!(store.delete(key).await.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Content-addressed blob storage for attachments.
+//!
+//! Blobs are stored under their SHA-256 hex digest, so writing the same key
+//! twice is harmless. The backend is chosen once from the environment:
+//!
+//! - `BLOB_STORE=local` (default): files under `BLOB_DIR` (default `./data/blobs`).
+//! - `BLOB_STORE=s3`: an S3-compatible bucket, addressed path-style so MinIO
+//!   and other stand-ins work. Needs `S3_ENDPOINT`, `S3_BUCKET`,
+//!   `S3_ACCESS_KEY` and `S3_SECRET_KEY`; `S3_REGION` defaults to `us-east-1`.
+
+mod local;
+mod s3;
+
+pub use local::LocalBlobStore;
+pub use s3::S3BlobStore;
+
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::OnceLock;
+
+use bytes::Bytes;
+use futures_util::Stream;
+
+/// A blob's contents, read incrementally.
+pub type ByteStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>;
+
+#[async_trait::async_trait]
+pub trait BlobStore: Send + Sync {
+    /// Store the file at `path` under `key`, replacing anything already there.
+    async fn put_file(&self, key: &str, path: &Path, size: u64, content_type: &str) -> io::Result<()>;
+
+    /// Stream the blob stored under `key`. Missing blobs are `ErrorKind::NotFound`.
+    async fn get(&self, key: &str) -> io::Result<ByteStream>;
+
+    /// Remove the blob under `key`. Removing a missing blob succeeds.
+    async fn delete(&self, key: &str) -> io::Result<()>;
+}
+
+static BLOB_STORE: OnceLock<Box<dyn BlobStore>> = OnceLock::new();
+
+/// The configured blob store.
+pub fn blob_store() -> &'static dyn BlobStore {
+    BLOB_STORE
+        .get_or_init(|| {
+            dotenvy::dotenv().ok();
+            match std::env::var("BLOB_STORE").as_deref() {
+                Ok("s3") => Box::new(S3BlobStore::from_env()),
+                Ok("local") | Err(_) => Box::new(LocalBlobStore::from_env()),
+                Ok(other) => panic!("Unknown BLOB_STORE {other:?}; expected local or s3"),
+            }
+        })
+        .as_ref()
+}
+
+/// Keys are SHA-256 hex digests; anything else could escape the store's namespace.
+fn check_key(key: &str) -> io::Result<()> {
+    if key.len() == 64 && key.bytes().all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase()) {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid blob key {key:?}")))
+    }
+}
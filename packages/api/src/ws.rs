@@ -1,4 +1,6 @@
+use std::collections::{HashMap, VecDeque};
 use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
 use axum::{
     extract::{ws::{Message, WebSocket, WebSocketUpgrade}, Query},
@@ -10,15 +12,113 @@ use serde::Deserialize;
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
+use crate::events::{ClientCommand, ServerFrame, TypingIndicator, WsEvent};
+
 type Sender = mpsc::UnboundedSender<String>;
 type Connections = DashMap<Uuid, Vec<Sender>>;
 
+/// Maximum number of unacknowledged frames kept per user for redelivery.
+const MAX_PENDING: usize = 500;
+/// How long a disconnected device keeps its place in the outbox, and how long
+/// frames wait for a user with no known device.
+const OUTBOX_IDLE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Longest device id a client may connect with.
+const MAX_DEVICE_ID_LEN: usize = 64;
+
+struct PendingFrame {
+    seq: u64,
+    frame: String,
+    /// `(message_id, sender_id)` when this frame delivers someone else's
+    /// message, until the first device acknowledges it.
+    delivery: Option<(Uuid, Uuid)>,
+}
+
+/// How far one device has got through its user's outbox.
+struct DeviceCursor {
+    /// Highest sequence number the device acknowledged.
+    acked: u64,
+    /// Sockets the device has open.
+    sockets: usize,
+    /// When the device last connected, disconnected or acknowledged a frame.
+    seen_at: Instant,
+}
+
+/// Per-user queue of frames awaiting acknowledgement, with a cursor for every
+/// device. A frame is kept until each known device acknowledged it, so a
+/// device that was offline still gets it redelivered when it reconnects.
+///
+/// Sequence numbers start at the current time in microseconds, so they keep
+/// increasing across server restarts and clients never mistake new frames for
+/// ones they have already seen.
+struct Outbox {
+    next_seq: u64,
+    pending: VecDeque<PendingFrame>,
+    devices: HashMap<String, DeviceCursor>,
+    created_at: Instant,
+}
+
+impl Default for Outbox {
+    fn default() -> Self {
+        Self {
+            next_seq: chrono::Utc::now().timestamp_micros().max(0) as u64,
+            pending: VecDeque::new(),
+            devices: HashMap::new(),
+            created_at: Instant::now(),
+        }
+    }
+}
+
+impl Outbox {
+    /// The frames to send a device reconnecting with its cursor at `acked`:
+    /// everything it hasn't acknowledged, led by a resync notice when frames
+    /// past its cursor were already dropped. A cursor of 0 never saw a frame,
+    /// so it can't have missed any.
+    fn replay(&self, acked: u64) -> Vec<String> {
+        let first_held = self.pending.front().map_or(self.next_seq + 1, |p| p.seq);
+        let mut frames = Vec::new();
+        if acked != 0 && acked + 1 < first_held {
+            if let Ok(frame) = serde_json::to_string(&ServerFrame { seq: None, event: WsEvent::Resync }) {
+                frames.push(frame);
+            }
+        }
+        frames.extend(self.pending.iter().filter(|p| p.seq > acked).map(|p| p.frame.clone()));
+        frames
+    }
+
+    /// Drop frames every known device has acknowledged. Without any known
+    /// device, frames wait for the first one to connect.
+    fn release_acknowledged(&mut self) {
+        let Some(floor) = self.devices.values().map(|d| d.acked).min() else {
+            return;
+        };
+        while self.pending.front().is_some_and(|p| p.seq <= floor) {
+            self.pending.pop_front();
+        }
+    }
+}
+
+/// How long a typing indicator stays visible without being refreshed.
+const TYPING_TTL: Duration = Duration::from_secs(5);
+/// Minimum gap between relayed "started typing" frames for one conversation.
+const TYPING_RELAY_INTERVAL: Duration = Duration::from_secs(2);
+
 static WS_CONNECTIONS: OnceLock<Connections> = OnceLock::new();
+static WS_OUTBOXES: OnceLock<DashMap<Uuid, Outbox>> = OnceLock::new();
+/// When each `(typist, conversation)` pair last had a "started typing" frame relayed.
+static TYPING_RELAYED: OnceLock<DashMap<(Uuid, Uuid), Instant>> = OnceLock::new();
 
 fn connections() -> &'static Connections {
     WS_CONNECTIONS.get_or_init(DashMap::new)
 }
 
+fn outboxes() -> &'static DashMap<Uuid, Outbox> {
+    WS_OUTBOXES.get_or_init(DashMap::new)
+}
+
+fn typing_relayed() -> &'static DashMap<(Uuid, Uuid), Instant> {
+    TYPING_RELAYED.get_or_init(DashMap::new)
+}
+
 /// Broadcast a JSON message to a specific user's open WebSocket connections.
 pub fn broadcast_to_user(user_id: Uuid, message: &str) {
     if let Some(mut senders) = connections().get_mut(&user_id) {
@@ -26,9 +126,142 @@ pub fn broadcast_to_user(user_id: Uuid, message: &str) {
     }
 }
 
+/// Assign an event the user's next sequence number, queue it until acknowledged,
+/// and broadcast it to the user's open WebSocket connections.
+pub fn send_event(user_id: Uuid, event: &WsEvent) {
+    let delivery = match event {
+        WsEvent::MessageCreated(m) if m.sender_id != user_id.to_string() => {
+            m.id.parse().ok().zip(m.sender_id.parse().ok())
+        }
+        _ => None,
+    };
+
+    let mut outbox = outboxes().entry(user_id).or_default();
+    outbox.next_seq += 1;
+    let seq = outbox.next_seq;
+    let Ok(frame) = serde_json::to_string(&ServerFrame { seq: Some(seq), event: event.clone() }) else {
+        return;
+    };
+
+    outbox.pending.push_back(PendingFrame { seq, frame: frame.clone(), delivery });
+    if outbox.pending.len() > MAX_PENDING {
+        outbox.pending.pop_front();
+    }
+    drop(outbox);
+
+    broadcast_to_user(user_id, &frame);
+}
+
+/// Broadcast an event without a sequence number. It is not queued, acknowledged
+/// or redelivered, so use this only for state that is stale a few seconds later.
+pub fn send_ephemeral(user_id: Uuid, event: &WsEvent) {
+    if let Ok(frame) = serde_json::to_string(&ServerFrame { seq: None, event: event.clone() }) {
+        broadcast_to_user(user_id, &frame);
+    }
+}
+
+/// Relay a typing notification to the other members' sockets. "Started" frames
+/// are rate-limited per conversation and need permission to send messages;
+/// "stopped" frames are only relayed if a start is still live on the other side.
+///
+/// Members and permissions come from the caches alone, which loading a
+/// conversation's history fills, so a flood of frames never reaches the
+/// database. Frames for conversations missing from the caches are dropped.
+fn relay_typing(user_id: Uuid, conversation_id: Uuid, typing: bool) {
+    let key = (user_id, conversation_id);
+    let now = Instant::now();
+
+    if typing && typing_relayed().get(&key).is_some_and(|last| now.duration_since(*last) < TYPING_RELAY_INTERVAL) {
+        return;
+    }
+    let Some(members) = crate::features::conversations::cached_member_ids(conversation_id) else {
+        return;
+    };
+    if !members.contains(&user_id) {
+        return;
+    }
+
+    if typing {
+        let send = crate::permissions::Permissions::SEND_MESSAGES;
+        if !crate::features::roles::cached_permissions(conversation_id, user_id).is_some_and(|p| p.contains(send)) {
+            return;
+        }
+        typing_relayed().insert(key, now);
+    } else {
+        match typing_relayed().remove(&key) {
+            Some((_, last)) if now.duration_since(last) < TYPING_TTL => {}
+            _ => return,
+        }
+    }
+
+    let event = WsEvent::Typing(TypingIndicator {
+        conversation_id: conversation_id.to_string(),
+        user_id: user_id.to_string(),
+        typing,
+        expires_in_ms: TYPING_TTL.as_millis() as u64,
+    });
+    for member in members.iter().filter(|m| **m != user_id) {
+        send_ephemeral(*member, &event);
+    }
+}
+
+/// Advance `device`'s cursor to `seq`, mark the messages the acknowledged
+/// frames carried as delivered, and drop frames no device still needs.
+async fn acknowledge(user_id: Uuid, device: &str, seq: u64) {
+    let mut delivered = Vec::new();
+    if let Some(mut outbox) = outboxes().get_mut(&user_id) {
+        let Some(cursor) = outbox.devices.get_mut(device) else {
+            return;
+        };
+        cursor.acked = cursor.acked.max(seq);
+        cursor.seen_at = Instant::now();
+
+        // The first device to receive a message delivers it
+        for pending in outbox.pending.iter_mut().take_while(|p| p.seq <= seq) {
+            delivered.extend(pending.delivery.take());
+        }
+        outbox.release_acknowledged();
+    }
+
+    if delivered.is_empty() {
+        return;
+    }
+
+    let pool = crate::db::pool().await;
+    for (message_id, sender_id) in delivered {
+        if let Err(e) = crate::features::messages::mark_delivered(pool, message_id, sender_id, user_id).await {
+            println!("mark_delivered failed for message {message_id}: {e}");
+        }
+    }
+}
+
+/// Forget devices that have been disconnected for [`OUTBOX_IDLE_TTL`], and
+/// drop the outboxes of users left with no known device once they are that
+/// old. Returns how many outboxes were dropped.
+pub fn prune_outboxes() -> usize {
+    let now = Instant::now();
+    let before = outboxes().len();
+    outboxes().retain(|_, outbox| {
+        outbox.devices.retain(|_, d| d.sockets > 0 || now.duration_since(d.seen_at) < OUTBOX_IDLE_TTL);
+        outbox.release_acknowledged();
+        !outbox.devices.is_empty() || now.duration_since(outbox.created_at) < OUTBOX_IDLE_TTL
+    });
+    before - outboxes().len()
+}
+
 #[derive(Deserialize)]
 pub struct WsParams {
     pub token: String,
+    /// Identifies the client across reconnects so it is redelivered exactly
+    /// the frames it missed. Connections without one only get frames sent
+    /// while they are open.
+    #[serde(default)]
+    pub device_id: Option<String>,
+    /// The highest sequence number the client processed, so a device the
+    /// server doesn't know, after a restart or a long absence, still learns
+    /// whether it missed frames.
+    #[serde(default)]
+    pub last_seq: Option<u64>,
 }
 
 /// Axum handler for WebSocket upgrade.
@@ -60,16 +293,43 @@ pub async fn ws_handler(
         }
     };
 
-    ws.on_upgrade(move |socket| handle_socket(socket, user_id))
+    let device_id = params.device_id.filter(|id| !id.is_empty() && id.len() <= MAX_DEVICE_ID_LEN);
+
+    let last_seq = params.last_seq.unwrap_or(0);
+
+    ws.on_upgrade(move |socket| handle_socket(socket, user_id, device_id, last_seq))
         .into_response()
 }
 
-async fn handle_socket(socket: WebSocket, user_id: Uuid) {
+/// A connection without a device id gets a cursor of its own, forgotten once
+/// it closes.
+async fn handle_socket(socket: WebSocket, user_id: Uuid, device_id: Option<String>, last_seq: u64) {
     let (mut ws_sender, mut ws_receiver) = socket.split();
     let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+    let remembered = device_id.is_some();
+    let device = device_id.unwrap_or_else(|| Uuid::new_v4().to_string());
 
-    // Register this connection
-    connections().entry(user_id).or_default().push(tx);
+    // Replay the frames this device hasn't acknowledged, then register this
+    // connection. The outbox stays locked throughout so no new frame can slip
+    // in between the two. A device seen for the first time picks up from the
+    // last frame it processed, or else gets every frame still waiting, as
+    // those were sent while no device was listening.
+    {
+        let mut outbox = outboxes().entry(user_id).or_default();
+        let next_seq = outbox.next_seq;
+        let cursor = outbox.devices.entry(device.clone()).or_insert_with(|| DeviceCursor {
+            acked: if remembered { last_seq } else { next_seq },
+            sockets: 0,
+            seen_at: Instant::now(),
+        });
+        cursor.sockets += 1;
+        cursor.seen_at = Instant::now();
+        let acked = cursor.acked;
+        for frame in outbox.replay(acked) {
+            let _ = tx.send(frame);
+        }
+        connections().entry(user_id).or_default().push(tx);
+    }
 
     println!("WebSocket connected: user {user_id}");
 
@@ -82,12 +342,14 @@ async fn handle_socket(socket: WebSocket, user_id: Uuid) {
         }
     });
 
-    // Task: read from WebSocket (handle pings, keep-alive)
+    // Task: read from WebSocket (client commands, pings, keep-alive)
+    let recv_device = device.clone();
     let recv_task = tokio::spawn(async move {
         while let Some(Ok(msg)) = ws_receiver.next().await {
             match msg {
+                Message::Text(text) => handle_command(user_id, &recv_device, &text).await,
                 Message::Close(_) => break,
-                _ => {} // ignore other incoming messages for now
+                _ => {}
             }
         }
     });
@@ -99,9 +361,108 @@ async fn handle_socket(socket: WebSocket, user_id: Uuid) {
     }
 
     // Clean up: remove closed senders for this user
-    if let Some(mut senders) = connections().get_mut(&user_id) {
-        senders.retain(|tx| !tx.is_closed());
+    let still_connected = match connections().get_mut(&user_id) {
+        Some(mut senders) => {
+            senders.retain(|tx| !tx.is_closed());
+            !senders.is_empty()
+        }
+        None => false,
+    };
+
+    if let Some(mut outbox) = outboxes().get_mut(&user_id) {
+        if let Some(cursor) = outbox.devices.get_mut(&device) {
+            cursor.sockets -= 1;
+            cursor.seen_at = Instant::now();
+            if cursor.sockets == 0 && !remembered {
+                outbox.devices.remove(&device);
+                outbox.release_acknowledged();
+            }
+        }
+    }
+
+    // A user with no sockets left can't still be typing
+    if !still_connected {
+        typing_relayed().retain(|(typist, _), _| *typist != user_id);
     }
 
     println!("WebSocket disconnected: user {user_id}");
 }
+
+/// Dispatch a command sent by the client over its socket.
+async fn handle_command(user_id: Uuid, device: &str, text: &str) {
+    let command = match serde_json::from_str::<ClientCommand>(text) {
+        Ok(c) => c,
+        Err(e) => {
+            println!("Ignoring malformed WebSocket command from user {user_id}: {e}");
+            return;
+        }
+    };
+
+    match command {
+        ClientCommand::MarkRead { conversation_id, message_id } => {
+            use crate::features::conversations;
+
+            let (Ok(conversation_id), Ok(message_id)) = (conversation_id.parse(), message_id.parse()) else {
+                return;
+            };
+            let pool = crate::db::pool().await;
+            let view = crate::permissions::Permissions::VIEW_CHANNEL;
+            let result = match crate::features::roles::require_permission(pool, conversation_id, user_id, view).await {
+                Ok(_) => conversations::mark_read(pool, user_id, conversation_id, message_id).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                println!("mark_read failed for user {user_id}: {e}");
+            }
+        }
+        ClientCommand::Ack { seq } => acknowledge(user_id, device, seq).await,
+        ClientCommand::Typing { conversation_id, typing } => {
+            if let Ok(conversation_id) = conversation_id.parse() {
+                relay_typing(user_id, conversation_id, typing);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outbox(next_seq: u64, held: std::ops::RangeInclusive<u64>) -> Outbox {
+        let pending = held
+            .map(|seq| PendingFrame { seq, frame: format!("frame {seq}"), delivery: None })
+            .collect();
+        Outbox { next_seq, pending, devices: HashMap::new(), created_at: Instant::now() }
+    }
+
+    fn is_resync(frame: &str) -> bool {
+        serde_json::from_str::<ServerFrame>(frame).is_ok_and(|f| f.seq.is_none() && f.event == WsEvent::Resync)
+    }
+
+    #[test]
+    fn reconnecting_replays_what_was_not_acknowledged() {
+        let frames = outbox(110, 101..=110).replay(105);
+        assert_eq!(frames, (106..=110).map(|seq| format!("frame {seq}")).collect::<Vec<_>>());
+        assert!(outbox(110, 101..=110).replay(110).is_empty());
+    }
+
+    #[test]
+    fn frames_dropped_past_the_cursor_trigger_a_resync() {
+        // 101..=105 overflowed while the device was away
+        let frames = outbox(110, 106..=110).replay(100);
+        assert!(is_resync(&frames[0]));
+        assert_eq!(frames.len(), 6);
+
+        // A restart loses everything queued; the client's last frame is behind
+        let frames = outbox(5_000, 1..=0).replay(110);
+        assert_eq!(frames.len(), 1);
+        assert!(is_resync(&frames[0]));
+    }
+
+    #[test]
+    fn a_device_that_never_saw_a_frame_gets_what_is_held() {
+        let frames = outbox(110, 106..=110).replay(0);
+        assert_eq!(frames.len(), 5);
+        assert!(!frames.iter().any(|f| is_resync(f)));
+    }
+}
@@ -1,7 +1,7 @@
 use dioxus::prelude::*;
 
 use ui::Navbar;
-use views::{Blog, Home};
+use views::{Blog, Home, Reign, ReignChannel, Reigns};
 
 mod views;
 
@@ -13,6 +13,12 @@ enum Route {
     Home {},
     #[route("/blog/:id")]
     Blog { id: i32 },
+    #[route("/reigns")]
+    Reigns {},
+    #[route("/reigns/:reign_id")]
+    Reign { reign_id: String },
+    #[route("/reigns/:reign_id/:channel_id")]
+    ReignChannel { reign_id: String, channel_id: String },
 }
 
 const MAIN_CSS: Asset = asset!("/assets/main.css");
@@ -24,6 +30,7 @@ fn main() {
 #[component]
 fn App() -> Element {
     // Build cool things ✌️
+    ui::use_session_provider();
 
     rsx! {
         // Global app resources
@@ -47,6 +54,10 @@ fn DesktopNavbar() -> Element {
                 to: Route::Blog { id: 1 },
                 "Blog"
             }
+            Link {
+                to: Route::Reigns {},
+                "Reigns"
+            }
         }
 
         Outlet::<Route> {}
@@ -0,0 +1,46 @@
+use crate::Route;
+use dioxus::prelude::*;
+use ui::{ReignList, ReignView};
+
+#[component]
+pub fn Reigns() -> Element {
+    rsx! {
+        ReignList {
+            on_open: move |reign_id| {
+                navigator().push(Route::Reign { reign_id });
+            },
+        }
+    }
+}
+
+#[component]
+pub fn Reign(reign_id: String) -> Element {
+    rsx! {
+        ReignView {
+            reign_id: reign_id.clone(),
+            channel_id: None,
+            on_open_channel: move |channel_id| {
+                navigator().push(Route::ReignChannel { reign_id: reign_id.clone(), channel_id });
+            },
+            on_left: move |_| {
+                navigator().push(Route::Reigns {});
+            },
+        }
+    }
+}
+
+#[component]
+pub fn ReignChannel(reign_id: String, channel_id: String) -> Element {
+    rsx! {
+        ReignView {
+            reign_id: reign_id.clone(),
+            channel_id: Some(channel_id),
+            on_open_channel: move |channel_id| {
+                navigator().push(Route::ReignChannel { reign_id: reign_id.clone(), channel_id });
+            },
+            on_left: move |_| {
+                navigator().push(Route::Reigns {});
+            },
+        }
+    }
+}
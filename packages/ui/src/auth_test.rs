@@ -1,24 +1,26 @@
 use dioxus::prelude::*;
 
-use crate::use_websocket;
+use crate::{use_session, use_websocket, ReactionBar, TypingIndicator};
 
 /// Temporary smoke-test component for auth + messages + WebSocket.
 #[component]
 pub fn AuthTest() -> Element {
     // Auth state
-    let mut email = use_signal(|| String::new());
-    let mut username = use_signal(|| String::new());
-    let mut password = use_signal(|| String::new());
-    let mut token = use_signal(|| String::new());
-    let mut result_text = use_signal(|| String::new());
+    let mut email = use_signal(String::new);
+    let mut username = use_signal(String::new);
+    let mut password = use_signal(String::new);
+    let mut token = use_session().token;
+    let mut result_text = use_signal(String::new);
 
     // Message state
-    let mut recipient_id = use_signal(|| String::new());
-    let mut message_content = use_signal(|| String::new());
-    let mut message_id = use_signal(|| String::new());
+    let mut recipient_id = use_signal(String::new);
+    let mut conversation_id = use_signal(String::new);
+    let mut group_name = use_signal(String::new);
+    let mut message_content = use_signal(String::new);
+    let mut message_id = use_signal(String::new);
 
     // WebSocket: real-time incoming messages
-    let ws_messages = use_websocket(token);
+    let mut ws = use_websocket(token);
 
     let handle_register = move |_| async move {
         let req = api::features::users::register::RegisterRequest {
@@ -55,15 +57,21 @@ pub fn AuthTest() -> Element {
         }
     };
 
-    let handle_send = move |_| async move {
+    // Sends a new message, or a reply to the message in the Message ID field
+    let handle_send = move |reply: bool| async move {
+        ws.stop_typing(&conversation_id());
         let req = api::features::messages::create::CreateMessageRequest {
             token: token(),
-            recipient_id: recipient_id(),
+            conversation_id: non_empty(conversation_id()),
+            recipient_id: non_empty(recipient_id()),
             content: message_content(),
+            attachment_ids: Vec::new(),
+            reply_to_id: if reply { Some(message_id()) } else { None },
         };
         match api::create_message(req).await {
             Ok(msg) => {
                 message_id.set(msg.id.clone());
+                conversation_id.set(msg.conversation_id.clone());
                 result_text.set(format!("Message sent! id: {}", msg.id));
             }
             Err(e) => result_text.set(format!("Send failed: {e}")),
@@ -73,15 +81,26 @@ pub fn AuthTest() -> Element {
     let handle_list = move |_| async move {
         let req = api::features::messages::list::ListMessagesRequest {
             token: token(),
-            other_user_id: recipient_id(),
+            conversation_id: non_empty(conversation_id()),
+            other_user_id: non_empty(recipient_id()),
         };
         match api::list_messages(req).await {
             Ok(msgs) => {
                 let mut out = format!("Found {} messages:\n\n", msgs.len());
                 for m in &msgs {
                     out.push_str(&format!(
-                        "[{}] {} -> {}: {}\n",
-                        m.created_at, m.sender_id, m.recipient_id, m.content
+                        "[{}] {} -> {}: {}{}\n",
+                        m.created_at,
+                        m.sender_id,
+                        m.recipient_id.as_deref().unwrap_or("group"),
+                        if m.deleted_at.is_some() { "(deleted)" } else { &m.content },
+                        if m.read {
+                            " (seen)"
+                        } else if m.delivered {
+                            " (delivered)"
+                        } else {
+                            ""
+                        }
                     ));
                 }
                 result_text.set(out);
@@ -90,6 +109,41 @@ pub fn AuthTest() -> Element {
         }
     };
 
+    let handle_inbox = move |_| async move {
+        let req = api::features::conversations::list::ListConversationsRequest {
+            token: token(),
+            limit: None,
+            before: None,
+        };
+        match api::list_conversations(req).await {
+            Ok(convos) => {
+                let mut out = format!("Found {} conversations:\n\n", convos.len());
+                for c in &convos {
+                    out.push_str(&format!(
+                        "[{}] {}{} ({}) unread: {}\n  {}\n",
+                        c.last_message_at,
+                        c.title,
+                        if c.is_group { format!(" [{} members]", c.member_count) } else { String::new() },
+                        c.conversation_id,
+                        c.unread_count,
+                        c.last_message_preview.as_deref().unwrap_or("(no messages)")
+                    ));
+                }
+                result_text.set(out);
+            }
+            Err(e) => result_text.set(format!("Inbox failed: {e}")),
+        }
+    };
+
+    // Mark the conversation read up to the message in the Message ID field, over the socket
+    let handle_mark_read = move |_| {
+        ws.send(&api::events::ClientCommand::MarkRead {
+            conversation_id: conversation_id(),
+            message_id: message_id(),
+        });
+        result_text.set("Mark-read sent over WebSocket".to_string());
+    };
+
     let handle_update = move |_| async move {
         let req = api::features::messages::update::UpdateMessageRequest {
             token: token(),
@@ -104,10 +158,45 @@ pub fn AuthTest() -> Element {
         }
     };
 
-    let handle_delete = move |_| async move {
+    let handle_history = move |_| async move {
+        let req = api::features::messages::history::MessageEditsRequest {
+            token: token(),
+            message_id: message_id(),
+        };
+        match api::list_message_edits(req).await {
+            Ok(edits) => {
+                let mut out = format!("{} previous versions:\n\n", edits.len());
+                for e in &edits {
+                    out.push_str(&format!("[{}] {}\n", e.edited_at, e.content));
+                }
+                result_text.set(out);
+            }
+            Err(e) => result_text.set(format!("History failed: {e}")),
+        }
+    };
+
+    let handle_thread = move |_| async move {
+        let req = api::features::messages::thread::ThreadRepliesRequest {
+            token: token(),
+            message_id: message_id(),
+        };
+        match api::list_thread_replies(req).await {
+            Ok(replies) => {
+                let mut out = format!("{} replies:\n\n", replies.len());
+                for m in &replies {
+                    out.push_str(&format!("[{}] {}: {}\n", m.created_at, m.sender_id, m.content));
+                }
+                result_text.set(out);
+            }
+            Err(e) => result_text.set(format!("Thread failed: {e}")),
+        }
+    };
+
+    let handle_delete = move |for_everyone: bool| async move {
         let req = api::features::messages::delete::DeleteMessageRequest {
             token: token(),
             message_id: message_id(),
+            for_everyone,
         };
         match api::delete_message(req).await {
             Ok(_) => result_text.set("Message deleted!".to_string()),
@@ -115,6 +204,99 @@ pub fn AuthTest() -> Element {
         }
     };
 
+    let handle_reaction = move |message_id: String, emoji: String, add: bool| async move {
+        let req = api::features::reactions::add::ReactionRequest {
+            token: token(),
+            message_id,
+            emoji,
+        };
+        let result = if add {
+            api::add_reaction(req).await
+        } else {
+            api::remove_reaction(req).await
+        };
+        if let Err(e) = result {
+            result_text.set(format!("Reaction failed: {e}"));
+        }
+    };
+
+    // Group management: members come from the Recipient field (comma-separated for create)
+    let handle_create_group = move |_| async move {
+        let req = api::features::conversations::create_group::CreateGroupRequest {
+            token: token(),
+            name: group_name(),
+            member_ids: recipient_id().split(',').map(|id| id.trim().to_string()).filter(|id| !id.is_empty()).collect(),
+            avatar_url: None,
+        };
+        match api::create_group(req).await {
+            Ok(group) => {
+                conversation_id.set(group.conversation_id.clone());
+                result_text.set(format!("Group created! id: {}", group.conversation_id));
+            }
+            Err(e) => result_text.set(format!("Create group failed: {e}")),
+        }
+    };
+
+    let handle_membership = move |add: bool| async move {
+        let req = api::features::conversations::add_member::MemberRequest {
+            token: token(),
+            conversation_id: conversation_id(),
+            user_id: recipient_id(),
+        };
+        let result = if add {
+            api::add_member(req).await
+        } else {
+            api::remove_member(req).await
+        };
+        match result {
+            Ok(()) => result_text.set(if add { "Member added!" } else { "Member removed!" }.to_string()),
+            Err(e) => result_text.set(format!("Membership change failed: {e}")),
+        }
+    };
+
+    let handle_members = move |_| async move {
+        let req = api::features::conversations::members::ListMembersRequest {
+            token: token(),
+            conversation_id: conversation_id(),
+        };
+        match api::list_members(req).await {
+            Ok(members) => {
+                let mut out = format!("{} members:\n\n", members.len());
+                for m in &members {
+                    out.push_str(&format!("{} ({}) {}\n", m.username, m.user_id, m.role));
+                }
+                result_text.set(out);
+            }
+            Err(e) => result_text.set(format!("Members failed: {e}")),
+        }
+    };
+
+    let handle_rename = move |_| async move {
+        let req = api::features::conversations::rename::RenameConversationRequest {
+            token: token(),
+            conversation_id: conversation_id(),
+            name: group_name(),
+        };
+        match api::rename_conversation(req).await {
+            Ok(()) => result_text.set("Group renamed!".to_string()),
+            Err(e) => result_text.set(format!("Rename failed: {e}")),
+        }
+    };
+
+    let handle_leave = move |_| async move {
+        let req = api::features::conversations::leave::LeaveConversationRequest {
+            token: token(),
+            conversation_id: conversation_id(),
+        };
+        match api::leave_conversation(req).await {
+            Ok(()) => {
+                conversation_id.set(String::new());
+                result_text.set("Left the group".to_string());
+            }
+            Err(e) => result_text.set(format!("Leave failed: {e}")),
+        }
+    };
+
     rsx! {
         div {
             style: "max-width: 500px; margin: 2rem auto; padding: 1rem; border: 1px solid #ccc; border-radius: 8px;",
@@ -157,12 +339,24 @@ pub fn AuthTest() -> Element {
                 value: "{recipient_id}",
                 oninput: move |e| recipient_id.set(e.value()),
             }
+            input {
+                style: "display: block; width: 100%; margin: 0.5rem 0; padding: 0.5rem;",
+                placeholder: "Conversation ID (overrides recipient, auto-filled on send)",
+                value: "{conversation_id}",
+                oninput: move |e| conversation_id.set(e.value()),
+            }
             input {
                 style: "display: block; width: 100%; margin: 0.5rem 0; padding: 0.5rem;",
                 placeholder: "Message content",
                 value: "{message_content}",
-                oninput: move |e| message_content.set(e.value()),
+                oninput: move |e| {
+                    if !e.value().is_empty() && !conversation_id().is_empty() {
+                        ws.notify_typing(&conversation_id());
+                    }
+                    message_content.set(e.value());
+                },
             }
+            TypingIndicator { ws, conversation_id: conversation_id() }
             input {
                 style: "display: block; width: 100%; margin: 0.5rem 0; padding: 0.5rem;",
                 placeholder: "Message ID (for update/delete, auto-filled on send)",
@@ -171,10 +365,33 @@ pub fn AuthTest() -> Element {
             }
             div {
                 style: "display: flex; gap: 0.5rem; flex-wrap: wrap; margin-top: 0.5rem;",
-                button { onclick: handle_send, "Send" }
+                button { onclick: move |_| handle_send(false), "Send" }
+                button { onclick: move |_| handle_send(true), "Reply" }
+                button { onclick: handle_thread, "Thread" }
                 button { onclick: handle_list, "List" }
+                button { onclick: handle_inbox, "Inbox" }
+                button { onclick: handle_mark_read, "Mark Read" }
                 button { onclick: handle_update, "Update" }
-                button { onclick: handle_delete, "Delete" }
+                button { onclick: handle_history, "History" }
+                button { onclick: move |_| handle_delete(false), "Delete for me" }
+                button { onclick: move |_| handle_delete(true), "Delete for everyone" }
+            }
+
+            h4 { "Groups" }
+            input {
+                style: "display: block; width: 100%; margin: 0.5rem 0; padding: 0.5rem;",
+                placeholder: "Group name",
+                value: "{group_name}",
+                oninput: move |e| group_name.set(e.value()),
+            }
+            div {
+                style: "display: flex; gap: 0.5rem; flex-wrap: wrap; margin-top: 0.5rem;",
+                button { onclick: handle_create_group, "Create Group" }
+                button { onclick: move |_| handle_membership(true), "Add Member" }
+                button { onclick: move |_| handle_membership(false), "Remove Member" }
+                button { onclick: handle_members, "Members" }
+                button { onclick: handle_rename, "Rename" }
+                button { onclick: handle_leave, "Leave" }
             }
 
             if !result_text().is_empty() {
@@ -192,22 +409,69 @@ pub fn AuthTest() -> Element {
             }
             div {
                 style: "max-height: 300px; overflow-y: auto; padding: 0.5rem; background: #fafafa; border: 1px solid #eee; border-radius: 4px;",
-                if ws_messages().is_empty() {
+                if ws.messages.read().is_empty() {
                     p { style: "color: #aaa;", "No real-time messages yet. Send one from another browser!" }
                 }
-                for msg in ws_messages().iter().rev() {
+                for msg in ws.messages.read().iter().rev() {
                     div {
                         style: "padding: 0.25rem 0; border-bottom: 1px solid #eee; font-size: 0.85rem;",
                         strong { "{msg.sender_id}" }
-                        " -> "
-                        strong { "{msg.recipient_id}" }
+                        if let Some(recipient) = &msg.recipient_id {
+                            " -> "
+                            strong { "{recipient}" }
+                        } else {
+                            " in "
+                            strong { "{msg.conversation_id}" }
+                        }
                         br {}
-                        "{msg.content}"
+                        if let Some(parent) = &msg.reply_to {
+                            blockquote {
+                                style: "margin: 0.25rem 0; padding-left: 0.5rem; border-left: 3px solid #ccc; color: #666;",
+                                match &parent.content {
+                                    Some(content) if parent.edited => rsx! { "{content} (edited)" },
+                                    Some(content) => rsx! { "{content}" },
+                                    None => rsx! { i { "Original message deleted" } },
+                                }
+                            }
+                        }
+                        if msg.deleted_at.is_some() {
+                            i { style: "color: #888;", "Message deleted" }
+                        } else {
+                            "{msg.content}"
+                        }
+                        if msg.edited_at.is_some() && msg.deleted_at.is_none() {
+                            small { style: "color: #888; margin-left: 0.25rem;", "(edited)" }
+                        }
                         br {}
                         small { style: "color: #888;", "{msg.created_at}" }
+                        if msg.reply_count > 0 {
+                            small { style: "color: #6d85c6; margin-left: 0.5rem;", "{msg.reply_count} replies" }
+                        }
+                        if msg.read {
+                            small { style: "color: #4a4; margin-left: 0.5rem;", "Seen" }
+                        } else if msg.delivered {
+                            small { style: "color: #888; margin-left: 0.5rem;", "Delivered" }
+                        }
+                        if msg.deleted_at.is_none() {
+                            ReactionBar {
+                                reactions: msg.reactions.clone(),
+                                on_react: {
+                                    let id = msg.id.clone();
+                                    move |emoji| handle_reaction(id.clone(), emoji, true)
+                                },
+                                on_unreact: {
+                                    let id = msg.id.clone();
+                                    move |emoji| handle_reaction(id.clone(), emoji, false)
+                                },
+                            }
+                        }
                     }
                 }
             }
         }
     }
 }
+
+fn non_empty(value: String) -> Option<String> {
+    (!value.is_empty()).then_some(value)
+}
@@ -0,0 +1,93 @@
+use dioxus::prelude::*;
+
+use api::features::invites::InviteTarget;
+
+use crate::use_session;
+
+const REIGNS_CSS: Asset = asset!("/assets/styling/reigns.css");
+
+/// Landing page for an invite link: shows what the invite leads to and joins
+/// it, asking signed-out visitors to log in first. `on_joined` receives the
+/// joined target so the platform router can open it.
+#[component]
+pub fn InviteView(code: ReadSignal<String>, on_joined: EventHandler<InviteTarget>) -> Element {
+    let mut token = use_session().token;
+    let mut email = use_signal(String::new);
+    let mut password = use_signal(String::new);
+    let mut error = use_signal(|| None::<String>);
+
+    let preview = use_resource(move || async move {
+        api::preview_invite(api::features::invites::preview::InvitePreviewRequest { code: code() }).await
+    });
+
+    let redeem = move || async move {
+        let req = api::features::invites::revoke::InviteCodeRequest {
+            token: token(),
+            code: code(),
+        };
+        match api::redeem_invite(req).await {
+            Ok(target) => on_joined.call(target),
+            Err(e) => error.set(Some(format!("Couldn't join: {e}"))),
+        }
+    };
+
+    let handle_login_and_join = move |_| async move {
+        let req = api::features::users::login::LoginRequest {
+            email: email(),
+            password: password(),
+        };
+        match api::login(req).await {
+            Ok(tokens) => {
+                token.set(tokens.access_token);
+                redeem().await;
+            }
+            Err(e) => error.set(Some(format!("Login failed: {e}"))),
+        }
+    };
+
+    rsx! {
+        document::Link { rel: "stylesheet", href: REIGNS_CSS }
+
+        div {
+            class: "reign-list",
+            match &*preview.read() {
+                Some(Ok(invite)) => rsx! {
+                    p { class: "reign-muted", "You've been invited to join" }
+                    h2 {
+                        "{invite.name}"
+                        if let Some(channel) = &invite.channel_name {
+                            span { class: "reign-muted", " # {channel}" }
+                        }
+                    }
+                    p { class: "reign-muted", "{invite.member_count} members" }
+
+                    if token().is_empty() {
+                        div {
+                            class: "reign-form",
+                            input {
+                                placeholder: "Email",
+                                value: "{email}",
+                                oninput: move |e| email.set(e.value()),
+                            }
+                            input {
+                                r#type: "password",
+                                placeholder: "Password",
+                                value: "{password}",
+                                oninput: move |e| password.set(e.value()),
+                            }
+                            button { onclick: handle_login_and_join, "Log in and join" }
+                        }
+                    } else {
+                        button { class: "reign-entry", onclick: move |_| redeem(), "Join" }
+                    }
+                },
+                Some(Err(e)) => rsx! { p { class: "reign-error", "{e}" } },
+                None => rsx! { p { class: "reign-muted", "Loading…" } },
+            }
+
+            if let Some(e) = error() {
+                p { class: "reign-error", "{e}" }
+            }
+        }
+    }
+}
@@ -12,5 +12,29 @@ pub use echo::Echo;
 mod auth_test;
 pub use auth_test::AuthTest;
 
+mod reaction_bar;
+pub use reaction_bar::ReactionBar;
+
+mod typing_indicator;
+pub use typing_indicator::TypingIndicator;
+
+mod session;
+pub use session::{use_session, use_session_provider, Session};
+
+mod reign_list;
+pub use reign_list::ReignList;
+
+mod reign_view;
+pub use reign_view::ReignView;
+
+mod invite_view;
+pub use invite_view::InviteView;
+
 mod use_websocket;
-pub use use_websocket::use_websocket;
+pub use use_websocket::{use_websocket, WsHandle};
+
+mod search_panel;
+pub use search_panel::SearchPanel;
+
+mod markdown;
+pub use markdown::Markdown;
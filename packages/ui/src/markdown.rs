@@ -0,0 +1,104 @@
+use dioxus::prelude::*;
+
+use api::markdown::{parse, Block, Inline};
+
+const MARKDOWN_CSS: Asset = asset!("/assets/styling/markdown.css");
+
+/// Message content rendered from its Markdown.
+///
+/// Everything goes through [`api::markdown::parse`] and is emitted as
+/// elements and text nodes, never as raw HTML, so markup in a message can't
+/// inject anything the parser doesn't produce. Links open in a new tab
+/// without referrer or opener access.
+#[component]
+pub fn Markdown(content: String) -> Element {
+    let blocks = parse(&content);
+
+    rsx! {
+        document::Link { rel: "stylesheet", href: MARKDOWN_CSS }
+
+        div {
+            class: "markdown",
+            for block in blocks {
+                {render_block(block)}
+            }
+        }
+    }
+}
+
+fn render_block(block: Block) -> Element {
+    match block {
+        Block::Paragraph(inlines) => rsx! {
+            p {
+                for inline in inlines {
+                    {render_inline(inline)}
+                }
+            }
+        },
+        Block::CodeBlock { language, code } => rsx! {
+            pre {
+                code {
+                    class: language.map(|l| format!("language-{l}")),
+                    "{code}"
+                }
+            }
+        },
+        Block::Quote(blocks) => rsx! {
+            blockquote {
+                for block in blocks {
+                    {render_block(block)}
+                }
+            }
+        },
+    }
+}
+
+fn render_inline(inline: Inline) -> Element {
+    match inline {
+        Inline::Text(text) => rsx! { "{text}" },
+        Inline::Bold(inner) => rsx! {
+            strong {
+                for inline in inner {
+                    {render_inline(inline)}
+                }
+            }
+        },
+        Inline::Italic(inner) => rsx! {
+            em {
+                for inline in inner {
+                    {render_inline(inline)}
+                }
+            }
+        },
+        Inline::Code(code) => rsx! { code { "{code}" } },
+        Inline::Spoiler(inner) => rsx! { Spoiler { inner } },
+        Inline::Link { url, label } => rsx! {
+            a {
+                href: "{url}",
+                target: "_blank",
+                rel: "noopener noreferrer nofollow",
+                for inline in label {
+                    {render_inline(inline)}
+                }
+            }
+        },
+        Inline::LineBreak => rsx! { br {} },
+    }
+}
+
+/// Hidden text that stays blurred until clicked.
+#[component]
+fn Spoiler(inner: Vec<Inline>) -> Element {
+    let mut revealed = use_signal(|| false);
+
+    rsx! {
+        span {
+            class: if revealed() { "markdown-spoiler revealed" } else { "markdown-spoiler" },
+            title: if !revealed() { "Click to reveal" },
+            onclick: move |_| revealed.set(true),
+            for inline in inner.clone() {
+                {render_inline(inline)}
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use dioxus::prelude::*;
+
+use api::features::reactions::{validate_emoji, ReactionCount};
+
+const REACTION_BAR_CSS: Asset = asset!("/assets/styling/reaction_bar.css");
+
+/// Emoji offered in the picker before the user types their own.
+const QUICK_REACTIONS: [&str; 6] = ["👍", "❤️", "😂", "😮", "😢", "🎉"];
+
+/// Reaction chips for one message plus a picker for adding new ones.
+/// Clicking a chip toggles the caller's reaction with that emoji.
+#[component]
+pub fn ReactionBar(
+    reactions: Vec<ReactionCount>,
+    on_react: EventHandler<String>,
+    on_unreact: EventHandler<String>,
+) -> Element {
+    let mut picker_open = use_signal(|| false);
+    let mut custom = use_signal(String::new);
+    let mut error = use_signal(|| None::<&'static str>);
+
+    let mut submit_custom = move || {
+        let emoji = custom().trim().to_string();
+        match validate_emoji(&emoji) {
+            Ok(()) => {
+                on_react.call(emoji);
+                custom.set(String::new());
+                error.set(None);
+                picker_open.set(false);
+            }
+            Err(e) => error.set(Some(e)),
+        }
+    };
+
+    rsx! {
+        document::Link { rel: "stylesheet", href: REACTION_BAR_CSS }
+
+        div {
+            class: "reaction-bar",
+            for reaction in reactions {
+                button {
+                    key: "{reaction.emoji}",
+                    class: if reaction.reacted { "reaction-chip reacted" } else { "reaction-chip" },
+                    onclick: {
+                        let emoji = reaction.emoji.clone();
+                        move |_| {
+                            if reaction.reacted {
+                                on_unreact.call(emoji.clone());
+                            } else {
+                                on_react.call(emoji.clone());
+                            }
+                        }
+                    },
+                    "{reaction.emoji} {reaction.count}"
+                }
+            }
+            button {
+                class: "reaction-chip reaction-add",
+                onclick: move |_| picker_open.toggle(),
+                "+"
+            }
+        }
+
+        if picker_open() {
+            div {
+                class: "reaction-picker",
+                for emoji in QUICK_REACTIONS {
+                    button {
+                        class: "reaction-chip",
+                        onclick: move |_| {
+                            on_react.call(emoji.to_string());
+                            picker_open.set(false);
+                        },
+                        "{emoji}"
+                    }
+                }
+                input {
+                    placeholder: "Emoji or :shortcode:",
+                    value: "{custom}",
+                    oninput: move |e| custom.set(e.value()),
+                    onkeydown: move |e| {
+                        if e.key() == Key::Enter {
+                            submit_custom();
+                        }
+                    },
+                }
+                if let Some(err) = error() {
+                    small { class: "reaction-error", "{err}" }
+                }
+            }
+        }
+    }
+}
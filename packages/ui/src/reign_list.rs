@@ -0,0 +1,106 @@
+use dioxus::prelude::*;
+
+use crate::use_session;
+
+const REIGNS_CSS: Asset = asset!("/assets/styling/reigns.css");
+
+/// The Reigns the signed-in user has joined, plus forms to create or join one.
+/// `on_open` receives the id of the Reign to navigate to.
+#[component]
+pub fn ReignList(on_open: EventHandler<String>) -> Element {
+    let token = use_session().token;
+    let mut new_name = use_signal(String::new);
+    let mut join_id = use_signal(String::new);
+    let mut error = use_signal(|| None::<String>);
+
+    let mut reigns = use_resource(move || async move {
+        let token = token();
+        if token.is_empty() {
+            return Ok(Vec::new());
+        }
+        api::list_reigns(api::features::reigns::list::ListReignsRequest { token }).await
+    });
+
+    let handle_create = move |_| async move {
+        let req = api::features::reigns::create::CreateReignRequest {
+            token: token(),
+            name: new_name(),
+        };
+        match api::create_reign(req).await {
+            Ok(reign) => {
+                new_name.set(String::new());
+                on_open.call(reign.id);
+            }
+            Err(e) => error.set(Some(format!("Create failed: {e}"))),
+        }
+    };
+
+    let handle_join = move |_| async move {
+        let req = api::features::reigns::join::ReignRequest {
+            token: token(),
+            reign_id: join_id().trim().to_string(),
+        };
+        match api::join_reign(req).await {
+            Ok(reign) => {
+                join_id.set(String::new());
+                reigns.restart();
+                on_open.call(reign.id);
+            }
+            Err(e) => error.set(Some(format!("Join failed: {e}"))),
+        }
+    };
+
+    rsx! {
+        document::Link { rel: "stylesheet", href: REIGNS_CSS }
+
+        div {
+            class: "reign-list",
+            h2 { "Reigns" }
+
+            if token().is_empty() {
+                p { class: "reign-muted", "Log in on the Home page to see your Reigns." }
+            } else {
+                match &*reigns.read() {
+                    Some(Ok(list)) if list.is_empty() => rsx! {
+                        p { class: "reign-muted", "You haven't joined any Reigns yet." }
+                    },
+                    Some(Ok(list)) => rsx! {
+                        for reign in list.iter().cloned() {
+                            button {
+                                class: "reign-entry",
+                                onclick: move |_| on_open.call(reign.id.clone()),
+                                strong { "{reign.name}" }
+                                span { class: "reign-muted", " · {reign.member_count} members · {reign.role}" }
+                            }
+                        }
+                    },
+                    Some(Err(e)) => rsx! { p { class: "reign-error", "Couldn't load Reigns: {e}" } },
+                    None => rsx! { p { class: "reign-muted", "Loading…" } },
+                }
+
+                div {
+                    class: "reign-form",
+                    input {
+                        placeholder: "New Reign name",
+                        value: "{new_name}",
+                        oninput: move |e| new_name.set(e.value()),
+                    }
+                    button { onclick: handle_create, "Create" }
+                }
+                div {
+                    class: "reign-form",
+                    input {
+                        placeholder: "Reign ID to join",
+                        value: "{join_id}",
+                        oninput: move |e| join_id.set(e.value()),
+                    }
+                    button { onclick: handle_join, "Join" }
+                }
+            }
+
+            if let Some(e) = error() {
+                p { class: "reign-error", "{e}" }
+            }
+        }
+    }
+}
@@ -0,0 +1,613 @@
+use dioxus::prelude::*;
+
+use api::content::normalize_content;
+use api::events::ClientCommand;
+use api::features::channels::Channel;
+use api::features::mentions::trailing_mention;
+use api::features::messages::rate_limit::SendError;
+use api::features::messages::create::MessageResponse;
+use api::permissions::Permissions;
+
+use crate::use_websocket::sleep_ms;
+use crate::{use_session, use_websocket, Markdown, ReactionBar, TypingIndicator, WsHandle};
+
+const REIGNS_CSS: Asset = asset!("/assets/styling/reigns.css");
+
+/// How long typing must pause before the draft is saved.
+const DRAFT_SAVE_DELAY_MS: u32 = 800;
+/// Saves refused because the stored draft moved on are retried over its
+/// newer revision at most this many times.
+const DRAFT_SAVE_ATTEMPTS: usize = 3;
+
+/// A Reign's channel sidebar next to the selected channel's history and composer.
+///
+/// Navigation is left to the platform router: `on_open_channel` receives a
+/// channel id, and `on_left` fires after the user leaves or deletes the Reign.
+#[component]
+pub fn ReignView(
+    reign_id: ReadSignal<String>,
+    channel_id: ReadSignal<Option<String>>,
+    on_open_channel: EventHandler<String>,
+    on_left: EventHandler<()>,
+) -> Element {
+    let token = use_session().token;
+    let ws = use_websocket(token);
+    let mut new_channel = use_signal(String::new);
+    let mut invite_code = use_signal(|| None::<String>);
+    let mut error = use_signal(|| None::<String>);
+
+    let details = use_resource(move || async move {
+        // Refetch whenever the server says the channel list changed
+        let _ = (ws.reign_revision)();
+        let req = api::features::reigns::details::ReignDetailsRequest {
+            token: token(),
+            reign_id: reign_id(),
+        };
+        api::get_reign(req).await
+    });
+
+    let handle_create_channel = move |_| async move {
+        let req = api::features::channels::create::CreateChannelRequest {
+            token: token(),
+            reign_id: reign_id(),
+            name: new_channel(),
+            category_id: None,
+            topic: None,
+        };
+        match api::create_channel(req).await {
+            Ok(channel) => {
+                new_channel.set(String::new());
+                on_open_channel.call(channel.id);
+            }
+            Err(e) => error.set(Some(format!("Create channel failed: {e}"))),
+        }
+    };
+
+    // Invites land in the open channel when there is one
+    let handle_invite = move |_| async move {
+        let (target_type, target_id) = match channel_id() {
+            Some(id) => ("channel", id),
+            None => ("reign", reign_id()),
+        };
+        let req = api::features::invites::create::CreateInviteRequest {
+            token: token(),
+            target_type: target_type.to_string(),
+            target_id,
+            max_uses: None,
+            expires_in_secs: Some(7 * 24 * 60 * 60),
+        };
+        match api::create_invite(req).await {
+            Ok(invite) => invite_code.set(Some(invite.code)),
+            Err(e) => error.set(Some(format!("Invite failed: {e}"))),
+        }
+    };
+
+    let handle_leave = move |owner: bool| async move {
+        let req = api::features::reigns::join::ReignRequest {
+            token: token(),
+            reign_id: reign_id(),
+        };
+        let result = if owner {
+            api::delete_reign(req).await
+        } else {
+            api::leave_reign(req).await
+        };
+        match result {
+            Ok(()) => on_left.call(()),
+            Err(e) => error.set(Some(format!("Leave failed: {e}"))),
+        }
+    };
+
+    let details = details.read();
+    let details = match &*details {
+        Some(Ok(details)) => details,
+        Some(Err(e)) => {
+            return rsx! {
+                document::Link { rel: "stylesheet", href: REIGNS_CSS }
+                p { class: "reign-error", "Couldn't load this Reign: {e}" }
+            };
+        }
+        None => return rsx! { p { class: "reign-muted", "Loading…" } },
+    };
+
+    let can_manage = details.reign.permissions.contains(Permissions::MANAGE_CHANNELS);
+    let is_owner = details.reign.role == "owner";
+    let selected = channel_id().and_then(|id| details.channels.iter().find(|c| c.id == id).cloned());
+
+    // Uncategorized channels first, then each category in order
+    let mut groups: Vec<(Option<String>, Vec<Channel>)> =
+        vec![(None, details.channels.iter().filter(|c| c.category_id.is_none()).cloned().collect())];
+    for category in &details.categories {
+        let channels = details
+            .channels
+            .iter()
+            .filter(|c| c.category_id.as_ref() == Some(&category.id))
+            .cloned()
+            .collect();
+        groups.push((Some(category.name.clone()), channels));
+    }
+
+    rsx! {
+        document::Link { rel: "stylesheet", href: REIGNS_CSS }
+
+        div {
+            class: "reign-view",
+            nav {
+                class: "reign-sidebar",
+                h3 { "{details.reign.name}" }
+                for (heading, channels) in groups {
+                    if let Some(heading) = heading {
+                        div { class: "reign-category", "{heading}" }
+                    }
+                    for channel in channels {
+                        button {
+                            class: if selected.as_ref().is_some_and(|s| s.id == channel.id) { "reign-channel selected" } else { "reign-channel" },
+                            onclick: {
+                                let id = channel.id.clone();
+                                move |_| on_open_channel.call(id.clone())
+                            },
+                            "# {channel.name}"
+                            if channel.unread_count > 0 {
+                                span { class: "reign-unread", "{channel.unread_count}" }
+                            }
+                        }
+                    }
+                }
+                if can_manage {
+                    div {
+                        class: "reign-form",
+                        input {
+                            placeholder: "new-channel",
+                            value: "{new_channel}",
+                            oninput: move |e| new_channel.set(e.value()),
+                        }
+                        button { onclick: handle_create_channel, "Add" }
+                    }
+                }
+                button { class: "reign-channel", onclick: handle_invite, "Create invite link" }
+                if let Some(code) = invite_code() {
+                    small { class: "reign-muted", "Share /invite/{code} (valid for 7 days)" }
+                }
+                button {
+                    class: "reign-leave",
+                    onclick: move |_| handle_leave(is_owner),
+                    if is_owner { "Delete Reign" } else { "Leave Reign" }
+                }
+                if let Some(e) = error() {
+                    p { class: "reign-error", "{e}" }
+                }
+            }
+            main {
+                class: "reign-main",
+                match selected {
+                    Some(channel) => rsx! { ChannelPane { ws, channel } },
+                    None => rsx! { p { class: "reign-muted", "Select a channel." } },
+                }
+            }
+        }
+    }
+}
+
+/// History and composer for one channel. Live events from `ws` are merged
+/// over the fetched history.
+#[component]
+fn ChannelPane(ws: WsHandle, channel: ReadSignal<Channel>) -> Element {
+    let token = use_session().token;
+    let mut ws = ws;
+    let mut draft = use_signal(String::new);
+    let mut error = use_signal(|| None::<String>);
+    // The channel the composer text belongs to
+    let mut draft_channel = use_signal(|| None::<String>);
+    // Bumped on every local edit; a pending save only runs if it is unchanged
+    let mut draft_edits = use_signal(|| 0u64);
+    // Whether the composer holds edits the server hasn't stored yet
+    let mut draft_dirty = use_signal(|| false);
+    // The newest stored revision seen; saves are typed over it
+    let mut draft_revision = use_signal(|| 0i64);
+
+    // On switching channels, save what was typed in the last one and pick up
+    // the draft left for this one on any device
+    use_resource(move || async move {
+        let channel_id = channel().id;
+        if let Some(previous) = draft_channel.peek().clone().filter(|_| *draft_dirty.peek()) {
+            *draft_edits.write() += 1;
+            let content = draft.peek().clone();
+            let base_revision = *draft_revision.peek();
+            spawn(async move {
+                let _ = save_draft(token(), previous, content, base_revision).await;
+            });
+        }
+        draft_dirty.set(false);
+        draft_revision.set(0);
+        draft.set(String::new());
+        draft_channel.set(Some(channel_id.clone()));
+
+        let req = api::features::drafts::list::ListDraftsRequest { token: token() };
+        let saved = api::list_drafts(req).await.unwrap_or_default();
+        if let Some(saved) = saved.into_iter().find(|d| d.conversation_id == channel_id) {
+            if draft_channel.peek().as_ref() == Some(&channel_id) && saved.revision > *draft_revision.peek() {
+                draft_revision.set(saved.revision);
+                if !*draft_dirty.peek() {
+                    draft.set(saved.content);
+                }
+            }
+        }
+    });
+
+    // Follow newer drafts saved elsewhere. Local edits not stored yet are
+    // kept and saved over the newer revision, so the latest typing wins.
+    use_effect(move || {
+        let drafts = ws.drafts.read();
+        let Some(remote) = draft_channel().and_then(|id| drafts.get(&id)).cloned() else {
+            return;
+        };
+        if remote.revision <= *draft_revision.peek() {
+            return;
+        }
+        draft_revision.set(remote.revision);
+        if !*draft_dirty.peek() && *draft.peek() != remote.content {
+            draft.set(remote.content);
+        }
+    });
+
+    let mut schedule_draft_save = move || {
+        *draft_edits.write() += 1;
+        draft_dirty.set(true);
+        let edit = *draft_edits.peek();
+        let Some(channel_id) = draft_channel.peek().clone() else {
+            return;
+        };
+        spawn(async move {
+            sleep_ms(DRAFT_SAVE_DELAY_MS).await;
+            // The composer is never overwritten from here: a refused save
+            // means the stored draft moved on, and this edit is still newer
+            for _ in 0..DRAFT_SAVE_ATTEMPTS {
+                if *draft_edits.peek() != edit {
+                    return;
+                }
+                let content = draft.peek().clone();
+                let base_revision = *draft_revision.peek();
+                let Ok(saved) = save_draft(token(), channel_id.clone(), content.clone(), base_revision).await else {
+                    return;
+                };
+                if *draft_edits.peek() != edit {
+                    return;
+                }
+                if saved.revision > *draft_revision.peek() {
+                    draft_revision.set(saved.revision);
+                }
+                if saved.content == content {
+                    draft_dirty.set(false);
+                    return;
+                }
+            }
+        });
+    };
+
+    let history = use_resource(move || async move {
+        // Refetch when events missed while disconnected couldn't be redelivered
+        let _ = (ws.resync_revision)();
+        let req = api::features::messages::list::ListMessagesRequest {
+            token: token(),
+            conversation_id: Some(channel().id),
+            other_user_id: None,
+        };
+        api::list_messages(req).await.unwrap_or_default()
+    });
+
+    let pins = use_resource(move || async move {
+        // Refetch whenever a message here is pinned, unpinned or deleted
+        let _ = (ws.pins_revision)();
+        let req = api::features::pins::list::ListPinsRequest {
+            token: token(),
+            conversation_id: channel().id,
+        };
+        api::list_pins(req).await.unwrap_or_default()
+    });
+
+    let shown = use_memo(move || {
+        let channel_id = channel().id;
+        let live = ws.messages.read();
+        let history = history.read();
+        let history = history.as_deref().unwrap_or_default();
+
+        let mut shown: Vec<MessageResponse> = history
+            .iter()
+            .map(|m| live.iter().find(|l| l.id == m.id).unwrap_or(m).clone())
+            .collect();
+        shown.extend(
+            live.iter()
+                .filter(|l| l.conversation_id == channel_id && !history.iter().any(|m| m.id == l.id))
+                .cloned(),
+        );
+        shown
+    });
+
+    // Everything on screen counts as read
+    use_effect(move || {
+        if let Some(last) = shown.read().last() {
+            ws.send(&ClientCommand::MarkRead {
+                conversation_id: last.conversation_id.clone(),
+                message_id: last.id.clone(),
+            });
+        }
+    });
+
+    // Checked as the user types with the same rules the server applies
+    let draft_problem = use_memo(move || normalize_content(&draft()).err().map(|e| e.to_string()));
+
+    let handle_send = move |_| async move {
+        let content = match normalize_content(&draft()) {
+            Ok(content) if content.is_empty() => return,
+            Ok(content) => content,
+            Err(_) => return,
+        };
+        ws.stop_typing(&channel().id);
+        let req = api::features::messages::create::CreateMessageRequest {
+            token: token(),
+            conversation_id: Some(channel().id),
+            recipient_id: None,
+            content,
+            attachment_ids: Vec::new(),
+            reply_to_id: None,
+        };
+        match api::create_message(req).await {
+            Ok(_) => {
+                // Sending clears the stored draft; drop any save still pending
+                *draft_edits.write() += 1;
+                draft_dirty.set(false);
+                draft.set(String::new());
+                error.set(None);
+            }
+            Err(e) => match SendError::from_server_error(&e) {
+                // The draft stays in the composer to resend once allowed
+                Some(limited) => error.set(Some(limited.to_string())),
+                None => error.set(Some(format!("Send failed: {e}"))),
+            },
+        }
+    };
+
+    // Suggest members while an @mention is being typed
+    let suggestions = use_resource(move || async move {
+        let Some(prefix) = trailing_mention(&draft()).map(str::to_string) else {
+            return Vec::new();
+        };
+        let req = api::features::mentions::suggest::SuggestMentionsRequest {
+            token: token(),
+            conversation_id: channel().id,
+            prefix,
+        };
+        api::suggest_mentions(req).await.unwrap_or_default()
+    });
+
+    let mut complete_mention = move |username: String| {
+        let text = draft();
+        if let Some(partial) = trailing_mention(&text) {
+            let typed = &text[..text.len() - partial.len()];
+            draft.set(format!("{typed}{username} "));
+            schedule_draft_save();
+        }
+    };
+
+    let handle_reaction = move |message_id: String, emoji: String, add: bool| async move {
+        let req = api::features::reactions::add::ReactionRequest {
+            token: token(),
+            message_id,
+            emoji,
+        };
+        let result = if add {
+            api::add_reaction(req).await
+        } else {
+            api::remove_reaction(req).await
+        };
+        if let Err(e) = result {
+            error.set(Some(format!("Reaction failed: {e}")));
+        }
+    };
+
+    let handle_pin = move |message_id: String, pin: bool| async move {
+        let req = api::features::pins::pin::PinRequest {
+            token: token(),
+            message_id,
+        };
+        let result = if pin {
+            api::pin_message(req).await
+        } else {
+            api::unpin_message(req).await
+        };
+        if let Err(e) = result {
+            error.set(Some(format!("Pin failed: {e}")));
+        }
+    };
+
+    let channel = channel();
+    let can_pin = channel.permissions.contains(Permissions::MANAGE_MESSAGES);
+    let pinned = pins.read().clone().unwrap_or_default();
+
+    rsx! {
+        header {
+            class: "reign-channel-header",
+            strong { "# {channel.name}" }
+            if let Some(topic) = &channel.topic {
+                span { class: "reign-muted", " — {topic}" }
+            }
+        }
+        if !pinned.is_empty() {
+            details {
+                class: "reign-pins",
+                summary { "📌 {pinned.len()} pinned" }
+                for pin in pinned.iter().cloned() {
+                    div {
+                        key: "{pin.message.id}",
+                        class: "reign-pin",
+                        small { class: "reign-muted", "{pin.message.sender_id.chars().take(8).collect::<String>()} · {pin.message.created_at}" }
+                        Markdown { content: pin.message.content.clone() }
+                        if can_pin {
+                            button {
+                                onclick: move |_| handle_pin(pin.message.id.clone(), false),
+                                "Unpin"
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        div {
+            class: "reign-messages",
+            for msg in shown.read().iter().cloned() {
+                div {
+                    key: "{msg.id}",
+                    class: if ws.mentions.read().iter().any(|m| m.message_id == msg.id) { "reign-message mentioned" } else { "reign-message" },
+                    small { class: "reign-muted", "{msg.sender_id.chars().take(8).collect::<String>()} · {msg.created_at}" }
+                    br {}
+                    if msg.deleted_at.is_some() {
+                        i { class: "reign-muted", "Message deleted" }
+                    } else if msg.kind == "pin_notice" {
+                        i {
+                            class: "reign-muted",
+                            "📌 Pinned a message"
+                            if let Some(content) = msg.reply_to.as_ref().and_then(|p| p.content.clone()) {
+                                ": {content}"
+                            }
+                        }
+                    } else {
+                        if msg.forwarded_from.is_some() {
+                            small { class: "reign-muted", "↪ Forwarded" }
+                            br {}
+                        }
+                        Markdown { content: msg.content.clone() }
+                        if msg.edited_at.is_some() {
+                            small { class: "reign-muted", " (edited)" }
+                        }
+                        if msg.expires_at.is_some() {
+                            small { class: "reign-muted", title: "Disappears at {msg.expires_at.clone().unwrap_or_default()}", " ⏱" }
+                        }
+                        for attachment in msg.attachments.iter().cloned() {
+                            div {
+                                key: "{attachment.id}",
+                                class: "reign-attachment",
+                                if attachment.mime_type.starts_with("image/") && attachment.processing {
+                                    small { class: "reign-muted", "Processing {attachment.filename}…" }
+                                } else if attachment.failed {
+                                    small { class: "reign-muted", "{attachment.filename} couldn't be processed" }
+                                } else if attachment.mime_type.starts_with("image/") {
+                                    a { href: "{attachment.url}", target: "_blank",
+                                        img {
+                                            src: attachment.thumbnails.iter().find(|t| t.size >= 320).map_or(attachment.url.clone(), |t| t.url.clone()),
+                                            alt: "{attachment.filename}",
+                                            max_width: "320px",
+                                        }
+                                    }
+                                } else {
+                                    a { href: "{attachment.url}", target: "_blank", "{attachment.filename}" }
+                                    small { class: "reign-muted", " ({attachment.size_bytes / 1024} KiB)" }
+                                }
+                            }
+                        }
+                        for preview in msg.link_previews.iter().cloned() {
+                            a {
+                                key: "{preview.url}",
+                                class: "reign-link-preview",
+                                href: "{preview.url}",
+                                target: "_blank",
+                                rel: "noopener noreferrer nofollow",
+                                if let Some(image_url) = &preview.image_url {
+                                    img { src: "{image_url}", alt: "", loading: "lazy", referrerpolicy: "no-referrer" }
+                                }
+                                div {
+                                    if let Some(site_name) = &preview.site_name {
+                                        small { class: "reign-muted", "{site_name}" }
+                                    }
+                                    if let Some(title) = &preview.title {
+                                        strong { "{title}" }
+                                    }
+                                    if let Some(description) = &preview.description {
+                                        p { "{description}" }
+                                    }
+                                }
+                            }
+                        }
+                        if can_pin {
+                            button {
+                                class: "reign-pin-toggle",
+                                onclick: {
+                                    let id = msg.id.clone();
+                                    let is_pinned = pinned.iter().any(|p| p.message.id == msg.id);
+                                    move |_| handle_pin(id.clone(), !is_pinned)
+                                },
+                                if pinned.iter().any(|p| p.message.id == msg.id) { "Unpin" } else { "Pin" }
+                            }
+                        }
+                        ReactionBar {
+                            reactions: msg.reactions.clone(),
+                            on_react: {
+                                let id = msg.id.clone();
+                                move |emoji| handle_reaction(id.clone(), emoji, true)
+                            },
+                            on_unreact: {
+                                let id = msg.id.clone();
+                                move |emoji| handle_reaction(id.clone(), emoji, false)
+                            },
+                        }
+                    }
+                }
+            }
+        }
+        TypingIndicator { ws, conversation_id: channel.id.clone() }
+        if !channel.permissions.contains(Permissions::SEND_MESSAGES) {
+            p { class: "reign-muted", "You can't send messages in this channel." }
+        } else {
+            div {
+                class: "reign-form",
+                input {
+                    placeholder: "Message #{channel.name}",
+                    value: "{draft}",
+                    oninput: {
+                        let channel_id = channel.id.clone();
+                        move |e: FormEvent| {
+                            if !e.value().is_empty() {
+                                ws.notify_typing(&channel_id);
+                            }
+                            draft.set(e.value());
+                            schedule_draft_save();
+                        }
+                    },
+                }
+                button { disabled: draft_problem.read().is_some(), onclick: handle_send, "Send" }
+            }
+            if let Some(problem) = draft_problem() {
+                p { class: "reign-error", "{problem}" }
+            }
+            if let Some(suggestions) = suggestions.read().as_ref().filter(|s| !s.is_empty()) {
+                div {
+                    class: "reign-suggestions",
+                    for suggestion in suggestions.iter().cloned() {
+                        button {
+                            key: "{suggestion.user_id}",
+                            onclick: move |_| complete_mention(suggestion.username.clone()),
+                            "@{suggestion.username}"
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(e) = error() {
+            p { class: "reign-error", "{e}" }
+        }
+    }
+}
+
+async fn save_draft(
+    token: String,
+    conversation_id: String,
+    content: String,
+    base_revision: i64,
+) -> Result<api::features::drafts::Draft, ServerFnError> {
+    let req = api::features::drafts::save::SaveDraftRequest {
+        token,
+        conversation_id,
+        content,
+        base_revision,
+    };
+    api::save_draft(req).await
+}
@@ -0,0 +1,113 @@
+use dioxus::prelude::*;
+
+use api::features::messages::search::{SearchHit, SearchMessagesRequest};
+
+use crate::use_session;
+
+const SEARCH_CSS: Asset = asset!("/assets/styling/search.css");
+
+/// Message search with filter hints and "Load more" paging.
+/// `on_open` receives the hit the user clicked, to navigate to its conversation.
+#[component]
+pub fn SearchPanel(on_open: EventHandler<SearchHit>) -> Element {
+    let token = use_session().token;
+    let mut query = use_signal(String::new);
+    let mut hits = use_signal(Vec::<SearchHit>::new);
+    let mut next_cursor = use_signal(|| None::<String>);
+    let mut searched = use_signal(|| false);
+    let mut error = use_signal(|| None::<String>);
+
+    // `more` continues from the last page instead of starting over
+    let run_search = move |more: bool| async move {
+        let req = SearchMessagesRequest {
+            token: token(),
+            query: query(),
+            cursor: if more { next_cursor() } else { None },
+            limit: None,
+        };
+        match api::search_messages(req).await {
+            Ok(results) => {
+                if more {
+                    hits.write().extend(results.hits);
+                } else {
+                    hits.set(results.hits);
+                }
+                next_cursor.set(results.next_cursor);
+                searched.set(true);
+                error.set(None);
+            }
+            Err(e) => error.set(Some(format!("Search failed: {e}"))),
+        }
+    };
+
+    rsx! {
+        document::Link { rel: "stylesheet", href: SEARCH_CSS }
+
+        div {
+            class: "search-panel",
+            h2 { "Search messages" }
+
+            if token().is_empty() {
+                p { class: "search-muted", "Log in on the Home page to search your messages." }
+            } else {
+                form {
+                    class: "search-form",
+                    onsubmit: move |e| {
+                        e.prevent_default();
+                        run_search(false)
+                    },
+                    input {
+                        r#type: "search",
+                        placeholder: "Search…",
+                        value: "{query}",
+                        oninput: move |e| query.set(e.value()),
+                    }
+                    button { r#type: "submit", "Search" }
+                }
+                small { class: "search-muted",
+                    "Filters: from:username  in:conversation-id  before:2025-01-31  after:2025-01-01  has:attachment"
+                }
+
+                if let Some(e) = error() {
+                    p { class: "search-error", "{e}" }
+                }
+
+                if searched() && hits.read().is_empty() {
+                    p { class: "search-muted", "No messages found." }
+                }
+
+                for hit in hits.read().iter().cloned() {
+                    button {
+                        key: "{hit.message.id}",
+                        class: "search-hit",
+                        onclick: {
+                            let hit = hit.clone();
+                            move |_| on_open.call(hit.clone())
+                        },
+                        small {
+                            class: "search-muted",
+                            "{hit.conversation_name.as_deref().unwrap_or(\"Direct message\")} · {hit.message.created_at}"
+                        }
+                        p {
+                            class: "search-snippet",
+                            for part in hit.snippet.iter() {
+                                if part.highlighted {
+                                    mark { "{part.text}" }
+                                } else {
+                                    span { "{part.text}" }
+                                }
+                            }
+                        }
+                        if !hit.message.attachments.is_empty() {
+                            small { class: "search-muted", "📎 {hit.message.attachments.len()} attachment(s)" }
+                        }
+                    }
+                }
+
+                if next_cursor().is_some() {
+                    button { class: "search-more", onclick: move |_| run_search(true), "Load more" }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use dioxus::prelude::*;
+
+/// The signed-in user's state, shared by every screen through context.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Session {
+    /// JWT access token; empty while signed out.
+    pub token: Signal<String>,
+}
+
+/// Provide an empty [`Session`] to the whole app. Call once in the root component.
+pub fn use_session_provider() -> Session {
+    use_context_provider(|| Session {
+        token: Signal::new(String::new()),
+    })
+}
+
+/// The [`Session`] provided by [`use_session_provider`].
+pub fn use_session() -> Session {
+    use_context()
+}
@@ -0,0 +1,30 @@
+use dioxus::prelude::*;
+
+use crate::WsHandle;
+
+const TYPING_CSS: Asset = asset!("/assets/styling/typing.css");
+
+/// Shows "<name> is typing…" while someone else is typing in the conversation,
+/// or "N people are typing…" in a busy group. `name` is used when exactly one
+/// member is typing. Renders nothing otherwise; expiry is handled by [`WsHandle`].
+#[component]
+pub fn TypingIndicator(ws: WsHandle, conversation_id: String, name: Option<String>) -> Element {
+    let typists = ws.typing.read().keys().filter(|(id, _)| *id == conversation_id).count();
+    let label = match typists {
+        0 => return rsx! {},
+        1 => format!("{} is typing…", name.unwrap_or_else(|| "Someone".to_string())),
+        n => format!("{n} people are typing…"),
+    };
+
+    rsx! {
+        document::Link { rel: "stylesheet", href: TYPING_CSS }
+
+        div {
+            class: "typing-indicator",
+            span { class: "typing-dot" }
+            span { class: "typing-dot" }
+            span { class: "typing-dot" }
+            span { class: "typing-label", "{label}" }
+        }
+    }
+}
@@ -1,14 +1,316 @@
+use std::collections::HashMap;
+
 use dioxus::prelude::*;
 
+use api::events::{ClientCommand, MentionNotice};
+use api::features::conversations::mark_read::ReadReceipt;
+use api::features::drafts::Draft;
 use api::features::messages::create::MessageResponse;
 
+/// Delay before the first reconnect attempt; doubles up to [`RECONNECT_MAX_MS`].
+#[cfg(target_arch = "wasm32")]
+const RECONNECT_MIN_MS: u32 = 1_000;
+#[cfg(target_arch = "wasm32")]
+const RECONNECT_MAX_MS: u32 = 30_000;
+
+/// While typing continues, how often to re-send "started typing" so the
+/// other members' indicators don't expire.
+const TYPING_RESEND_MS: f64 = 3_000.0;
+
+/// Live state of the user's WebSocket connection, returned by [`use_websocket`].
+#[derive(Clone, Copy, PartialEq)]
+pub struct WsHandle {
+    /// Messages pushed since connecting, oldest first.
+    pub messages: Signal<Vec<MessageResponse>>,
+    /// Latest read receipt per `(conversation_id, reader_id)`, for showing "Seen".
+    pub receipts: Signal<HashMap<(String, String), ReadReceipt>>,
+    /// `(conversation_id, user_id)` of members currently typing, mapped to when
+    /// their indicator expires (ms).
+    pub typing: Signal<HashMap<(String, String), f64>>,
+    /// Bumped whenever a joined Reign's channels change; read it to refetch.
+    pub reign_revision: Signal<u64>,
+    /// Mentions of the user received since connecting, oldest first.
+    pub mentions: Signal<Vec<MentionNotice>>,
+    /// Bumped whenever a message is pinned, unpinned or deleted; read it to
+    /// refetch pinned messages.
+    pub pins_revision: Signal<u64>,
+    /// Latest draft per conversation saved from any of the user's devices.
+    pub drafts: Signal<HashMap<String, Draft>>,
+    /// Bumped when the server couldn't redeliver frames missed while
+    /// disconnected; read it to refetch history.
+    pub resync_revision: Signal<u64>,
+    outgoing: Signal<Option<UnboundedSender<String>>>,
+    /// Conversation and time of the last "started typing" we sent.
+    typing_sent: Signal<Option<(String, f64)>>,
+}
+
+impl WsHandle {
+    /// Send a command to the server. Dropped silently while disconnected.
+    pub fn send(&self, command: &ClientCommand) {
+        if let (Some(tx), Ok(json)) = (self.outgoing.peek().as_ref(), serde_json::to_string(command)) {
+            let _ = tx.unbounded_send(json);
+        }
+    }
+
+    /// Tell the other members of `conversation_id` we are typing. Safe to call
+    /// on every keystroke; frames are only sent every [`TYPING_RESEND_MS`].
+    pub fn notify_typing(&mut self, conversation_id: &str) {
+        let now = now_ms();
+        let recently_sent = self
+            .typing_sent
+            .peek()
+            .as_ref()
+            .is_some_and(|(id, at)| id == conversation_id && now - at < TYPING_RESEND_MS);
+        if recently_sent {
+            return;
+        }
+
+        self.typing_sent.set(Some((conversation_id.to_string(), now)));
+        self.send(&ClientCommand::Typing {
+            conversation_id: conversation_id.to_string(),
+            typing: true,
+        });
+    }
+
+    /// Tell `conversation_id` we stopped typing, e.g. after sending or clearing the composer.
+    pub fn stop_typing(&mut self, conversation_id: &str) {
+        let was_typing = self.typing_sent.peek().as_ref().is_some_and(|(id, _)| id == conversation_id);
+        if !was_typing {
+            return;
+        }
+
+        self.typing_sent.set(None);
+        self.send(&ClientCommand::Typing {
+            conversation_id: conversation_id.to_string(),
+            typing: false,
+        });
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn apply(&mut self, event: api::events::WsEvent) {
+        use api::events::WsEvent;
+        use api::features::messages::REPLY_PREVIEW_CHARS;
+        use api::markdown::to_plain_text;
+
+        match event {
+            WsEvent::MessageCreated(msg) => {
+                let mut messages = self.messages.write();
+                if let Some(parent_id) = msg.reply_to.as_ref().map(|p| &p.id) {
+                    if let Some(parent) = messages.iter_mut().find(|m| &m.id == parent_id) {
+                        parent.reply_count += 1;
+                    }
+                }
+                messages.push(msg);
+            }
+            WsEvent::MessageUpdated(msg) => {
+                let mut messages = self.messages.write();
+                // Refresh quotes of this message in its replies
+                for reply in messages.iter_mut() {
+                    if let Some(parent) = reply.reply_to.as_mut().filter(|p| p.id == msg.id) {
+                        parent.content = Some(to_plain_text(&msg.content).chars().take(REPLY_PREVIEW_CHARS).collect());
+                        parent.edited = msg.edited_at.is_some();
+                    }
+                }
+                // Reactions in the payload are from the editor's point of view; keep ours
+                if let Some(m) = messages.iter_mut().find(|m| m.id == msg.id) {
+                    let reactions = std::mem::take(&mut m.reactions);
+                    *m = msg;
+                    m.reactions = reactions;
+                }
+            }
+            WsEvent::MessageDeleted(deletion) if deletion.expired => {
+                *self.pins_revision.write() += 1;
+                let mut messages = self.messages.write();
+                for reply in messages.iter_mut() {
+                    if reply.reply_to.as_ref().is_some_and(|p| p.id == deletion.message_id) {
+                        reply.reply_to = None;
+                    }
+                }
+                let parent_id = messages
+                    .iter()
+                    .find(|m| m.id == deletion.message_id)
+                    .and_then(|m| m.reply_to.as_ref().map(|p| p.id.clone()));
+                if let Some(parent) = parent_id.and_then(|id| messages.iter_mut().find(|m| m.id == id)) {
+                    parent.reply_count = (parent.reply_count - 1).max(0);
+                }
+                messages.retain(|m| m.id != deletion.message_id);
+            }
+            WsEvent::MessageDeleted(deletion) if deletion.for_everyone => {
+                *self.pins_revision.write() += 1;
+                let mut messages = self.messages.write();
+                for reply in messages.iter_mut() {
+                    if let Some(parent) = reply.reply_to.as_mut().filter(|p| p.id == deletion.message_id) {
+                        parent.content = None;
+                        parent.deleted = true;
+                    }
+                }
+                let parent_id = messages
+                    .iter_mut()
+                    .find(|m| m.id == deletion.message_id)
+                    .and_then(|m| {
+                        m.content.clear();
+                        m.link_previews.clear();
+                        m.deleted_at = Some(deletion.deleted_at);
+                        m.reply_to.as_ref().map(|p| p.id.clone())
+                    });
+                if let Some(parent) = parent_id.and_then(|id| messages.iter_mut().find(|m| m.id == id)) {
+                    parent.reply_count = (parent.reply_count - 1).max(0);
+                }
+            }
+            WsEvent::MessageDeleted(deletion) => {
+                self.messages.write().retain(|m| m.id != deletion.message_id);
+            }
+            WsEvent::ReactionUpdated(update) => {
+                use api::features::reactions::ReactionCount;
+
+                let mut messages = self.messages.write();
+                let Some(m) = messages.iter_mut().find(|m| m.id == update.message_id) else {
+                    return;
+                };
+                match m.reactions.iter().position(|r| r.emoji == update.emoji) {
+                    Some(i) if update.count == 0 => {
+                        m.reactions.remove(i);
+                    }
+                    Some(i) => {
+                        m.reactions[i].count = update.count;
+                        m.reactions[i].reacted = update.reacted;
+                    }
+                    None if update.count > 0 => m.reactions.push(ReactionCount {
+                        emoji: update.emoji,
+                        count: update.count,
+                        reacted: update.reacted,
+                    }),
+                    None => {}
+                }
+            }
+            WsEvent::MessagesRead(receipt) => {
+                let mut messages = self.messages.write();
+                if let Some(pos) = messages.iter().position(|m| m.id == receipt.last_read_message_id) {
+                    for m in messages[..=pos].iter_mut() {
+                        if m.conversation_id == receipt.conversation_id && m.sender_id != receipt.reader_id {
+                            m.read = true;
+                        }
+                    }
+                }
+                let key = (receipt.conversation_id.clone(), receipt.reader_id.clone());
+                self.receipts.write().insert(key, receipt);
+            }
+            WsEvent::MessageDelivered(receipt) => {
+                if let Some(m) = self.messages.write().iter_mut().find(|m| m.id == receipt.message_id) {
+                    m.delivered = true;
+                }
+            }
+            WsEvent::Typing(indicator) if indicator.typing => {
+                let key = (indicator.conversation_id, indicator.user_id);
+                let expires_at = now_ms() + indicator.expires_in_ms as f64;
+                self.typing.write().insert(key.clone(), expires_at);
+
+                // Expire the indicator unless a newer frame pushed the deadline out
+                let mut typing = self.typing;
+                spawn(async move {
+                    gloo_timers::future::TimeoutFuture::new(indicator.expires_in_ms as u32).await;
+                    let expired = typing.peek().get(&key).is_some_and(|at| *at <= now_ms());
+                    if expired {
+                        typing.write().remove(&key);
+                    }
+                });
+            }
+            WsEvent::Typing(indicator) => {
+                self.typing.write().remove(&(indicator.conversation_id, indicator.user_id));
+            }
+            WsEvent::MembershipChanged(change) if !change.joined => {
+                self.typing.write().remove(&(change.conversation_id, change.user_id));
+            }
+            WsEvent::ReignUpdated(_) => {
+                *self.reign_revision.write() += 1;
+            }
+            WsEvent::AttachmentProcessed(update) => {
+                let mut messages = self.messages.write();
+                let Some(m) = messages.iter_mut().find(|m| update.message_id.as_ref() == Some(&m.id)) else {
+                    return;
+                };
+                if let Some(a) = m.attachments.iter_mut().find(|a| a.id == update.attachment.id) {
+                    *a = update.attachment;
+                }
+            }
+            WsEvent::Mentioned(notice) => {
+                self.mentions.write().push(notice);
+            }
+            WsEvent::PinsUpdated(_) => {
+                *self.pins_revision.write() += 1;
+            }
+            WsEvent::DraftUpdated(draft) => {
+                self.drafts.write().insert(draft.conversation_id.clone(), draft);
+            }
+            WsEvent::Resync => {
+                *self.resync_revision.write() += 1;
+                *self.reign_revision.write() += 1;
+                *self.pins_revision.write() += 1;
+            }
+            WsEvent::ConversationUpdated(_) | WsEvent::MembershipChanged(_) => {}
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn now_ms() -> f64 {
+    js_sys::Date::now()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn now_ms() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64() * 1000.0)
+        .unwrap_or_default()
+}
+
+/// This tab's id for the server's redelivery queue, kept in session storage
+/// so it survives reloads. Each tab acknowledges frames on its own.
+#[cfg(target_arch = "wasm32")]
+fn device_id() -> Option<String> {
+    const KEY: &str = "reigncloud.device_id";
+    let window = web_sys::window()?;
+    let storage = window.session_storage().ok()??;
+    if let Ok(Some(id)) = storage.get_item(KEY) {
+        return Some(id);
+    }
+    let id = window.crypto().ok()?.random_uuid();
+    let _ = storage.set_item(KEY, &id);
+    Some(id)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn sleep_ms(ms: u32) {
+    gloo_timers::future::TimeoutFuture::new(ms).await;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn sleep_ms(ms: u32) {
+    tokio::time::sleep(std::time::Duration::from_millis(ms.into())).await;
+}
+
 /// Hook that manages a WebSocket connection for real-time messages.
-/// Returns a signal containing incoming messages.
+/// Returns a handle exposing incoming state and a way to send commands.
 ///
 /// Pass the JWT access token to connect. When the token changes
-/// (e.g. on login), the connection is re-established.
-pub fn use_websocket(token: Signal<String>) -> Signal<Vec<MessageResponse>> {
-    let mut messages: Signal<Vec<MessageResponse>> = use_signal(Vec::new);
+/// (e.g. on login), the connection is re-established. Dropped connections
+/// are retried with exponential backoff, and the server replays any frames
+/// that were not acknowledged before the drop.
+#[cfg_attr(not(target_arch = "wasm32"), allow(unused_mut, unused_variables))]
+pub fn use_websocket(token: Signal<String>) -> WsHandle {
+    let mut handle = WsHandle {
+        messages: use_signal(Vec::new),
+        receipts: use_signal(HashMap::new),
+        typing: use_signal(HashMap::new),
+        reign_revision: use_signal(|| 0),
+        mentions: use_signal(Vec::new),
+        pins_revision: use_signal(|| 0),
+        drafts: use_signal(HashMap::new),
+        resync_revision: use_signal(|| 0),
+        outgoing: use_signal(|| None),
+        typing_sent: use_signal(|| None),
+    };
 
     #[cfg(target_arch = "wasm32")]
     {
@@ -19,53 +321,95 @@ pub fn use_websocket(token: Signal<String>) -> Signal<Vec<MessageResponse>> {
             }
 
             spawn(async move {
+                use api::events::ServerFrame;
+                use futures_util::{SinkExt, StreamExt};
                 use gloo_net::websocket::{futures::WebSocket, Message};
-                use futures_util::StreamExt;
 
                 // Build ws:// or wss:// URL relative to current host
                 let location = web_sys::window().unwrap().location();
                 let protocol = location.protocol().unwrap_or_default();
                 let host = location.host().unwrap_or_default();
                 let ws_protocol = if protocol == "https:" { "wss:" } else { "ws:" };
-                let url = format!("{ws_protocol}//{host}/ws?token={tok}");
-
-                let ws = match WebSocket::open(&url) {
-                    Ok(ws) => ws,
-                    Err(e) => {
-                        web_sys::console::log_1(
-                            &format!("WebSocket connect failed: {e:?}").into(),
-                        );
-                        return;
-                    }
-                };
-
-                let (_write, mut read) = ws.split();
+                let mut base_url = format!("{ws_protocol}//{host}/ws?token={tok}");
+                if let Some(device_id) = device_id() {
+                    base_url.push_str(&format!("&device_id={device_id}"));
+                }
 
-                web_sys::console::log_1(&"WebSocket connected".into());
+                // Highest frame sequence applied so far; redelivered frames at or
+                // below it are acknowledged again but not re-applied.
+                let mut last_seq = 0u64;
+                let mut backoff_ms = RECONNECT_MIN_MS;
 
-                while let Some(msg) = read.next().await {
-                    match msg {
-                        Ok(Message::Text(text)) => {
-                            if let Ok(msg_response) =
-                                serde_json::from_str::<MessageResponse>(&text)
-                            {
-                                messages.write().push(msg_response);
-                            }
-                        }
-                        Ok(Message::Bytes(_)) => {}
+                // Reconnect until the token changes (a new task takes over then)
+                while *token.peek() == tok {
+                    // Lets the server tell whether frames since then were lost
+                    let url = match last_seq {
+                        0 => base_url.clone(),
+                        seq => format!("{base_url}&last_seq={seq}"),
+                    };
+                    let ws = match WebSocket::open(&url) {
+                        Ok(ws) => ws,
                         Err(e) => {
                             web_sys::console::log_1(
-                                &format!("WebSocket error: {e:?}").into(),
+                                &format!("WebSocket connect failed: {e:?}").into(),
                             );
-                            break;
+                            return;
+                        }
+                    };
+
+                    let (mut write, mut read) = ws.split();
+
+                    // Forward queued commands to the socket
+                    let (tx, mut rx) = futures_channel::mpsc::unbounded::<String>();
+                    handle.outgoing.set(Some(tx));
+                    spawn(async move {
+                        while let Some(text) = rx.next().await {
+                            if write.send(Message::Text(text)).await.is_err() {
+                                break;
+                            }
+                        }
+                    });
+
+                    web_sys::console::log_1(&"WebSocket connected".into());
+
+                    while let Some(msg) = read.next().await {
+                        match msg {
+                            Ok(Message::Text(text)) => match serde_json::from_str::<ServerFrame>(&text) {
+                                Ok(ServerFrame { seq: None, event }) => handle.apply(event),
+                                Ok(ServerFrame { seq: Some(seq), event }) => {
+                                    backoff_ms = RECONNECT_MIN_MS;
+                                    if seq > last_seq {
+                                        last_seq = seq;
+                                        handle.apply(event);
+                                    }
+                                    handle.send(&ClientCommand::Ack { seq });
+                                }
+                                Err(e) => {
+                                    web_sys::console::log_1(
+                                        &format!("Unrecognized WebSocket frame: {e}").into(),
+                                    );
+                                }
+                            },
+                            Ok(Message::Bytes(_)) => {}
+                            Err(e) => {
+                                web_sys::console::log_1(
+                                    &format!("WebSocket error: {e:?}").into(),
+                                );
+                                break;
+                            }
                         }
                     }
-                }
 
-                web_sys::console::log_1(&"WebSocket disconnected".into());
+                    handle.outgoing.set(None);
+                    web_sys::console::log_1(
+                        &format!("WebSocket disconnected, retrying in {backoff_ms}ms").into(),
+                    );
+                    gloo_timers::future::TimeoutFuture::new(backoff_ms).await;
+                    backoff_ms = (backoff_ms * 2).min(RECONNECT_MAX_MS);
+                }
             });
         });
     }
 
-    messages
+    handle
 }
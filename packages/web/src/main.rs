@@ -1,7 +1,7 @@
 use dioxus::prelude::*;
 
 use ui::Navbar;
-use views::{Blog, Home};
+use views::{Blog, Home, Invite, Reign, ReignChannel, Reigns, Search};
 
 mod views;
 
@@ -13,6 +13,16 @@ enum Route {
     Home {},
     #[route("/blog/:id")]
     Blog { id: i32 },
+    #[route("/reigns")]
+    Reigns {},
+    #[route("/reigns/:reign_id")]
+    Reign { reign_id: String },
+    #[route("/reigns/:reign_id/:channel_id")]
+    ReignChannel { reign_id: String, channel_id: String },
+    #[route("/invite/:code")]
+    Invite { code: String },
+    #[route("/search")]
+    Search {},
 }
 
 const FAVICON: Asset = asset!("/assets/favicon.ico");
@@ -20,12 +30,16 @@ const MAIN_CSS: Asset = asset!("/assets/main.css");
 
 #[cfg(feature = "server")]
 fn main() {
-    use axum::routing::get;
+    use axum::routing::{get, post};
     use dioxus_server::{DioxusRouterExt, ServeConfig};
 
     dioxus_server::serve(|| async {
+        api::jobs::spawn_background_jobs();
+
         let router = axum::Router::new()
             .route("/ws", get(api::ws::ws_handler))
+            .route("/api/attachments/upload", post(api::features::attachments::upload::upload_handler))
+            .route("/api/attachments/{id}", get(api::features::attachments::download::download_handler))
             .serve_dioxus_application(ServeConfig::new(), App);
         Ok(router)
     });
@@ -39,6 +53,7 @@ fn main() {
 #[component]
 fn App() -> Element {
     // Build cool things ✌️
+    ui::use_session_provider();
 
     rsx! {
         // Global app resources
@@ -63,6 +78,14 @@ fn WebNavbar() -> Element {
                 to: Route::Blog { id: 1 },
                 "Blog"
             }
+            Link {
+                to: Route::Reigns {},
+                "Reigns"
+            }
+            Link {
+                to: Route::Search {},
+                "Search"
+            }
         }
 
         Outlet::<Route> {}
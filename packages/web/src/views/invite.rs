@@ -0,0 +1,21 @@
+use crate::Route;
+use dioxus::prelude::*;
+use ui::InviteView;
+
+#[component]
+pub fn Invite(code: String) -> Element {
+    rsx! {
+        InviteView {
+            code,
+            on_joined: move |target: api::features::invites::InviteTarget| {
+                let route = match (target.reign_id, target.conversation_id) {
+                    (Some(reign_id), Some(channel_id)) => Route::ReignChannel { reign_id, channel_id },
+                    (Some(reign_id), None) => Route::Reign { reign_id },
+                    // Groups live in the inbox on the home page
+                    (None, _) => Route::Home {},
+                };
+                navigator().push(route);
+            },
+        }
+    }
+}
@@ -3,3 +3,12 @@ pub use home::Home;
 
 mod blog;
 pub use blog::Blog;
+
+mod reigns;
+pub use reigns::{Reign, ReignChannel, Reigns};
+
+mod invite;
+pub use invite::Invite;
+
+mod search;
+pub use search::Search;
@@ -0,0 +1,19 @@
+use crate::Route;
+use dioxus::prelude::*;
+use ui::SearchPanel;
+
+#[component]
+pub fn Search() -> Element {
+    rsx! {
+        SearchPanel {
+            on_open: move |hit: api::features::messages::search::SearchHit| {
+                let route = match hit.reign_id {
+                    Some(reign_id) => Route::ReignChannel { reign_id, channel_id: hit.message.conversation_id },
+                    // Direct and group conversations live in the inbox on the home page
+                    None => Route::Home {},
+                };
+                navigator().push(route);
+            },
+        }
+    }
+}